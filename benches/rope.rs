@@ -0,0 +1,119 @@
+use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
+use rawdeo::rope::{Rope, SplitStrategy};
+
+const EDITS: usize = 10_000;
+
+// About 10 MB of 53-char lines.
+fn large_text() -> String {
+    (0..190_000).map(|i| format!("{:>8} the quick brown fox jumps over the lazy dog\n", i)).collect()
+}
+
+// xorshift64 so every run edits the same places.
+fn indices(count: usize, len: usize) -> Vec<usize> {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % len as u64) as usize
+        })
+        .collect()
+}
+
+fn construction(c: &mut Criterion) {
+    let path = std::env::temp_dir().join(format!("rawdeo_bench_{}.txt", std::process::id()));
+    std::fs::write(&path, large_text()).unwrap();
+
+    let mut group = c.benchmark_group("construction");
+    group.sample_size(10);
+    group.bench_function("from_file 10 MB", |b| {
+        b.iter(|| Rope::from_file(&path, SplitStrategy::LineBased).unwrap())
+    });
+    group.finish();
+    std::fs::remove_file(path).unwrap();
+}
+
+fn edits(c: &mut Criterion) {
+    // 1 MB, small enough to rebuild for every batch of edits.
+    let text = large_text()[..1_000_000].to_string();
+    let medium_rope = || Rope::from_string(&text, SplitStrategy::LineBased);
+    let mut group = c.benchmark_group("edits");
+    group.sample_size(10);
+
+    let inserts = indices(EDITS, 1_000_000);
+    group.bench_function("10k random inserts", |b| {
+        b.iter_batched(
+            medium_rope,
+            |mut rope| {
+                for &index in &inserts {
+                    rope.insert_char(index, 'x');
+                }
+                rope
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let removals = indices(EDITS, 1_000_000 - EDITS);
+    group.bench_function("10k random removals", |b| {
+        b.iter_batched(
+            medium_rope,
+            |mut rope| {
+                for &index in &removals {
+                    rope.remove_clamped(index, index + 1);
+                }
+                rope
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let typed: Vec<char> = "fn main() {\n    println!(\"hello\");\n}\n".chars().cycle().take(EDITS).collect();
+    group.bench_function("10k chars typed at the end", |b| {
+        b.iter_batched(
+            medium_rope,
+            |mut rope| {
+                for &c in &typed {
+                    rope.insert_char(rope.len(), c);
+                }
+                rope
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let undo_edits = indices(1_000, 1_000_000);
+    group.bench_function("undo 1k actions", |b| {
+        b.iter_batched(
+            || {
+                let mut rope = medium_rope();
+                for &index in &undo_edits {
+                    rope.insert(index, "edit");
+                }
+                rope
+            },
+            |mut rope| {
+                while rope.undo().is_some() {}
+                rope
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn reads(c: &mut Criterion) {
+    let rope = Rope::from_string(&large_text(), SplitStrategy::LineBased);
+    println!("tree for 10 MB: {:?}", rope.stats());
+
+    let mut group = c.benchmark_group("reads");
+    group.bench_function("lines() on 10 MB", |b| b.iter(|| black_box(&rope).lines()));
+    group.bench_function("text_range of 1k chars on 10 MB", |b| {
+        b.iter(|| black_box(&rope).text_range(5_000_000, 5_001_000))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, construction, edits, reads);
+criterion_main!(benches);
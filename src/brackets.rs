@@ -0,0 +1,52 @@
+use crate::rope::Rope;
+
+// How far `%` looks for the other bracket, so an unmatched one in a huge file
+// gives up quickly instead of scanning to the end.
+pub const MAX_SCAN: usize = 1_000_000;
+
+// The pair `c` belongs to, and whether it opens it.
+fn pair(c: char) -> Option<(char, char, bool)> {
+    match c {
+        '(' | ')' => Some(('(', ')', c == '(')),
+        '[' | ']' => Some(('[', ']', c == '[')),
+        '{' | '}' => Some(('{', '}', c == '{')),
+        _ => None,
+    }
+}
+
+pub fn is_bracket(c: char) -> bool {
+    pair(c).is_some()
+}
+
+// Index of the bracket matching the one at `index`, counting nesting forward
+// from an opening bracket and backward from a closing one. Only brackets of
+// the same kind count, and strings and comments are nothing special. `None`
+// when there's no bracket at `index`, it's unmatched, or the match is more
+// than `limit` chars away.
+pub fn matching_bracket(rope: &Rope, index: usize, limit: usize) -> Option<usize> {
+    let (open, close, opening) = pair(rope.get_char(index)?)?;
+    let (same, other) = if opening { (open, close) } else { (close, open) };
+    let chars: Box<dyn Iterator<Item = char>> = match opening {
+        true => Box::new(rope.chars_at(index + 1)),
+        false => Box::new(rope.chars_before(index)),
+    };
+
+    let mut depth = 0;
+    for (distance, c) in chars.take(limit).enumerate() {
+        if c == same {
+            depth += 1;
+        } else if c == other {
+            if depth == 0 {
+                return Some(if opening { index + 1 + distance } else { index - 1 - distance });
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+// The first bracket at `index` or after it on the same line, the one `%`
+// jumps from.
+pub fn bracket_from(rope: &Rope, index: usize) -> Option<usize> {
+    rope.chars_at(index).take_while(|&c| c != '\n').position(is_bracket).map(|offset| index + offset)
+}
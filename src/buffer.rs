@@ -1,43 +1,632 @@
+use std::collections::{HashMap, VecDeque};
 use std::io::{self, Write};
 use std::fs::File;
-use crate::{input::Key, terminal::disable_raw_mode};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::brackets;
+use crate::highlight::{Highlighter, StyleSpan};
+use crate::history::{History, DEFAULT_HISTORY_SIZE};
+use crate::input::{Key, KeyPress, MouseEvent, MouseKind};
+use crate::layout::Layout;
+use crate::rope::{LineEnding, Rope, SplitStrategy};
+use crate::status::{Severity, StatusBar};
+use crate::swap::{self, Recovery};
+use crate::unicode;
+use crate::wrap::LineWrap;
+
+// Oldest jumps are forgotten past this many.
+const JUMPLIST_SIZE: usize = 20;
+
+// Lines the view moves per step of the mouse wheel.
+const WHEEL_LINES: usize = 3;
+
+// How deep `@x` may nest, so a macro that calls itself stops eventually.
+const MACRO_DEPTH_LIMIT: usize = 20;
+
+// The terminal row the text area starts on.
+const TEXT_TOP: usize = 1;
+
+// How far the bracket highlight in Insert mode looks, kept well below what
+// `%` scans since it runs on every frame.
+const BRACKET_HIGHLIGHT_SCAN: usize = 10_000;
 
 pub struct TextBuffer {
-    pub lines: Vec<String>,
+    // The document as lines joined by '\n', without the final terminator,
+    // so an empty buffer is a single empty line.
+    pub rope: Rope,
+    // Byte offset into the current line, always on a grapheme boundary.
     pub cursor_x: usize,
     pub cursor_y: usize,
     pub scroll_y: usize,
+    // First display column shown when long lines aren't wrapped.
+    pub scroll_x: usize,
     pub screen_height: usize,
+    pub screen_width: usize,
     pub mode: Mode,
     pub command_input: String,
-    render_cache: Vec<String>,
+    // Executed `:` lines, recalled with Up and Down on the command line.
+    pub command_history: History,
+    pub register: String,
+    pub search_input: String,
+    pub last_search: Option<String>,
+    // Char index of the match the cursor was last moved to, `None` when the
+    // last search found nothing.
+    pub search_match: Option<usize>,
+    // Shown on the bottom line until the next key.
+    pub message: Option<(Severity, String)>,
+    pub filename: Option<String>,
+    pub tab_width: usize,
+    // Tab inserts spaces up to the next tab stop instead of a '\t'.
+    pub expand_tabs: bool,
+    // Enter starts the new line with the indentation of the current one, and
+    // Backspace in leading spaces goes back a whole tab stop.
+    pub auto_indent: bool,
+    pub line_numbers: LineNumbers,
+    // Long lines continue on the next screen rows instead of running past the
+    // right edge.
+    pub wrap: bool,
+    // Fixups made right before a write: dropping spaces and tabs at line ends,
+    // and blank lines at the end of the file so it ends in exactly one '\n'.
+    pub trim_trailing_ws: bool,
+    pub final_newline: bool,
+    // How long a modified buffer goes without edits before `tick` writes it to
+    // its swap file. `None` turns autosave off.
+    pub autosave: Option<Duration>,
+    pub should_quit: bool,
+    pending_operator: Option<char>,
+    // Digits typed before a command, like the 5 in `5@a`.
+    pending_count: Option<usize>,
+    // Keys recorded by `q{register}`, replayed by `@{register}`.
+    macros: HashMap<char, Vec<KeyPress>>,
+    recording: Option<(char, Vec<KeyPress>)>,
+    macro_depth: usize,
+    // Set when a replay hits the depth limit, so every level stops.
+    macro_aborted: bool,
+    search_origin: (usize, usize, usize),
+    // (x, y) positions left by searches and `:N`, most recent last. Ctrl+O
+    // goes back through them.
+    jumplist: VecDeque<(usize, usize)>,
+    // Rows as they were last written to the terminal, see `render_to`.
+    screen_cache: Vec<(usize, String)>,
+    // Split windows from top to bottom, all showing this buffer. `screen_height`
+    // is the active window's share of the `text_height` rows.
+    windows: Vec<Window>,
+    active_window: usize,
+    text_height: usize,
+    // The rope generation `tick` last saw change and when, and the one last
+    // sent to the swap file.
+    last_edit: (u64, Instant),
+    swapped_generation: u64,
+    // The background write of the swap file, if one is running.
+    swap_writer: Option<JoinHandle<io::Result<()>>>,
+    // A swap file `open_file` found, until the prompt about it is answered.
+    pending_recovery: Option<PathBuf>,
+    // Set while Alt+Up/Down keep coming, so the whole run undoes at once.
+    line_move_burst: bool,
+    // Colors text on screen when set. `highlights` holds its spans for each
+    // line asked for so far, up to the first line changed since.
+    highlighter: Option<Box<dyn Highlighter>>,
+    highlights: Vec<Option<Vec<StyleSpan>>>,
+}
+
+// Where a window looks into the document. The active window's view lives in
+// the cursor and scroll fields, its entry here is only updated when focus
+// moves away.
+#[derive(Debug, Clone, Copy, Default)]
+struct Window {
+    cursor_x: usize,
+    cursor_y: usize,
+    scroll_y: usize,
+    scroll_x: usize,
+}
+
+// What the gutter shows, like vim's 'number' and 'relativenumber'. With both
+// on, the cursor line keeps its absolute number; with only `relative` it
+// shows 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineNumbers {
+    pub number: bool,
+    pub relative: bool,
+}
+
+// How the numbers in the gutter relate to the cursor. `Relative` counts the
+// distance from the cursor line, `Hybrid` does too except on the cursor line
+// itself, which shows its absolute number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineNumberMode {
+    Absolute,
+    Relative,
+    Hybrid,
+}
+
+impl LineNumbers {
+    // `None` when the gutter is turned off.
+    pub fn mode(&self) -> Option<LineNumberMode> {
+        match (self.number, self.relative) {
+            (true, false) => Some(LineNumberMode::Absolute),
+            (false, true) => Some(LineNumberMode::Relative),
+            (true, true) => Some(LineNumberMode::Hybrid),
+            (false, false) => None,
+        }
+    }
+
+    pub fn from_mode(mode: Option<LineNumberMode>) -> Self {
+        match mode {
+            Some(LineNumberMode::Absolute) => LineNumbers { number: true, relative: false },
+            Some(LineNumberMode::Relative) => LineNumbers { number: false, relative: true },
+            Some(LineNumberMode::Hybrid) => LineNumbers { number: true, relative: true },
+            None => LineNumbers { number: false, relative: false },
+        }
+    }
+}
+
+// What a counted operator like `3dd` or `2dw` acts on, from the cursor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Motion {
+    Lines,
+    Chars,
+    WordForward,
+    WordBackward,
+}
+
+pub struct Substitution {
+    pub pattern: String,
+    pub replacement: String,
+    pub global: bool,
+    pub whole_file: bool,
 }
 
 pub enum Mode {
     Normal,
     Insert,
     Command,
+    Search,
+    // `anchor` is the (x, y) where `v` was pressed, the selection runs from
+    // there to the cursor.
+    Visual { anchor: (usize, usize) },
+}
+
+// Everything `save_session` keeps: the document with its undo history, the
+// cursor as (line, col in chars) and the view.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Session {
+    rope: Rope,
+    cursor: (usize, usize),
+    scroll_y: usize,
+    scroll_x: usize,
+    filename: Option<String>,
+}
+
+// The command history of a session lives in a dotfile beside it, so
+// `notes.json` keeps it in `.notes.json.history`.
+#[cfg(feature = "serde")]
+fn history_path(session: &str) -> std::path::PathBuf {
+    let path = std::path::Path::new(session);
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!(".{}.history", name))
 }
 
 impl TextBuffer {
     pub fn new(screen_height: usize) -> Self {
         Self {
-            lines: vec![String::new()],
+            rope: Rope::new(SplitStrategy::LineBased),
             cursor_x: 0,
             cursor_y: 0,
             scroll_y: 0,
+            scroll_x: 0,
             screen_height: screen_height -2,
+            screen_width: 80,
             mode: Mode::Normal,
             command_input: String::new(),
-            render_cache: vec![String::new()],
+            command_history: History::new(DEFAULT_HISTORY_SIZE),
+            register: String::new(),
+            search_input: String::new(),
+            last_search: None,
+            search_match: None,
+            message: None,
+            filename: None,
+            tab_width: 4,
+            expand_tabs: true,
+            auto_indent: true,
+            line_numbers: LineNumbers { number: true, relative: false },
+            wrap: false,
+            trim_trailing_ws: false,
+            final_newline: false,
+            autosave: Some(Duration::from_secs(4)),
+            should_quit: false,
+            pending_operator: None,
+            pending_count: None,
+            macros: HashMap::new(),
+            recording: None,
+            macro_depth: 0,
+            macro_aborted: false,
+            search_origin: (0, 0, 0),
+            jumplist: VecDeque::new(),
+            screen_cache: Vec::new(),
+            windows: vec![Window::default()],
+            active_window: 0,
+            text_height: screen_height - 2,
+            last_edit: (0, Instant::now()),
+            swapped_generation: 0,
+            swap_writer: None,
+            pending_recovery: None,
+            line_move_burst: false,
+            highlighter: None,
+            highlights: Vec::new(),
+        }
+    }
+
+    // Opens `path` for editing. A missing file gives an empty buffer that is
+    // created on the first `:w`.
+    pub fn new_with_path(screen_height: usize, path: &str) -> io::Result<Self> {
+        let mut buffer = Self::new(screen_height);
+        buffer.open_file(path)?;
+        Ok(buffer)
+    }
+
+    // Replaces the buffer with the contents of `path` and moves the cursor to
+    // the top. On error the buffer is left untouched.
+    pub fn open_file(&mut self, path: &str) -> io::Result<()> {
+        let rope = match Rope::from_file(path, SplitStrategy::LineBased) {
+            Ok(mut rope) => {
+                let line_count = rope.display_lines();
+                let len = rope.len();
+                // The last line's terminator is written back on save, and
+                // splitting it off also drops the load from the undo history.
+                // The buffer's lines are then exactly the rope's.
+                if len > 0 && rope.get_char(len - 1) == Some('\n') {
+                    rope.split_at(len - 1);
+                }
+                self.notify(Severity::Info, format!("\"{}\" {}L [{}]", path, line_count, rope.line_ending().file_format()));
+                rope
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.notify(Severity::Info, format!("\"{}\" [New]", path));
+                Rope::new(SplitStrategy::LineBased)
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.rope = rope;
+        self.rope.mark_saved();
+        self.reset_highlights();
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.scroll_y = 0;
+        self.search_match = None;
+        self.filename = Some(path.to_string());
+        self.last_edit = (self.rope.generation(), Instant::now());
+        self.swapped_generation = self.rope.generation();
+
+        let swap = swap::swap_path(Path::new(path));
+        if swap::swap_is_newer(Path::new(path), &swap) {
+            self.pending_recovery = Some(swap);
+            self.prompt_recovery();
+        }
+        Ok(())
+    }
+
+    fn prompt_recovery(&mut self) {
+        if let Some(swap) = &self.pending_recovery {
+            let text = format!("Found swap file {}: (r)ecover or (i)gnore and delete it?", swap.display());
+            self.notify(Severity::Warning, text);
+        }
+    }
+
+    // Recovering replaces the text with the swap file's as one edit, so the
+    // buffer is modified and `u` gets the file's own text back.
+    fn answer_recovery(&mut self, key: Key) {
+        let answer = match key {
+            Key::Char(c) => Recovery::from_key(c),
+            _ => None,
+        };
+        let (Some(answer), Some(swap)) = (answer, self.pending_recovery.clone()) else {
+            self.prompt_recovery();
+            return;
+        };
+        self.pending_recovery = None;
+
+        match answer {
+            Recovery::Recover => match Rope::from_file(&swap, SplitStrategy::LineBased) {
+                Ok(recovered) => {
+                    let text = recovered.text_range(0, recovered.len());
+                    let len = self.rope.len();
+                    self.rope.replace_range(0, len, text.strip_suffix('\n').unwrap_or(&text));
+                    self.set_cursor_line_col(0, 0);
+                    self.notify(Severity::Info, format!("Recovered from {}", swap.display()));
+                }
+                Err(e) => self.notify(Severity::Error, format!("Can't read {}: {}", swap.display(), e)),
+            },
+            Recovery::Ignore => {
+                if let Err(e) = swap::remove_swap(&swap) {
+                    self.notify(Severity::Error, format!("Can't delete {}: {}", swap.display(), e));
+                }
+            }
+        }
+    }
+
+    // Called from the main loop after every key and every `swap::TICK_MS`
+    // without one. Once the buffer has gone `autosave` without an edit, a
+    // snapshot of it is written to the swap file on another thread.
+    pub fn tick(&mut self, now: Instant) {
+        if self.swap_writer.as_ref().is_some_and(|writer| writer.is_finished()) {
+            self.finish_swap();
+        }
+
+        let generation = self.rope.generation();
+        if generation != self.last_edit.0 {
+            self.last_edit = (generation, now);
+        }
+        let (Some(delay), Some(filename)) = (self.autosave, &self.filename) else {
+            return;
+        };
+        if !self.rope.is_modified()
+            || generation == self.swapped_generation
+            || self.swap_writer.is_some()
+            || self.pending_recovery.is_some()
+            || now.duration_since(self.last_edit.1) < delay
+        {
+            return;
+        }
+
+        let snapshot = self.rope.snapshot();
+        let swap = swap::swap_path(Path::new(filename));
+        self.swap_writer = Some(thread::spawn(move || swap::write_swap(&snapshot, &swap)));
+        self.swapped_generation = generation;
+    }
+
+    // Waits for a running swap write and reports if it failed.
+    pub fn finish_swap(&mut self) {
+        let Some(writer) = self.swap_writer.take() else {
+            return;
+        };
+        match writer.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => self.notify(Severity::Error, format!("Swap file not written: {}", e)),
+            Err(_) => self.notify(Severity::Error, "Swap file not written"),
+        }
+    }
+
+    // Deletes the buffer's swap file, after a save or when the editor exits.
+    pub fn discard_swap(&mut self) {
+        self.finish_swap();
+        if let Some(filename) = &self.filename
+            && let Err(e) = swap::remove_swap(&swap::swap_path(Path::new(filename)))
+        {
+            self.notify(Severity::Error, format!("Can't delete swap file: {}", e));
+        }
+    }
+
+    // Replaces the whole document and moves the cursor to the top.
+    pub fn set_text(&mut self, text: &str) {
+        self.rope = Rope::from_string(text, SplitStrategy::LineBased);
+        self.reset_highlights();
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.scroll_y = 0;
+        self.search_match = None;
+    }
+
+    // Writes the document, its undo history, the cursor and the view to `path`
+    // as JSON, unsaved changes included.
+    #[cfg(feature = "serde")]
+    pub fn save_session(&self, path: &str) -> io::Result<()> {
+        let session = Session {
+            rope: self.rope.clone(),
+            cursor: (self.cursor_y, self.line(self.cursor_y)[..self.cursor_x].chars().count()),
+            scroll_y: self.scroll_y,
+            scroll_x: self.scroll_x,
+            filename: self.filename.clone(),
+        };
+        let mut writer = io::BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &session)?;
+        writer.flush()?;
+        self.command_history.save(&history_path(path))
+    }
+
+    // Loads a session written by `save_session`. On error the buffer is left
+    // untouched.
+    #[cfg(feature = "serde")]
+    pub fn restore_session(&mut self, path: &str) -> io::Result<()> {
+        let session: Session = serde_json::from_reader(io::BufReader::new(File::open(path)?))?;
+        self.rope = session.rope;
+        self.reset_highlights();
+        self.filename = session.filename;
+        self.search_match = None;
+        self.scroll_y = session.scroll_y.min(self.line_count() - 1);
+        self.scroll_x = session.scroll_x;
+        self.set_cursor_line_col(session.cursor.0, session.cursor.1);
+        self.command_history.load(&history_path(path))
+    }
+
+    // Unsaved changes, tracked by the rope so undoing back to the saved text
+    // counts as clean.
+    pub fn is_modified(&self) -> bool {
+        self.rope.is_modified()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.rope.lines()
+    }
+
+    // Line `y` without its terminator, empty past the end of the document.
+    pub fn line(&self, y: usize) -> String {
+        self.rope.get_line(y).unwrap_or_default()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        (0..self.line_count()).map(|y| self.line(y)).collect()
+    }
+
+    // Columns line `y` takes on screen, with tabs expanded to `tab_width`.
+    pub fn visual_line_length(&self, y: usize) -> usize {
+        unicode::display_width(&self.line(y), self.tab_width)
+    }
+
+    // A zero width would put every tab stop at column 0, so it counts as 1.
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.tab_width = width.max(1);
+    }
+
+    fn line_start(&self, y: usize) -> usize {
+        self.rope.line_to_char(y).unwrap_or_else(|| self.rope.len())
+    }
+
+    // Char index just past the last char of line `y`, before its '\n'.
+    fn line_end(&self, y: usize) -> usize {
+        self.line_start(y) + self.rope.line_len(y).unwrap_or(0)
+    }
+
+    // Char index into the rope of byte offset `x` on line `y`.
+    fn char_index(&self, x: usize, y: usize) -> usize {
+        self.line_start(y) + self.line(y)[..x].chars().count()
+    }
+
+    fn cursor_index(&self) -> usize {
+        self.char_index(self.cursor_x, self.cursor_y)
+    }
+
+    fn set_cursor_index(&mut self, index: usize) {
+        let (y, col) = self.rope.line_col(index);
+        self.set_cursor_line_col(y, col);
+    }
+
+    // Moves to line `y`, `col` chars in, clamped to the document.
+    fn set_cursor_line_col(&mut self, y: usize, col: usize) {
+        self.cursor_y = y.min(self.line_count() - 1);
+        let line = self.line(self.cursor_y);
+        let x = line.char_indices().nth(col).map_or(line.len(), |(x, _)| x);
+        self.cursor_x = unicode::floor_boundary(&line, x);
+        self.scroll_to_cursor();
+    }
+
+    pub fn handle_keypress(&mut self, press: impl Into<KeyPress>) {
+        let press = press.into();
+        if self.macro_depth == 0 {
+            self.clear_message();
+        }
+        // Replayed keys are already part of the `@x` that was recorded.
+        if let Some((_, keys)) = self.recording.as_mut().filter(|_| self.macro_depth == 0) {
+            keys.push(press);
+        }
+
+        let moves_line = press.alt
+            && matches!(press.key, Key::ArrowUp | Key::ArrowDown)
+            && matches!(self.mode, Mode::Insert | Mode::Normal)
+            && self.pending_recovery.is_none();
+        if !moves_line {
+            self.line_move_burst = false;
+        }
+        if moves_line {
+            self.move_current_line(press.key == Key::ArrowDown);
+            return;
+        }
+        // Nothing else is bound to Alt yet. Other chords act as the plain key,
+        // so a key typed right after an ESC isn't lost.
+        if self.pending_recovery.is_some() {
+            self.answer_recovery(press.key);
+            return;
+        }
+
+        let key = press.key;
+
+        match (key, &self.mode) {
+            (Key::Mouse(event), _) => self.handle_mouse(event),
+            (_, Mode::Insert) => self.handle_insert_mode(key),
+            (_, Mode::Normal) => self.handle_normal_mode(key),
+            (_, Mode::Command) => self.handle_command_mode(key),
+            (_, Mode::Search) => self.handle_search_mode(key),
+            (_, Mode::Visual { .. }) => self.handle_visual_mode(key),
+        }
+
+        // Commands place the view by logical lines, which a wrapped line
+        // above the cursor can push it out of.
+        if self.wrap {
+            self.scroll_to_cursor();
+        }
+        self.scroll_x_to_cursor();
+    }
+
+    // A click moves the cursor, into the window under it if there are several,
+    // and dragging selects from where the button went down. The prompt modes
+    // ignore the mouse.
+    fn handle_mouse(&mut self, event: MouseEvent) {
+        if matches!(self.mode, Mode::Command | Mode::Search) {
+            return;
+        }
+
+        match event.kind {
+            MouseKind::ScrollUp => self.scroll_view(false, WHEEL_LINES),
+            MouseKind::ScrollDown => self.scroll_view(true, WHEEL_LINES),
+            MouseKind::Press | MouseKind::Drag => {
+                if event.kind == MouseKind::Press
+                    && let Some(index) = self.window_at(event.row)
+                {
+                    self.focus_window(index);
+                }
+                let Some((x, y)) = self.screen_to_text(event.row, event.column) else {
+                    return;
+                };
+
+                match (event.kind, &self.mode) {
+                    (MouseKind::Press, Mode::Visual { .. }) => self.mode = Mode::Normal,
+                    (MouseKind::Drag, Mode::Normal) => {
+                        self.mode = Mode::Visual { anchor: (self.cursor_x, self.cursor_y) };
+                    }
+                    _ => {}
+                }
+                self.cursor_x = x;
+                self.cursor_y = y;
+                self.scroll_to_cursor();
+            }
+            MouseKind::Release => {}
+        }
+    }
+
+    // Index of the window whose text area covers terminal `row`.
+    fn window_at(&self, row: usize) -> Option<usize> {
+        let text_row = row.checked_sub(TEXT_TOP)?;
+        self.layout().windows.iter().position(|rows| rows.contains(&text_row))
+    }
+
+    // The (x, y) cursor position under the 1-based terminal cell at `row` and
+    // `column` in the active window, or `None` outside its text area. Clicks
+    // in the gutter go to column 0, past a line end to the end of the line and
+    // below the document to its last line.
+    pub fn screen_to_text(&self, row: usize, column: usize) -> Option<(usize, usize)> {
+        let rows = self.layout().windows.get(self.active_window)?.clone();
+        let text_row = row.checked_sub(TEXT_TOP).filter(|text_row| rows.contains(text_row))?;
+        let gutter = format_gutter(0, 0, self.line_count(), self.line_numbers).len();
+        let column = column.saturating_sub(gutter + 1) + self.scroll_x;
+
+        let mut remaining = text_row - rows.start;
+        let mut y = self.scroll_y.min(self.line_count() - 1);
+        loop {
+            let wrap = self.line_wrap(y);
+            if remaining < wrap.row_count() || y + 1 == self.line_count() {
+                return Some((wrap.byte_at(remaining, column), y));
+            }
+            remaining -= wrap.row_count();
+            y += 1;
         }
     }
 
-    pub fn handle_keypress(&mut self, key: Key) {
-        match self.mode {
-            Mode::Insert => self.handle_insert_mode(key),
-            Mode::Normal => self.handle_normal_mode(key),
-            Mode::Command => self.handle_command_mode(key),
+    // Moves the view by `lines` without the cursor, unless the cursor would
+    // end up off screen.
+    pub fn scroll_view(&mut self, down: bool, lines: usize) {
+        let max_scroll = self.line_count().saturating_sub(self.screen_height);
+        self.scroll_y = match down {
+            true => (self.scroll_y + lines).min(max_scroll),
+            false => self.scroll_y.saturating_sub(lines),
+        };
+
+        let bottom = (self.scroll_y + self.screen_height).min(self.line_count()) - 1;
+        let y = self.cursor_y.clamp(self.scroll_y, bottom.max(self.scroll_y));
+        if y != self.cursor_y {
+            self.cursor_y = y;
+            self.cursor_x = unicode::floor_boundary(&self.line(y), self.cursor_x);
         }
     }
 
@@ -48,21 +637,96 @@ impl TextBuffer {
             Key::Tab => self.insert_char('\t'),
             Key::Enter => self.insert_new_line(),
             Key::Backspace => self.delete_char(),
-            Key::ArrowLeft | Key::ArrowRight | Key::ArrowUp | Key::ArrowDown => self.move_cursor(key),
-            Key::OptionSpace => self.mode = Mode::Normal,
+            Key::ArrowLeft
+            | Key::ArrowRight
+            | Key::ArrowUp
+            | Key::ArrowDown
+            | Key::PageUp
+            | Key::PageDown
+            | Key::Home
+            | Key::End => self.move_cursor(key),
+            Key::Delete => self.delete_forward(),
+            // Terminals send Ctrl+Backspace as ^H.
+            Key::Ctrl('h' | 'w') => self.delete_word_backward(),
+            Key::CtrlDelete => self.delete_word_forward(),
+            Key::CtrlArrowRight => self.word_forward(),
+            Key::CtrlArrowLeft => self.word_backward(),
+            Key::Ctrl('z') => self.undo(),
+            Key::Ctrl('r' | 'y') => self.redo(),
+            Key::Escape | Key::OptionSpace | Key::Ctrl('c') => {
+                // Leaving Insert mode ends the typing burst, so `u` undoes it
+                // as a whole.
+                self.rope.break_undo_group();
+                self.mode = Mode::Normal;
+            }
             _ => {}
         }
     }
 
     fn handle_normal_mode(&mut self, key: Key) {
+        let pending = self.pending_operator.take();
+        let count = self.pending_count.take();
+        let repeat = count.unwrap_or(1);
+
         match key {
+            Key::Char(c) if pending == Some('q') => self.start_recording(c),
+            Key::Char(c) if pending == Some('@') => self.replay_macro(c, count.unwrap_or(1)),
+            Key::Char('y') if pending == Some('y') => self.apply_operator('y', Motion::Lines, repeat),
+            Key::Char('d') if pending == Some('d') => self.apply_operator('d', Motion::Lines, repeat),
+            Key::Char('w') if pending == Some('d') => self.apply_operator('d', Motion::WordForward, repeat),
+            Key::Char('b') if pending == Some('d') => self.apply_operator('d', Motion::WordBackward, repeat),
+            Key::Char(c @ '0'..='9') if pending.is_none() && (c != '0' || count.is_some()) => {
+                let digit = c.to_digit(10).unwrap_or(0) as usize;
+                self.pending_count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+            }
+            Key::Char('q') if self.recording.is_some() => self.stop_recording(),
+            Key::ArrowUp | Key::Char('k') if pending == Some('w') => {
+                self.focus_window(self.active_window.saturating_sub(1));
+            }
+            Key::ArrowDown | Key::Char('j') if pending == Some('w') => self.focus_window(self.active_window + 1),
+            Key::ArrowUp if pending == Some('g') => self.move_line(false),
+            Key::ArrowDown if pending == Some('g') => self.move_line(true),
+            Key::Char(c @ ('y' | 'd')) => {
+                self.pending_operator = Some(c);
+                self.pending_count = count;
+            }
+            Key::Char(c @ ('q' | 'g')) => self.pending_operator = Some(c),
+            // Ctrl+W waits for the direction to move focus in.
+            Key::Ctrl('w') => self.pending_operator = Some('w'),
+            Key::Char('@') => {
+                self.pending_operator = Some('@');
+                self.pending_count = count;
+            }
+            Key::Char('u') | Key::Ctrl('z') => self.undo(),
+            Key::Ctrl('r' | 'y') => self.redo(),
+            Key::Char('x') => self.apply_operator('d', Motion::Chars, repeat),
+            Key::Char('%') => self.jump_to_match(),
+            Key::Ctrl('d') => self.duplicate_lines(self.cursor_y, repeat),
+            Key::Char('J') => self.join_lines(self.cursor_y, repeat.max(2)),
+            Key::Char('p') => self.paste(true),
+            Key::Char('P') => self.paste(false),
             Key::Char('i') => self.mode = Mode::Insert,
+            Key::Char('v') => self.mode = Mode::Visual { anchor: (self.cursor_x, self.cursor_y) },
             Key::Char(':') => {
                 self.mode = Mode::Command;
                 self.command_input.clear();
             }
+            Key::Char('/') => self.start_search(),
+            Key::Char('w') | Key::CtrlArrowRight => self.repeat_motion(repeat, Self::word_forward),
+            Key::Char('b') | Key::CtrlArrowLeft => self.repeat_motion(repeat, Self::word_backward),
+            Key::Char('e') => self.repeat_motion(repeat, Self::word_end),
+            Key::Ctrl('o') => self.jump_back(),
+            // Ctrl+C reaches the editor as a key, it doesn't quit on its own.
+            Key::Ctrl('c') => self.notify(Severity::Warning, "Type :q to quit"),
+            Key::Char('n') => self.repeat_search(true),
+            Key::Char('N') => self.repeat_search(false),
             Key::OptionSpace => self.mode = Mode::Insert,
-            Key::ArrowLeft | Key::ArrowRight | Key::ArrowUp | Key::ArrowDown => self.move_cursor(key),
+            Key::ArrowLeft | Key::ArrowRight | Key::ArrowUp | Key::ArrowDown | Key::PageUp | Key::PageDown => {
+                self.repeat_motion(repeat, |buffer| buffer.move_cursor(key));
+            }
+            Key::Home | Key::End => self.move_cursor(key),
+            Key::Delete => self.delete_forward(),
+            Key::CtrlDelete => self.delete_word_forward(),
             Key::Space => self.insert_char(' '),
             Key::Tab => self.insert_char('\t'),
             Key::Enter => self.insert_new_line(),
@@ -71,184 +735,1556 @@ impl TextBuffer {
         }
     }
 
+    fn handle_visual_mode(&mut self, key: Key) {
+        match key {
+            Key::Char('y') => {
+                self.yank_selection();
+                self.mode = Mode::Normal;
+            }
+            Key::Char('d' | 'x') | Key::Delete => {
+                self.delete_selection();
+                self.mode = Mode::Normal;
+            }
+            Key::Ctrl('d') => {
+                self.duplicate_selection();
+                self.mode = Mode::Normal;
+            }
+            Key::Char('J') => {
+                self.join_selection();
+                self.mode = Mode::Normal;
+            }
+            Key::Char('w') | Key::CtrlArrowRight => self.word_forward(),
+            Key::Char('b') | Key::CtrlArrowLeft => self.word_backward(),
+            Key::Char('e') => self.word_end(),
+            Key::Char('%') => self.jump_to_match(),
+            Key::ArrowLeft
+            | Key::ArrowRight
+            | Key::ArrowUp
+            | Key::ArrowDown
+            | Key::PageUp
+            | Key::PageDown
+            | Key::Home
+            | Key::End => self.move_cursor(key),
+            Key::Char('v') | Key::Escape | Key::OptionSpace | Key::Ctrl('c') => self.mode = Mode::Normal,
+            _ => {}
+        }
+    }
+
     fn handle_command_mode(&mut self, key: Key) {
         match key {
             Key::Char(c) => self.command_input.push(c),
+            Key::Space => self.command_input.push(' '),
             Key::Backspace => {
                 self.command_input.pop();
             }
             Key::Enter => {
-                self.execute_command();
+                let command = std::mem::take(&mut self.command_input);
+                self.command_history.push(&command);
+                self.execute_command(&command);
+            }
+            Key::ArrowUp => {
+                if let Some(entry) = self.command_history.older(&self.command_input) {
+                    self.command_input = entry.to_string();
+                }
+            }
+            Key::ArrowDown => {
+                if let Some(entry) = self.command_history.newer() {
+                    self.command_input = entry.to_string();
+                }
+            }
+            Key::Escape | Key::OptionSpace | Key::Ctrl('c') => {
+                self.command_history.reset();
+                self.mode = Mode::Normal;
             }
-            Key::OptionSpace => self.mode = Mode::Normal,
             _ => {}
         }
     }
 
-    fn execute_command(&mut self) {
-        print!("\x1b[2;1H\x1b[K");
-        println!("executed: {}", self.command_input);
-        io::stdout().flush().unwrap();
-
-        match self.command_input.as_str() {
-            "q!" => {
-                print!("\x1b[2J\x1b[H");
-                disable_raw_mode();
-                std::process::exit(0);
+    fn handle_search_mode(&mut self, key: Key) {
+        match key {
+            Key::Char(c) => {
+                self.search_input.push(c);
+                self.update_search();
+            }
+            Key::Space => {
+                self.search_input.push(' ');
+                self.update_search();
+            }
+            Key::Backspace if self.search_input.is_empty() => self.cancel_search(),
+            Key::Backspace => {
+                self.search_input.pop();
+                self.update_search();
             }
-            "s" => {
-                if let Err(e) = self.save_to_file("output.txt") {
-                    print!("\x1b[2;1H\x1b[KSave failed: {}", e);
-                } else {
-                    print!("\x1b[2;1H\x1b[KFile saved: output.txt");
+            Key::Enter => {
+                if !self.search_input.is_empty() {
+                    if self.search_match.is_none() {
+                        self.notify(Severity::Error, format!("Pattern not found: {}", self.search_input));
+                    } else {
+                        let (x, y, _) = self.search_origin;
+                        self.push_jump(x, y);
+                    }
+                    self.last_search = Some(self.search_input.clone());
                 }
-                io::stdout().flush().unwrap();
+                self.mode = Mode::Normal;
             }
+            Key::Escape | Key::OptionSpace | Key::Ctrl('c') => self.cancel_search(),
             _ => {}
         }
+    }
 
-        self.command_input.clear();
+    fn cancel_search(&mut self) {
+        (self.cursor_x, self.cursor_y, self.scroll_y) = self.search_origin;
+        self.search_match = None;
+        self.clear_message();
         self.mode = Mode::Normal;
     }
 
-    fn save_to_file(&self, filename: &str) -> io::Result<()> {
-        let mut file = File::create(filename)?;
-        for line in &self.lines {
-            writeln!(file, "{}", line)?;
-        }
-        Ok(())
+    fn start_search(&mut self) {
+        self.search_origin = (self.cursor_x, self.cursor_y, self.scroll_y);
+        self.search_input.clear();
+        self.search_match = None;
+        self.clear_message();
+        self.mode = Mode::Search;
     }
 
-    pub fn insert_char(&mut self, c: char) {
-        if c == '\t' {
-            for _ in 0..4 {
-                self.lines[self.cursor_y].insert(self.cursor_x, ' ');
-                self.cursor_x += 1;
+    // Re-runs the search from where the cursor was when `/` was pressed, so
+    // every keystroke refines the match instead of hopping past it.
+    fn update_search(&mut self) {
+        let (origin_x, origin_y, _) = self.search_origin;
+        (self.cursor_x, self.cursor_y, self.scroll_y) = self.search_origin;
+
+        self.clear_message();
+        if self.search_input.is_empty() {
+            self.search_match = None;
+            return;
+        }
+
+        let pattern = self.search_input.clone();
+        let origin = self.char_index(origin_x, origin_y);
+        self.search_match = self.find_forward(&pattern, origin).map(|(index, wrapped)| {
+            self.set_cursor_index(index);
+            if wrapped {
+                self.notify(Severity::Warning, "search wrapped");
             }
-        } else if c == ' ' || c.is_ascii_graphic() {
-            if self.cursor_x > self.lines[self.cursor_y].len() {
-                self.cursor_x = self.lines[self.cursor_y].len();
+            index
+        });
+    }
+
+    pub fn repeat_search(&mut self, forward: bool) {
+        let Some(pattern) = self.last_search.clone() else {
+            self.notify(Severity::Error, "No previous search pattern");
+            return;
+        };
+
+        // The scan starts at the cursor, normally the last match, and reads
+        // only up to the next one.
+        let from = self.cursor_index();
+        let found = if forward { self.find_forward(&pattern, from) } else { self.find_backward(&pattern, from) };
+        self.search_match = found.map(|(index, _)| index);
+
+        match found {
+            Some((index, wrapped)) => {
+                self.push_jump(self.cursor_x, self.cursor_y);
+                self.set_cursor_index(index);
+                if wrapped {
+                    self.notify(Severity::Warning, "search wrapped");
+                }
             }
-            self.lines[self.cursor_y].insert(self.cursor_x, c);
-            self.cursor_x += 1;
+            None => self.notify(Severity::Error, format!("Pattern not found: {}", pattern)),
         }
     }
 
-    pub fn delete_char(&mut self) {
-        if self.cursor_x > 0 {
-            self.lines[self.cursor_y].remove(self.cursor_x -1);
-            self.cursor_x -= 1;
-        } else if self.cursor_y > 0 {
-            let prev_line = self.lines.remove(self.cursor_y);
-            self.cursor_y -= 1;
-            self.cursor_x = self.lines[self.cursor_y].len();
-            self.lines[self.cursor_y].push_str(&prev_line);
+    pub fn notify(&mut self, severity: Severity, text: impl Into<String>) {
+        self.message = Some((severity, text.into()));
+    }
 
-            if self.cursor_y < self.scroll_y {
-                self.scroll_y = self.cursor_y;
-            }
-        } 
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
 
-        self.cursor_x = self.cursor_x.min(self.lines[self.cursor_y].len());
+    // The message without its severity, empty when there is none.
+    pub fn message_text(&self) -> &str {
+        self.message.as_ref().map_or("", |(_, text)| text)
+    }
 
-        if self.cursor_y == 0 {
-            self.scroll_y = 0;
-        }
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
     }
 
-    pub fn insert_new_line(&mut self) {
-        let current_line = self.lines[self.cursor_y].split_off(self.cursor_x);
-        self.cursor_y += 1;
-        self.cursor_x = 0;
-        self.lines.insert(self.cursor_y, current_line);
+    pub fn macro_keys(&self, register: char) -> Option<&[KeyPress]> {
+        self.macros.get(&register).map(Vec::as_slice)
+    }
 
-        if self.cursor_y >= self.scroll_y + self.screen_height {
-            self.scroll_y += 1;
+    fn start_recording(&mut self, register: char) {
+        if register.is_ascii_lowercase() || register.is_ascii_digit() {
+            self.recording = Some((register, Vec::new()));
+            self.notify(Severity::Info, format!("recording @{}", register));
         }
     }
 
-    pub fn move_cursor(&mut self, direction: Key) {
-        match direction {
-            Key::ArrowLeft => {
-                if self.cursor_x > 0 {
-                    self.cursor_x -= 1;
-                } else if self.cursor_y > 0 {
-                    self.cursor_y -= 1;
-                    self.cursor_x = self.lines[self.cursor_y].len();
-                }
-            }
-            Key::ArrowRight => {
-                if self.cursor_x < self.lines[self.cursor_y].len() {
-                    self.cursor_x += 1;
-                } else if self.cursor_y < self.lines.len() - 1 {
-                    self.cursor_y += 1;
-                    self.cursor_x = 0;
-                }
-            }
-            Key::ArrowUp => {
-                if self.cursor_y > 0 {
-                    self.cursor_y -= 1;
-                    self.cursor_x = self.cursor_x.min(self.lines[self.cursor_y].len());
-                    if self.cursor_y < self.scroll_y {
-                        self.scroll_y -= 1;
-                    }
-                }
-            }
-            Key::ArrowDown => {
-                if self.cursor_y < self.lines.len() - 1 {
-                    self.cursor_y += 1;
-                    self.cursor_x = self.cursor_x.min(self.lines[self.cursor_y].len());
-                    if self.cursor_y >= self.scroll_y + self.screen_height {
-                        self.scroll_y += 1;
-                    }
+    // The `q` that ends the recording was recorded too and is dropped here.
+    fn stop_recording(&mut self) {
+        if let Some((register, mut keys)) = self.recording.take() {
+            keys.pop();
+            self.macros.insert(register, keys);
+            self.clear_message();
+        }
+    }
+
+    // Feeds the keys stored in `register` back through `handle_keypress`,
+    // `count` times over.
+    pub fn replay_macro(&mut self, register: char, count: usize) {
+        let Some(keys) = self.macros.get(&register).cloned() else {
+            return;
+        };
+        if self.macro_depth >= MACRO_DEPTH_LIMIT {
+            self.macro_aborted = true;
+            self.notify(Severity::Error, "Macro recursion too deep");
+            return;
+        }
+
+        // Changes made by the replay don't merge with the typing around it,
+        // and all of them undo as one. They're folded together after every
+        // key, so a replay bigger than the undo limit still fits in one entry.
+        self.rope.break_undo_group();
+        let generation = self.rope.generation();
+        self.macro_depth += 1;
+        'replay: for _ in 0..count {
+            for &key in &keys {
+                if self.macro_aborted {
+                    break 'replay;
                 }
+                self.handle_keypress(key);
+                self.rope.merge_undo_since(generation);
             }
-            _ => {}
         }
+        self.macro_depth -= 1;
+        if self.macro_depth == 0 {
+            self.macro_aborted = false;
+        }
+        self.rope.break_undo_group();
     }
 
-    pub fn render(&mut self) {
-        print!("\x1b[?25l");
+    fn push_jump(&mut self, x: usize, y: usize) {
+        if self.jumplist.len() == JUMPLIST_SIZE {
+            self.jumplist.pop_front();
+        }
+        self.jumplist.push_back((x, y));
+    }
 
-        let new_max_digits = self.lines.len().to_string().len();
-        let old_max_digits = self.render_cache.len().to_string().len();
+    // Vim's `%`: from the first bracket under or after the cursor on its line
+    // to the one matching it. The jump can be undone with Ctrl+O.
+    pub fn jump_to_match(&mut self) {
+        let Some(bracket) = brackets::bracket_from(&self.rope, self.cursor_index()) else {
+            self.notify(Severity::Warning, "No bracket under or after the cursor");
+            return;
+        };
+        match brackets::matching_bracket(&self.rope, bracket, brackets::MAX_SCAN) {
+            Some(target) => {
+                self.push_jump(self.cursor_x, self.cursor_y);
+                self.set_cursor_index(target);
+            }
+            None => self.notify(Severity::Warning, "No match"),
+        }
+    }
 
-        if new_max_digits != old_max_digits {
-            print!("\x1b[2J\x1b[H");
+    // In Insert mode, the bracket matching the one under the cursor, or else
+    // the one just typed before it.
+    pub fn highlighted_bracket(&self) -> Option<usize> {
+        if !matches!(self.mode, Mode::Insert) {
+            return None;
         }
+        let index = self.cursor_index();
+        let bracket = match self.rope.get_char(index) {
+            Some(c) if brackets::is_bracket(c) => index,
+            _ => index.checked_sub(1).filter(|&before| self.rope.get_char(before).is_some_and(brackets::is_bracket))?,
+        };
+        brackets::matching_bracket(&self.rope, bracket, BRACKET_HIGHLIGHT_SCAN)
+    }
 
-        let mode_display = match self.mode {
-            Mode::Normal => "-- NORMAL --",
-            Mode::Insert => "-- INSERT --",
-            Mode::Command => "-- COMMAND --",
+    // Returns to the position before the latest jump, clamped in case the
+    // text has changed since.
+    pub fn jump_back(&mut self) {
+        let Some((x, y)) = self.jumplist.pop_back() else {
+            self.notify(Severity::Warning, "Jump list is empty");
+            return;
         };
-        print!("\x1b[1;1H\x1b[K{}", mode_display);
+        self.cursor_y = y.min(self.line_count() - 1);
+        self.cursor_x = unicode::floor_boundary(&self.line(self.cursor_y), x);
+        self.scroll_to_cursor();
+    }
+
+    // Moves to the start of 1-based line `number`. `0` is the first line, and
+    // a line past the end lands on the last one with a note on the status bar.
+    pub fn goto_line(&mut self, number: usize) {
+        self.push_jump(self.cursor_x, self.cursor_y);
+        if number > self.line_count() {
+            let message = format!("Line {} out of range, moved to last line {}", number, self.line_count());
+            self.notify(Severity::Warning, message);
+        } else {
+            self.clear_message();
+        }
+        self.cursor_y = number.saturating_sub(1).min(self.line_count() - 1);
+        self.cursor_x = 0;
+        let max_scroll = self.line_count().saturating_sub(self.screen_height);
+        self.scroll_y = self.cursor_y.saturating_sub(self.screen_height / 2).min(max_scroll);
+    }
 
-        print!("\x1b[2;1H\x1b[K:{}", self.command_input);
+    // The first match strictly after char `index` as (index, wrapped),
+    // wrapping around the end of the document.
+    fn find_forward(&self, pattern: &str, index: usize) -> Option<(usize, bool)> {
+        match self.rope.find_from(index + 1, pattern) {
+            Some(found) => Some((found, false)),
+            None => self.rope.find(pattern).map(|found| (found, true)),
+        }
+    }
 
-        let mut last_rendered_line = 0;
-        for (i, line_index) in (self.scroll_y..self.scroll_y + self.screen_height)
-            .enumerate()
-            .take(self.lines.len() - self.scroll_y) 
-        {
-            let line = &self.lines[line_index];
+    // The last match strictly before char `index`, wrapping around the start.
+    fn find_backward(&self, pattern: &str, index: usize) -> Option<(usize, bool)> {
+        match self.rope.rfind_from(index, pattern) {
+            Some(found) => Some((found, false)),
+            None => self.rope.rfind(pattern).map(|found| (found, true)),
+        }
+    }
+
+    pub fn window_count(&self) -> usize {
+        self.windows.len()
+    }
 
-            print!("\x1b[{};1H\x1b[K{:>width$} | {}", i + 3, line_index + 1, line, width = new_max_digits);
-            last_rendered_line = i + 3;
-           
+    pub fn active_window(&self) -> usize {
+        self.active_window
+    }
+
+    // Opens a second view of the buffer above the current one and moves focus
+    // there. Both start out at the same position.
+    pub fn split_window(&mut self) {
+        let height = self.text_area_height();
+        if Layout::compute(height, self.windows.len() + 1).is_none() {
+            self.notify(Severity::Error, "Not enough room");
+            return;
+        }
+
+        self.text_height = height;
+        self.save_view();
+        self.windows.insert(self.active_window, self.windows[self.active_window]);
+        self.apply_layout();
+    }
+
+    // Focus goes to the window below, or above when the last one closes.
+    pub fn close_window(&mut self) {
+        if self.windows.len() == 1 {
+            self.notify(Severity::Error, "Cannot close last window");
+            return;
+        }
+
+        self.windows.remove(self.active_window);
+        self.active_window = self.active_window.min(self.windows.len() - 1);
+        self.load_view();
+        self.apply_layout();
+    }
+
+    pub fn focus_window(&mut self, index: usize) {
+        if index >= self.windows.len() || index == self.active_window {
+            return;
         }
 
-        for i in last_rendered_line + 1..self.screen_height + 3 {
-            print!("\x1b[{};1H\x1b[K", i);
+        self.save_view();
+        self.active_window = index;
+        self.load_view();
+        self.apply_layout();
+    }
+
+    // Fits the view to a terminal of `width` by `height` cells, counted the same
+    // way as for `new`. Splits that no longer fit are closed, the active one
+    // stays.
+    pub fn handle_resize(&mut self, width: usize, height: usize) {
+        self.screen_width = width;
+        self.text_height = height.saturating_sub(2).max(1);
+        while self.windows.len() > 1 && Layout::compute(self.text_height, self.windows.len()).is_none() {
+            let index = if self.active_window + 1 == self.windows.len() { 0 } else { self.windows.len() - 1 };
+            self.windows.remove(index);
+            if index < self.active_window {
+                self.active_window -= 1;
+            }
         }
 
-        self.render_cache = self.lines.clone();
+        self.apply_layout();
+        let max_scroll = self.line_count().saturating_sub(self.screen_height);
+        self.scroll_y = self.scroll_y.min(max_scroll);
+        self.scroll_to_cursor();
+        self.scroll_x_to_cursor();
+        // Rows the terminal reflowed can't be trusted, draw everything again.
+        self.screen_cache.clear();
+    }
 
-        let cursor_offset = new_max_digits + 3;
-        let cursor_screen_y = self.cursor_y.saturating_sub(self.scroll_y) + 2;
-        print!("\x1b[{};{}H", cursor_screen_y + 1, self.cursor_x + cursor_offset + 1);
-        print!("\x1b[?25h");
+    fn save_view(&mut self) {
+        self.windows[self.active_window] = Window {
+            cursor_x: self.cursor_x,
+            cursor_y: self.cursor_y,
+            scroll_y: self.scroll_y,
+            scroll_x: self.scroll_x,
+        };
+    }
 
-        io::stdout().flush().unwrap();
+    // Edits made from other windows may have moved the text under the saved
+    // cursor, so it is clamped back onto the document.
+    fn load_view(&mut self) {
+        let window = self.windows[self.active_window];
+        self.cursor_y = window.cursor_y.min(self.line_count() - 1);
+        let line = self.line(self.cursor_y);
+        self.cursor_x = unicode::floor_boundary(&line, window.cursor_x.min(line.len()));
+        self.scroll_y = window.scroll_y;
+        self.scroll_x = window.scroll_x;
     }
-}
\ No newline at end of file
+
+    fn apply_layout(&mut self) {
+        if let Some(rows) = Layout::compute(self.text_height, self.windows.len())
+            .and_then(|layout| layout.windows.get(self.active_window).cloned())
+        {
+            self.screen_height = rows.len();
+        }
+        self.scroll_to_cursor();
+    }
+
+    // Rows shared by the windows. A lone window's height can be changed
+    // directly through `screen_height`.
+    fn text_area_height(&self) -> usize {
+        if self.windows.len() == 1 { self.screen_height } else { self.text_height }
+    }
+
+    fn layout(&self) -> Layout {
+        let height = self.text_area_height();
+        Layout::compute(height, self.windows.len()).unwrap_or_else(|| Layout::single(height))
+    }
+
+    // Runs an ex command (without the leading ':') and returns to Normal mode.
+    // Quitting only sets `should_quit`, the main loop does the actual exit.
+    pub fn execute_command(&mut self, command: &str) {
+        let (name, arg) = match command.trim().split_once(' ') {
+            Some((name, arg)) => (name, Some(arg.trim())),
+            None => (command.trim(), None),
+        };
+
+        match (name, arg) {
+            ("q", None) if self.is_modified() => {
+                self.notify(Severity::Error, "No write since last change (add ! to override)");
+            }
+            ("q" | "q!", None) => self.should_quit = true,
+            ("e", Some(_)) if self.is_modified() => {
+                self.notify(Severity::Error, "No write since last change (add ! to override)");
+            }
+            ("e" | "e!", Some(path)) => {
+                if let Err(e) = self.open_file(path) {
+                    self.notify(Severity::Error, format!("Can't open {}: {}", path, e));
+                }
+            }
+            ("w" | "wq", path) => {
+                let written = match path.map(String::from).or_else(|| self.filename.clone()) {
+                    Some(filename) => self.write_file(&filename),
+                    None => {
+                        self.notify(Severity::Error, "No file name");
+                        false
+                    }
+                };
+                if written && name == "wq" {
+                    self.should_quit = true;
+                }
+            }
+            ("set", Some(option)) => self.set_option(option),
+            ("sp" | "split", None) => self.split_window(),
+            ("clo" | "close", None) => self.close_window(),
+            (number, None) if !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()) => {
+                self.goto_line(number.parse().unwrap_or(usize::MAX));
+            }
+            ("$", None) => self.goto_line(self.line_count()),
+            ("s", None) => {
+                self.write_file("output.txt");
+            }
+            _ => match parse_substitution(command) {
+                Some(substitution) => self.substitute(&substitution),
+                None => self.notify(Severity::Error, format!("Not an editor command: {}", command)),
+            },
+        }
+
+        self.mode = Mode::Normal;
+    }
+
+    pub fn substitute(&mut self, substitution: &Substitution) {
+        let range = if substitution.whole_file {
+            0..self.line_count()
+        } else {
+            self.cursor_y..self.cursor_y + 1
+        };
+
+        let pattern_len = substitution.pattern.chars().count();
+        let mut changed_lines = 0;
+        let mut last_changed = None;
+        let mut edits = Vec::new();
+
+        // Matches come back in order, so the line they fall on only moves
+        // forward and is found without copying any line out of the rope.
+        let mut y = range.start;
+        for index in self.rope.find_all(&substitution.pattern) {
+            while y < range.end && index > self.line_end(y) {
+                y += 1;
+            }
+            if y == range.end {
+                break;
+            }
+            if index < self.line_start(y) || index + pattern_len > self.line_end(y) {
+                continue;
+            }
+            if last_changed == Some(y) && !substitution.global {
+                continue;
+            }
+
+            if last_changed != Some(y) {
+                changed_lines += 1;
+                last_changed = Some(y);
+            }
+            edits.push((index, index + pattern_len, substitution.replacement.clone()));
+        }
+
+        let Some(y) = last_changed else {
+            self.notify(Severity::Error, format!("Pattern not found: {}", substitution.pattern));
+            return;
+        };
+        // All matches go in as one edit, so a single `u` takes back the lot.
+        let substitutions = edits.len();
+        self.rope.apply_edits(&edits).expect("matches don't overlap");
+
+        self.cursor_y = y;
+        self.cursor_x = unicode::floor_boundary(&self.line(y), self.cursor_x);
+        self.scroll_to_cursor();
+        self.notify(Severity::Info, format!(
+            "{} substitution{} on {} line{}",
+            substitutions,
+            if substitutions == 1 { "" } else { "s" },
+            changed_lines,
+            if changed_lines == 1 { "" } else { "s" },
+        ));
+    }
+
+    fn set_option(&mut self, option: &str) {
+        let (name, value) = option.split_once('=').unwrap_or((option, ""));
+        match (name, value) {
+            ("ff" | "fileformat", "") => {
+                self.notify(Severity::Info, format!("fileformat={}", self.rope.line_ending().file_format()));
+            }
+            ("ff" | "fileformat", _) => match LineEnding::from_file_format(value) {
+                Some(line_ending) => self.rope.set_line_ending(line_ending),
+                None => self.notify(Severity::Error, format!("Invalid argument: {}", option)),
+            },
+            ("ts" | "tabstop" | "tabwidth", "") => self.notify(Severity::Info, format!("tabwidth={}", self.tab_width)),
+            ("ts" | "tabstop" | "tabwidth", _) => match value.parse() {
+                Ok(width) if width > 0 => self.set_tab_width(width),
+                _ => self.notify(Severity::Error, format!("Invalid argument: {}", option)),
+            },
+            ("et" | "expandtab", "") => self.expand_tabs = true,
+            ("noet" | "noexpandtab", "") => self.expand_tabs = false,
+            ("ai" | "autoindent", "") => self.auto_indent = true,
+            ("noai" | "noautoindent", "") => self.auto_indent = false,
+            ("nu" | "number", "") => self.line_numbers.number = true,
+            ("nonu" | "nonumber", "") => self.line_numbers.number = false,
+            ("rnu" | "relativenumber", "") => self.line_numbers.relative = true,
+            ("nornu" | "norelativenumber", "") => self.line_numbers.relative = false,
+            ("hi" | "history", "") => {
+                self.notify(Severity::Info, format!("history={}", self.command_history.capacity()));
+            }
+            ("hi" | "history", _) => match value.parse() {
+                Ok(size) => self.command_history.set_capacity(size),
+                _ => self.notify(Severity::Error, format!("Invalid argument: {}", option)),
+            },
+            ("autosave", "") => {
+                let seconds = self.autosave.map_or(0, |delay| delay.as_secs());
+                self.notify(Severity::Info, format!("autosave={}", seconds));
+            }
+            ("autosave", _) => match value.parse() {
+                Ok(0) => self.autosave = None,
+                Ok(seconds) => self.autosave = Some(Duration::from_secs(seconds)),
+                _ => self.notify(Severity::Error, format!("Invalid argument: {}", option)),
+            },
+            ("wrap", "") => self.wrap = true,
+            ("nowrap", "") => self.wrap = false,
+            ("trim_trailing_ws", "") => self.trim_trailing_ws = true,
+            ("notrim_trailing_ws", "") => self.trim_trailing_ws = false,
+            ("final_newline", "") => self.final_newline = true,
+            ("nofinal_newline", "") => self.final_newline = false,
+            _ => self.notify(Severity::Error, format!("Invalid argument: {}", option)),
+        }
+    }
+
+    // Writes the buffer to `filename`, which also becomes the buffer's file name
+    // if it had none. Returns whether the write succeeded.
+    fn write_file(&mut self, filename: &str) -> bool {
+        self.apply_save_fixups();
+        match self.save_to_file(filename) {
+            Ok(()) => {
+                self.notify(Severity::Info, format!("\"{}\" {}L written", filename, self.line_count()));
+                if self.filename.is_none() {
+                    self.filename = Some(filename.to_string());
+                }
+                if self.filename.as_deref() == Some(filename) {
+                    self.rope.mark_saved();
+                    self.discard_swap();
+                }
+                true
+            }
+            Err(e) => {
+                self.notify(Severity::Error, format!("Save failed: {}", e));
+                false
+            }
+        }
+    }
+
+    // Makes the edits `trim_trailing_ws` and `final_newline` ask for as one
+    // undo entry, or none when the text is already clean. The save adds the
+    // final '\n' itself, so `final_newline` removes every '\n' at the end of
+    // the rope. The cursor only moves to stay inside a line that got shorter.
+    pub fn apply_save_fixups(&mut self) {
+        let mut edited = false;
+        if self.trim_trailing_ws {
+            let edits: Vec<_> = (0..self.line_count())
+                .filter_map(|y| {
+                    let line = self.line(y);
+                    let trimmed = line.trim_end_matches([' ', '\t']);
+                    (trimmed.len() < line.len())
+                        .then(|| (self.line_start(y) + trimmed.chars().count(), self.line_end(y), String::new()))
+                })
+                .collect();
+            if !edits.is_empty() {
+                self.rope.apply_edits(&edits).expect("trimmed ranges lie inside their lines");
+                edited = true;
+            }
+        }
+
+        if self.final_newline {
+            let len = self.rope.len();
+            let newlines = (0..len).rev().take_while(|&i| self.rope.get_char(i) == Some('\n')).count();
+            if newlines > 0 {
+                self.rope.remove_clamped(len - newlines, len);
+                if edited {
+                    self.rope.merge_last_undo();
+                }
+            }
+        }
+
+        self.cursor_y = self.cursor_y.min(self.line_count() - 1);
+        self.cursor_x = self.cursor_x.min(self.line(self.cursor_y).len());
+        self.scroll_to_cursor();
+    }
+
+    // Every line is written with a terminator, including the last one.
+    fn save_to_file(&self, filename: &str) -> io::Result<()> {
+        let mut file = io::BufWriter::new(File::create(filename)?);
+        self.rope.write_to(&mut file)?;
+        file.write_all(self.rope.line_ending().as_str().as_bytes())?;
+        file.flush()
+    }
+
+    // Both leave the cursor where the rope says the change was.
+    pub fn undo(&mut self) {
+        match self.rope.undo() {
+            Some((y, col)) => self.set_cursor_line_col(y, col),
+            None => self.notify(Severity::Warning, "Already at oldest change"),
+        }
+    }
+
+    pub fn redo(&mut self) {
+        match self.rope.redo() {
+            Some((y, col)) => self.set_cursor_line_col(y, col),
+            None => self.notify(Severity::Warning, "Already at newest change"),
+        }
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        if c == '\t' && self.expand_tabs {
+            let line = self.line(self.cursor_y);
+            self.cursor_x = unicode::floor_boundary(&line, self.cursor_x);
+            let column = unicode::display_width(&line[..self.cursor_x], self.tab_width);
+            let tab_width = self.tab_width.max(1);
+            let spaces = tab_width - column % tab_width;
+            self.rope.insert(self.cursor_index(), &" ".repeat(spaces));
+            self.cursor_x += spaces;
+        } else if c == '\t' || !c.is_control() {
+            self.cursor_x = unicode::floor_boundary(&self.line(self.cursor_y), self.cursor_x);
+            self.rope.insert_char(self.cursor_index(), c);
+            self.cursor_x += c.len_utf8();
+        }
+    }
+
+    pub fn delete_char(&mut self) {
+        let spaces = self.indent_spaces_to_delete();
+        if spaces > 0 {
+            self.rope.remove_clamped(self.cursor_index() - spaces, self.cursor_index());
+            self.cursor_x -= spaces;
+        } else if let Some(index) = self.cursor_index().checked_sub(1) {
+            // The rope finds the grapheme before the cursor by itself, a '\n'
+            // there joins the line onto the previous one.
+            let start = match self.rope.get_char(index) {
+                Some('\n') => index,
+                _ => self.rope.prev_grapheme_boundary(index + 1),
+            };
+            self.rope.remove_clamped(start, index + 1);
+            self.set_cursor_index(start);
+
+            if self.cursor_y < self.scroll_y {
+                self.scroll_y = self.cursor_y;
+            }
+        }
+
+        if self.cursor_y == 0 {
+            self.scroll_y = 0;
+        }
+    }
+
+    // Removes the grapheme under the cursor, or joins the next line when the
+    // cursor is at the end of its line.
+    pub fn delete_forward(&mut self) {
+        let line = self.line(self.cursor_y);
+        if self.cursor_x < line.len() {
+            let end = unicode::next_boundary_in(&line, self.cursor_x);
+            self.rope.remove_clamped(self.cursor_index(), self.char_index(end, self.cursor_y));
+        } else if self.cursor_y + 1 < self.line_count() {
+            let index = self.cursor_index();
+            self.rope.remove_clamped(index, index + 1);
+        }
+    }
+
+    // Normal mode `x`: like Delete, but it never joins lines. The removed text
+    // goes to the register.
+    pub fn delete_char_under_cursor(&mut self) {
+        self.apply_operator('d', Motion::Chars, 1);
+    }
+
+    // With auto-indent the new line gets the leading whitespace before the
+    // cursor. A line holding nothing but that indentation is left empty
+    // instead of keeping it as trailing whitespace.
+    pub fn insert_new_line(&mut self) {
+        let line = self.line(self.cursor_y);
+        let indent = if self.auto_indent { indentation(&line[..self.cursor_x]) } else { "" };
+        let text = format!("\n{}", indent);
+        if !indent.is_empty() && line.trim().is_empty() {
+            let start = self.line_start(self.cursor_y);
+            self.rope.replace_range(start, start + line.chars().count(), &text);
+        } else {
+            self.rope.insert(self.cursor_index(), &text);
+        }
+        self.cursor_y += 1;
+        self.cursor_x = indent.len();
+
+        if self.cursor_y >= self.scroll_y + self.screen_height {
+            self.scroll_y += 1;
+        }
+    }
+
+    // How many spaces Backspace takes at the cursor: back to the previous tab
+    // stop while only spaces come before it on the line, 0 when it should
+    // just delete one grapheme.
+    fn indent_spaces_to_delete(&self) -> usize {
+        if !self.auto_indent {
+            return 0;
+        }
+        let line = self.line(self.cursor_y);
+        let before = &line[..self.cursor_x.min(line.len())];
+        if before.is_empty() || indentation(before) != before {
+            return 0;
+        }
+
+        let trailing = before.len() - before.trim_end_matches(' ').len();
+        let column = unicode::display_width(before, self.tab_width);
+        let stop = (column - 1) / self.tab_width.max(1) * self.tab_width.max(1);
+        trailing.min(column - stop)
+    }
+
+    // The selected chars as a rope range. Like in vim the selection includes
+    // the grapheme under whichever end comes last.
+    pub fn selection_range(&self) -> Option<(usize, usize)> {
+        let Mode::Visual { anchor: (x, y) } = self.mode else {
+            return None;
+        };
+        let anchor = self.char_index(x, y);
+        let cursor = self.cursor_index();
+        let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+        Some((start, self.rope.next_grapheme_boundary(end)))
+    }
+
+    pub fn yank_selection(&mut self) {
+        if let Some((start, end)) = self.selection_range() {
+            self.register = self.rope.text_range(start, end);
+        }
+    }
+
+    // Removes the selection as a single undo entry and leaves the cursor at
+    // its start.
+    pub fn delete_selection(&mut self) {
+        if let Some((start, end)) = self.selection_range() {
+            self.register = self.rope.text_range(start, end);
+            self.rope.remove_clamped(start, end);
+            self.set_cursor_index(start);
+        }
+    }
+
+    pub fn yank_line(&mut self) {
+        self.apply_operator('y', Motion::Lines, 1);
+    }
+
+    // The chars `dd` removes for line `y`: the line along with one of the '\n's
+    // around it, the one before it when it is the last line.
+    pub fn line_removal_range(&self, y: usize) -> (usize, usize) {
+        self.lines_removal_range(y, 1)
+    }
+
+    // Like `line_removal_range` for `count` lines from `y` on, fewer when the
+    // document ends first.
+    pub fn lines_removal_range(&self, y: usize, count: usize) -> (usize, usize) {
+        let line_count = self.line_count();
+        let last = y.saturating_add(count.max(1)).min(line_count) - 1;
+        if y == 0 && last + 1 == line_count {
+            (0, self.rope.len())
+        } else if last + 1 < line_count {
+            (self.line_start(y), self.line_start(last + 1))
+        } else {
+            (self.line_start(y) - 1, self.rope.len())
+        }
+    }
+
+    // Copies `count` lines from `y` on and inserts them below the last one as
+    // one undo entry. The cursor moves the same number of lines down, so it
+    // lands on the copy at the column it had.
+    pub fn duplicate_lines(&mut self, y: usize, count: usize) {
+        let last = y.saturating_add(count.max(1)).min(self.line_count()) - 1;
+        let block: Vec<String> = (y..=last).map(|line| self.line(line)).collect();
+        self.rope.insert_line(last + 1, &block.join("\n"));
+        self.cursor_y += block.len();
+        self.scroll_to_cursor();
+    }
+
+    // Duplicates every line the selection touches, below the last of them.
+    pub fn duplicate_selection(&mut self) {
+        let Mode::Visual { anchor: (_, anchor_y) } = self.mode else {
+            return;
+        };
+        let first = anchor_y.min(self.cursor_y);
+        self.duplicate_lines(first, anchor_y.max(self.cursor_y) - first + 1);
+    }
+
+    // Vim's `J`: joins `count` lines from `y` on into one, replacing each
+    // line break and the indentation after it with a single space, as one
+    // undo entry. Unlike vim the space goes in before punctuation like `)`
+    // too; only an empty line on either side gets none. The cursor ends up
+    // at the last join. On the last line it does nothing.
+    pub fn join_lines(&mut self, y: usize, count: usize) {
+        let last = y.saturating_add(count.max(2) - 1).min(self.line_count() - 1);
+        let mut edits = Vec::new();
+        let mut shift = 0;
+        let mut cursor = None;
+        let mut joined_empty = self.line(y).is_empty();
+        for line in y..last {
+            let next = self.line(line + 1);
+            let indent = indentation(&next).chars().count();
+            let next_empty = next.len() == indentation(&next).len();
+            let separator = if joined_empty || next_empty { "" } else { " " };
+            joined_empty &= next_empty;
+
+            let start = self.line_end(line);
+            let end = self.line_start(line + 1) + indent;
+            cursor = Some(start - shift);
+            shift += end - start - separator.len();
+            edits.push((start, end, separator.to_string()));
+        }
+
+        let Some(cursor) = cursor else {
+            return;
+        };
+        self.rope.apply_edits(&edits).expect("join ranges lie between lines");
+        self.set_cursor_index(cursor);
+    }
+
+    // Joins every line the selection touches, at least two.
+    pub fn join_selection(&mut self) {
+        let Mode::Visual { anchor: (_, anchor_y) } = self.mode else {
+            return;
+        };
+        let first = anchor_y.min(self.cursor_y);
+        self.join_lines(first, anchor_y.max(self.cursor_y) - first + 1);
+    }
+
+    pub fn delete_line(&mut self) {
+        self.apply_operator('d', Motion::Lines, 1);
+    }
+
+    // The rope range `count` of `motion` covers from the cursor. Chars and
+    // the last word stop at the end of the line, like `x` and `dw` in vim.
+    pub fn motion_range(&self, motion: Motion, count: usize) -> (usize, usize) {
+        let start = self.cursor_index();
+        let line_end = self.line_end(self.cursor_y);
+        match motion {
+            Motion::Lines => self.lines_removal_range(self.cursor_y, count),
+            Motion::Chars => {
+                let mut end = start;
+                for _ in 0..count.max(1) {
+                    if end >= line_end {
+                        break;
+                    }
+                    end = self.rope.next_grapheme_boundary(end);
+                }
+                (start, end.min(line_end))
+            }
+            Motion::WordForward => {
+                let count = count.max(1);
+                let mut end = start;
+                for step in 1..=count {
+                    let from = end;
+                    end = self.rope.next_word_boundary(from);
+                    if step == count {
+                        let line = self.rope.line_col(from).0;
+                        let from_line_end = self.line_end(line);
+                        if from < from_line_end {
+                            end = end.min(from_line_end);
+                        }
+                    } else if end >= self.rope.len() {
+                        break;
+                    }
+                }
+                (start, end)
+            }
+            Motion::WordBackward => {
+                let mut begin = start;
+                for _ in 0..count.max(1) {
+                    if begin == 0 {
+                        break;
+                    }
+                    begin = self.rope.prev_word_boundary(begin);
+                }
+                (begin, start)
+            }
+        }
+    }
+
+    // Runs `operator` ('d' or 'y') over `count` of `motion`. Deletes are a
+    // single rope edit, so they undo in one step however big the count.
+    pub fn apply_operator(&mut self, operator: char, motion: Motion, count: usize) {
+        let (start, end) = self.motion_range(motion, count);
+        if motion == Motion::Lines {
+            let last = (self.cursor_y + count.max(1)).min(self.line_count());
+            let lines: Vec<String> = (self.cursor_y..last).map(|y| self.line(y)).collect();
+            self.register = format!("{}\n", lines.join("\n"));
+            if operator == 'd' {
+                self.rope.remove_clamped(start, end);
+                self.cursor_y = self.cursor_y.min(self.line_count() - 1);
+                self.cursor_x = 0;
+                self.scroll_to_cursor();
+            }
+        } else if operator == 'd' {
+            self.delete_to_register(start, end);
+        } else if start < end {
+            self.register = self.rope.text_range(start, end);
+        }
+    }
+
+    // Repeats a cursor motion `count` times, stopping early once it no longer
+    // moves the cursor so a huge count at the end of the document is cheap.
+    fn repeat_motion(&mut self, count: usize, mut motion: impl FnMut(&mut Self)) {
+        for _ in 0..count.max(1) {
+            let before = (self.cursor_x, self.cursor_y);
+            motion(self);
+            if (self.cursor_x, self.cursor_y) == before {
+                break;
+            }
+        }
+    }
+
+    // A register ending in '\n' holds whole lines and is pasted below/above the
+    // current line, with the cursor on the first of them. Anything else goes
+    // inline after/before the cursor, which ends up just past the pasted text.
+    pub fn paste(&mut self, after: bool) {
+        if self.register.is_empty() {
+            return;
+        }
+
+        let text = self.register.clone();
+
+        if let Some(block) = text.strip_suffix('\n') {
+            let at = if after { self.cursor_y + 1 } else { self.cursor_y };
+            if at < self.line_count() {
+                self.rope.insert(self.line_start(at), &text);
+            } else {
+                self.rope.insert(self.rope.len(), &format!("\n{}", block));
+            }
+            self.cursor_y = at;
+            self.cursor_x = 0;
+        } else {
+            let line = self.line(self.cursor_y);
+            let at = if after && !line.is_empty() {
+                unicode::next_boundary_in(&line, self.cursor_x)
+            } else {
+                unicode::floor_boundary(&line, self.cursor_x)
+            };
+
+            let index = self.char_index(at, self.cursor_y);
+            self.rope.insert(index, &text);
+            self.set_cursor_index(index + text.chars().count());
+        }
+
+        self.scroll_to_cursor();
+    }
+
+    // Word motions follow the rope's vim-style boundaries. Past the last word
+    // the cursor stops on the last char rather than after it.
+    pub fn word_forward(&mut self) {
+        let index = self.rope.next_word_boundary(self.cursor_index());
+        self.move_to_word(index);
+    }
+
+    pub fn word_backward(&mut self) {
+        let index = self.rope.prev_word_boundary(self.cursor_index());
+        self.move_to_word(index);
+    }
+
+    pub fn word_end(&mut self) {
+        let index = self.rope.next_word_end(self.cursor_index());
+        self.move_to_word(index);
+    }
+
+    fn move_to_word(&mut self, index: usize) {
+        let len = self.rope.len();
+        if index >= len && !self.line(self.line_count() - 1).is_empty() {
+            self.set_cursor_index(self.rope.prev_grapheme_boundary(len));
+        } else {
+            self.set_cursor_index(index);
+        }
+    }
+
+    // Deletes up to the next word start as one undo entry. Like vim's `dw` it
+    // stops at the end of the line unless the cursor already sits there.
+    pub fn delete_word_forward(&mut self) {
+        let start = self.cursor_index();
+        let line_end = self.line_end(self.cursor_y);
+        let mut end = self.rope.next_word_boundary(start);
+        if start < line_end {
+            end = end.min(line_end);
+        }
+        self.delete_to_register(start, end);
+    }
+
+    pub fn delete_word_backward(&mut self) {
+        let end = self.cursor_index();
+        let start = self.rope.prev_word_boundary(end);
+        self.delete_to_register(start, end);
+    }
+
+    fn delete_to_register(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.register = self.rope.text_range(start, end);
+            self.rope.remove_clamped(start, end);
+            self.set_cursor_index(start);
+        }
+    }
+
+    // Scrolls as little as needed to bring the cursor line into view.
+    pub fn scroll_to_cursor(&mut self) {
+        if self.cursor_y < self.scroll_y {
+            self.scroll_y = self.cursor_y;
+        } else if self.wrap {
+            // Screen rows from the top of the view down to the cursor's row.
+            let cursor_row = self.line_wrap(self.cursor_y).position(self.cursor_x).0;
+            let mut rows = self.rows_between(self.scroll_y, self.cursor_y) + cursor_row;
+            while self.scroll_y < self.cursor_y && rows >= self.screen_height {
+                rows -= self.line_wrap(self.scroll_y).row_count();
+                self.scroll_y += 1;
+            }
+        } else if self.cursor_y >= self.scroll_y + self.screen_height {
+            self.scroll_y = self.cursor_y + 1 - self.screen_height;
+        }
+    }
+
+    // Keeps the cursor's column on screen when long lines run past the right
+    // edge. Wrapped lines always fit, so the view never scrolls sideways then.
+    pub fn scroll_x_to_cursor(&mut self) {
+        if self.wrap {
+            self.scroll_x = 0;
+            return;
+        }
+
+        let column = unicode::display_width(&self.line(self.cursor_y)[..self.cursor_x], self.tab_width);
+        let width = self.text_width().max(1);
+        if column < self.scroll_x {
+            self.scroll_x = column;
+        } else if column >= self.scroll_x + width {
+            self.scroll_x = column + 1 - width;
+        }
+    }
+
+    // Columns left for text next to the gutter.
+    fn text_width(&self) -> usize {
+        let gutter = format_gutter(0, 0, self.line_count(), self.line_numbers).len();
+        self.screen_width.saturating_sub(gutter)
+    }
+
+    // Line `y` cut into screen rows, or left whole when wrapping is off.
+    pub fn line_wrap(&self, y: usize) -> LineWrap {
+        let line = self.line(y);
+        if self.wrap {
+            LineWrap::new(&line, self.text_width(), self.tab_width)
+        } else {
+            LineWrap::unwrapped(&line, self.tab_width)
+        }
+    }
+
+    // Screen rows taken by lines `from..to`.
+    fn rows_between(&self, from: usize, to: usize) -> usize {
+        (from..to).map(|y| self.line_wrap(y).row_count()).sum()
+    }
+
+    // Up or Down one screen row inside a wrapped line, keeping the column.
+    fn move_screen_row(&mut self, down: bool) {
+        let wrap = self.line_wrap(self.cursor_y);
+        let (row, column) = wrap.position(self.cursor_x);
+        if down && row + 1 < wrap.row_count() {
+            self.cursor_x = wrap.byte_at(row + 1, column);
+        } else if down && self.cursor_y < self.line_count() - 1 {
+            self.cursor_y += 1;
+            self.cursor_x = self.line_wrap(self.cursor_y).byte_at(0, column);
+        } else if !down && row > 0 {
+            self.cursor_x = wrap.byte_at(row - 1, column);
+        } else if !down && self.cursor_y > 0 {
+            self.cursor_y -= 1;
+            let wrap = self.line_wrap(self.cursor_y);
+            self.cursor_x = wrap.byte_at(wrap.row_count() - 1, column);
+        }
+        self.scroll_to_cursor();
+    }
+
+    // Up or Down one logical line, keeping the byte column where it can.
+    // Swaps the cursor line with the one above or below, and the cursor goes
+    // with it. Moves in a row are merged into one undo entry.
+    pub fn move_current_line(&mut self, down: bool) {
+        let target = match down {
+            true if self.cursor_y + 1 < self.line_count() => self.cursor_y + 1,
+            false if self.cursor_y > 0 => self.cursor_y - 1,
+            _ => return,
+        };
+        self.rope.swap_lines(self.cursor_y, target);
+        if self.line_move_burst {
+            self.rope.merge_last_undo();
+        }
+        self.line_move_burst = true;
+        self.cursor_y = target;
+        self.scroll_to_cursor();
+    }
+
+    pub fn move_line(&mut self, down: bool) {
+        if down && self.cursor_y < self.line_count() - 1 {
+            self.cursor_y += 1;
+        } else if !down && self.cursor_y > 0 {
+            self.cursor_y -= 1;
+        } else {
+            return;
+        }
+        self.cursor_x = unicode::floor_boundary(&self.line(self.cursor_y), self.cursor_x);
+        self.scroll_to_cursor();
+    }
+
+    // Left and Right step over whole graphemes in the rope, so they move across
+    // line ends as well. Up and Down go by screen row when lines wrap.
+    pub fn move_cursor(&mut self, direction: Key) {
+        match direction {
+            Key::ArrowLeft => {
+                let index = self.cursor_index();
+                if index > 0 {
+                    self.set_cursor_index(self.rope.grapheme_before(index));
+                }
+            }
+            Key::ArrowRight => {
+                let index = self.cursor_index();
+                if index < self.rope.len() {
+                    self.set_cursor_index(self.rope.grapheme_after(index));
+                }
+            }
+            Key::ArrowUp | Key::ArrowDown if self.wrap => self.move_screen_row(direction == Key::ArrowDown),
+            Key::ArrowUp | Key::ArrowDown => self.move_line(direction == Key::ArrowDown),
+            Key::Home => self.cursor_x = 0,
+            Key::End => self.cursor_x = self.line(self.cursor_y).len(),
+            // The view and the cursor move a screenful together, stopping where
+            // the last line is at the bottom of the screen.
+            Key::PageUp => {
+                self.cursor_y = self.cursor_y.saturating_sub(self.screen_height);
+                self.scroll_y = self.scroll_y.saturating_sub(self.screen_height);
+                self.cursor_x = unicode::floor_boundary(&self.line(self.cursor_y), self.cursor_x);
+                self.scroll_to_cursor();
+            }
+            Key::PageDown => {
+                let last_line = self.line_count() - 1;
+                let max_scroll = self.line_count().saturating_sub(self.screen_height);
+                self.cursor_y = (self.cursor_y + self.screen_height).min(last_line);
+                self.scroll_y = (self.scroll_y + self.screen_height).min(max_scroll);
+                self.cursor_x = unicode::floor_boundary(&self.line(self.cursor_y), self.cursor_x);
+                self.scroll_to_cursor();
+            }
+            _ => {}
+        }
+    }
+
+    // Colors the text with `highlighter` from now on.
+    pub fn set_highlighter(&mut self, highlighter: Box<dyn Highlighter>) {
+        self.highlighter = Some(highlighter);
+        self.reset_highlights();
+    }
+
+    // Forgets every line's spans, for when the whole rope is replaced. The new
+    // rope reports its changes from here on.
+    fn reset_highlights(&mut self) {
+        self.highlights.clear();
+        if self.highlighter.is_some() {
+            self.rope.track_changes();
+        }
+    }
+
+    // Drops the spans of every line from the first one edited since the last
+    // refresh, since lines after an edit may have moved, then asks the
+    // highlighter for the lines on screen that have none. `render_to` runs
+    // this before each frame.
+    pub fn refresh_highlights(&mut self) {
+        let Some(mut highlighter) = self.highlighter.take() else {
+            return;
+        };
+        if let Some(start) = self.rope.take_changes().iter().map(|change| change.range.start).min() {
+            let line = self.rope.line_col(start.min(self.rope.len())).0;
+            self.highlights.truncate(line);
+        }
+
+        let line_count = self.line_count();
+        for (index, rows) in self.layout().windows.iter().enumerate() {
+            let scroll_y = if index == self.active_window { self.scroll_y } else { self.windows[index].scroll_y };
+            for line_index in scroll_y..(scroll_y + rows.len()).min(line_count) {
+                if self.highlights.get(line_index).is_some_and(Option::is_some) {
+                    continue;
+                }
+                let spans = highlighter.highlight_line(line_index, &self.line(line_index));
+                if self.highlights.len() <= line_index {
+                    self.highlights.resize(line_index + 1, None);
+                }
+                self.highlights[line_index] = Some(spans);
+            }
+        }
+        self.highlighter = Some(highlighter);
+    }
+
+    pub fn render(&mut self) {
+        let mut stdout = io::stdout().lock();
+        self.render_to(&mut stdout).unwrap();
+    }
+
+    // Writes only the rows that differ from the previous frame, in one batch.
+    // When nothing changed the only output is the cursor move.
+    pub fn render_to(&mut self, out: &mut impl Write) -> io::Result<()> {
+        self.scroll_x_to_cursor();
+        self.refresh_highlights();
+        let frame = self.compose_frame();
+        let mut output = String::new();
+
+        for (i, entry) in frame.iter().enumerate() {
+            if self.screen_cache.get(i) != Some(entry) {
+                output.push_str(&format!("\x1b[{};1H\x1b[K{}", entry.0, entry.1));
+            }
+        }
+        if !output.is_empty() {
+            output = format!("\x1b[?25l{}\x1b[?25h", output);
+        }
+        self.screen_cache = frame;
+
+        let (row, column) = self.cursor_screen_position();
+        output.push_str(&format!("\x1b[{};{}H", row, column));
+
+        out.write_all(output.as_bytes())?;
+        out.flush()
+    }
+
+    // Every screen row as (row, text), rows counted from 1 like the terminal
+    // does: the status bar and the message or prompt line below the text
+    // area, then the text area itself padded with empty rows.
+    pub fn compose_frame(&self) -> Vec<(usize, String)> {
+        let mode_display = match self.mode {
+            Mode::Normal => "-- NORMAL --",
+            Mode::Insert => "-- INSERT --",
+            Mode::Command => "-- COMMAND --",
+            Mode::Search => "-- SEARCH --",
+            Mode::Visual { .. } => "-- VISUAL --",
+        };
+        let line = self.line(self.cursor_y);
+        let column = unicode::display_width(&line[..self.cursor_x.min(line.len())], self.tab_width) + 1;
+        let status = StatusBar {
+            mode: mode_display,
+            filename: self.filename.as_deref(),
+            modified: self.is_modified(),
+            file_format: self.rope.line_ending().file_format(),
+            recording: self.recording.as_ref().map(|(register, _)| *register),
+            line: self.cursor_y + 1,
+            column,
+            line_count: self.line_count(),
+        };
+
+        let prompt = match (&self.mode, &self.message) {
+            (Mode::Search, _) => format!("/{}", self.search_input),
+            (Mode::Command, _) => format!(":{}", self.command_input),
+            (_, Some((severity, text))) => severity.paint(text),
+            (_, None) => String::new(),
+        };
+
+        let status_row = TEXT_TOP + self.text_area_height();
+        let layout = self.layout();
+        let bracket = self.highlighted_bracket();
+        let mut frame = vec![(status_row, status.format(self.screen_width)), (status_row + 1, prompt)];
+        for (index, rows) in layout.windows.iter().enumerate() {
+            let active = index == self.active_window;
+            let view = if active { None } else { Some(self.windows[index]) };
+            let scroll_y = view.map_or(self.scroll_y, |window| window.scroll_y);
+            let cursor_y = view.map_or(self.cursor_y, |window| window.cursor_y);
+            let scroll_x = view.map_or(self.scroll_x, |window| window.scroll_x);
+            let texts = self.window_rows(scroll_y, scroll_x, cursor_y, rows.len(), bracket);
+            frame.extend(rows.clone().map(|row| row + TEXT_TOP).zip(texts));
+
+            if let Some(row) = layout.status_row(index) {
+                let marker = if active { '*' } else { ' ' };
+                let name = self.filename.as_deref().unwrap_or("[No Name]");
+                frame.push((row + TEXT_TOP, format!("{} {}  {}", marker, name, self.position(cursor_y))));
+            }
+        }
+        frame
+    }
+
+    // The `height` rows of a window scrolled to `scroll_y`, padded with empty
+    // rows past the end of the document. Rows that continue a wrapped line get
+    // a blank gutter.
+    fn window_rows(&self, scroll_y: usize, scroll_x: usize, cursor_y: usize, height: usize, bracket: Option<usize>) -> Vec<String> {
+        let line_count = self.line_count();
+        let gutter_width = format_gutter(0, 0, line_count, self.line_numbers).len();
+        let mut texts = Vec::with_capacity(height);
+        let mut line_index = scroll_y;
+        while texts.len() < height && line_index < line_count {
+            let wrap = self.line_wrap(line_index);
+            for row in 0..wrap.row_count().min(height - texts.len()) {
+                let gutter = match row {
+                    0 => format_gutter(line_index, cursor_y, line_count, self.line_numbers),
+                    _ => " ".repeat(gutter_width),
+                };
+                texts.push(format!("{}{}", gutter, self.visible_row(line_index, &wrap, row, scroll_x, bracket)));
+            }
+            line_index += 1;
+        }
+        texts.resize(height, String::new());
+        texts
+    }
+
+    // The part of `row` that fits on screen, highlighted. Unwrapped rows are
+    // cut to the columns from `scroll_x` on. `bracket` is the char index of a
+    // matching bracket to invert.
+    fn visible_row(&self, line_index: usize, wrap: &LineWrap, row: usize, scroll_x: usize, bracket: Option<usize>) -> String {
+        let expanded = wrap.row_text(row);
+        let (text, offset) = if self.wrap {
+            (expanded, 0)
+        } else {
+            let from = unicode::byte_at_column(&expanded, scroll_x, self.tab_width);
+            let to = unicode::byte_at_column(&expanded, scroll_x + self.text_width(), self.tab_width);
+            (expanded[from..to].to_string(), unicode::display_width(&expanded[..from], self.tab_width))
+        };
+        let range = self.selection_range().or(bracket.map(|index| (index, index + 1)));
+        if let Some(highlighted) = range.and_then(|range| self.highlight_range(line_index, wrap, row, &text, offset, range)) {
+            return highlighted;
+        }
+        match self.highlights.get(line_index) {
+            Some(Some(spans)) if !spans.is_empty() && !self.shows_matches(&text) => {
+                self.paint_spans(line_index, wrap, row, &text, offset, spans)
+            }
+            _ => self.highlight_matches(text),
+        }
+    }
+
+    // "line/total (percent%)" for a cursor on line `y`.
+    fn position(&self, y: usize) -> String {
+        let line_count = self.line_count();
+        let y = y.min(line_count - 1);
+        format!("{}/{} ({}%)", y + 1, line_count, (y + 1) * 100 / line_count)
+    }
+
+    // 1-based terminal (row, column) of the cursor, past the line number gutter.
+    // While a prompt is open the cursor sits at its end instead.
+    fn cursor_screen_position(&self) -> (usize, usize) {
+        let prompt = match self.mode {
+            Mode::Search => Some(&self.search_input),
+            Mode::Command => Some(&self.command_input),
+            _ => None,
+        };
+        if let Some(input) = prompt {
+            return (TEXT_TOP + self.text_area_height() + 1, unicode::str_width(input) + 2);
+        }
+
+        let gutter = format_gutter(self.cursor_y, self.cursor_y, self.line_count(), self.line_numbers).len();
+        let top = self.layout().windows.get(self.active_window).map_or(0, |rows| rows.start);
+        let rows_above = match self.wrap {
+            true => self.rows_between(self.scroll_y, self.cursor_y),
+            false => self.cursor_y.saturating_sub(self.scroll_y),
+        };
+        let (cursor_row, column) = self.line_wrap(self.cursor_y).position(self.cursor_x);
+        (TEXT_TOP + top + rows_above + cursor_row, column.saturating_sub(self.scroll_x) + gutter + 1)
+    }
+
+    // Inverts the chars `start..end` in `text`, the visible piece of `row`
+    // starting `offset` columns in, or gives `None` when they don't touch it.
+    // Columns are found on the expanded row, so tabs inside the range are
+    // inverted in full.
+    fn highlight_range(&self, line_index: usize, wrap: &LineWrap, row: usize, text: &str, offset: usize, (start, end): (usize, usize)) -> Option<String> {
+        let (from, to) = self.bytes_in_row(line_index, wrap, row, text, offset, (start, end))?;
+
+        // A selected line break shows as one inverted cell past the text, when
+        // the end of the line is in view.
+        let line_end = self.line_end(line_index);
+        let last_row = row + 1 == wrap.row_count();
+        let shows_end = offset + unicode::display_width(text, self.tab_width) == wrap.column_in_row(row, usize::MAX);
+        let newline = if last_row && end > line_end && shows_end { " " } else { "" };
+        Some(format!("{}\x1b[7m{}{}\x1b[0m{}", &text[..from], &text[from..to], newline, &text[to..]))
+    }
+
+    // Colors `text`, the visible piece of `row`, with the highlighter's spans
+    // for the line. Spans are clipped to what's in view.
+    fn paint_spans(&self, line_index: usize, wrap: &LineWrap, row: usize, text: &str, offset: usize, spans: &[StyleSpan]) -> String {
+        let line_start = self.line_start(line_index);
+        let mut painted = String::new();
+        let mut done = 0;
+        for span in spans {
+            let range = (line_start + span.start_col, line_start + span.end_col);
+            let Some((from, to)) = self.bytes_in_row(line_index, wrap, row, text, offset, range) else {
+                continue;
+            };
+            if from < done || from == to {
+                continue;
+            }
+            painted.push_str(&text[done..from]);
+            painted.push_str(&format!("{}{}\x1b[0m", span.escape(), &text[from..to]));
+            done = to;
+        }
+        painted.push_str(&text[done..]);
+        painted
+    }
+
+    // The bytes of `text` covering the chars `start..end` of the document,
+    // where `text` is the visible piece of `row` starting `offset` columns in.
+    // `None` when the chars don't touch the row. Columns are found on the
+    // expanded row, so a tab inside the range is covered in full.
+    fn bytes_in_row(&self, line_index: usize, wrap: &LineWrap, row: usize, text: &str, offset: usize, (start, end): (usize, usize)) -> Option<(usize, usize)> {
+        let line = self.line(line_index);
+        let range = wrap.row_range(row);
+        let last_row = row + 1 == wrap.row_count();
+        let line_start = self.line_start(line_index);
+        let row_start = line_start + line[..range.start].chars().count();
+        let row_end = line_start + line[..range.end].chars().count();
+        if end <= row_start || start > row_end || (start == row_end && !last_row) {
+            return None;
+        }
+
+        let to_byte = |index: usize| {
+            let chars = index.clamp(row_start, row_end) - line_start;
+            line.char_indices().nth(chars).map_or(line.len(), |(x, _)| x)
+        };
+        let column = |index: usize| wrap.column_in_row(row, to_byte(index)).saturating_sub(offset);
+        let from = unicode::byte_at_column(text, column(start), self.tab_width);
+        let to = unicode::byte_at_column(text, column(end), self.tab_width);
+        Some((from, to))
+    }
+
+    // Search matches win over the highlighter's colors, on rows they're in.
+    fn shows_matches(&self, text: &str) -> bool {
+        self.search_pattern().is_some_and(|pattern| text.contains(pattern))
+    }
+
+    fn search_pattern(&self) -> Option<&str> {
+        let pattern = match self.mode {
+            Mode::Search => &self.search_input,
+            _ => match &self.last_search {
+                Some(pattern) if self.search_match.is_some() => pattern,
+                _ => return None,
+            },
+        };
+        Some(pattern.as_str()).filter(|pattern| !pattern.is_empty())
+    }
+
+    fn highlight_matches(&self, line: String) -> String {
+        match self.search_pattern() {
+            Some(pattern) => line.replace(pattern, &format!("\x1b[7m{}\x1b[0m", pattern)),
+            None => line,
+        }
+    }
+}
+
+// The spaces and tabs `line` starts with.
+pub fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+// The gutter in front of line `line_index`, numbers right-aligned to the width
+// of `total` so the text starts in the same column on every row. Relative
+// distances never exceed `total`, which keeps that width for both formats.
+pub fn format_gutter(line_index: usize, cursor_line: usize, total: usize, line_numbers: LineNumbers) -> String {
+    match line_numbers.mode() {
+        Some(mode) => format!(
+            "{:>width$} | ",
+            line_number_label(line_index, cursor_line, mode),
+            width = total.to_string().len()
+        ),
+        None => String::new(),
+    }
+}
+
+// The number shown for line `line_index` with the cursor on `cursor_line`.
+pub fn line_number_label(line_index: usize, cursor_line: usize, mode: LineNumberMode) -> String {
+    let number = match mode {
+        LineNumberMode::Absolute => line_index + 1,
+        LineNumberMode::Hybrid if line_index == cursor_line => line_index + 1,
+        LineNumberMode::Relative | LineNumberMode::Hybrid => line_index.abs_diff(cursor_line),
+    };
+    number.to_string()
+}
+
+// Parses `s/pattern/replacement/[g]` and `%s/...`. A `\/` inside either part
+// is a literal slash, the trailing slash is optional.
+pub fn parse_substitution(command: &str) -> Option<Substitution> {
+    let (whole_file, rest) = match command.strip_prefix('%') {
+        Some(rest) => (true, rest),
+        None => (false, command),
+    };
+    let rest = rest.strip_prefix("s/")?;
+
+    let mut parts = vec![String::new()];
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('/') => parts.last_mut()?.push('/'),
+                Some(other) => {
+                    parts.last_mut()?.push('\\');
+                    parts.last_mut()?.push(other);
+                }
+                None => parts.last_mut()?.push('\\'),
+            },
+            '/' => parts.push(String::new()),
+            _ => parts.last_mut()?.push(c),
+        }
+    }
+
+    if parts.len() > 3 || parts[0].is_empty() {
+        return None;
+    }
+
+    let flags = parts.get(2).map(String::as_str).unwrap_or("");
+    if flags.chars().any(|flag| flag != 'g') {
+        return None;
+    }
+
+    Some(Substitution {
+        pattern: parts[0].clone(),
+        replacement: parts.get(1).cloned().unwrap_or_default(),
+        global: flags.contains('g'),
+        whole_file,
+    })
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+// Foreground colors a highlighter can ask for, the eight every terminal has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Default,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    fn code(self) -> u8 {
+        match self {
+            Color::Default => 39,
+            Color::Black => 30,
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+        }
+    }
+}
+
+// A styled piece of one line. The columns are char offsets into the line's
+// text, before tabs are expanded, with `end_col` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StyleSpan {
+    pub start_col: usize,
+    pub end_col: usize,
+    pub fg: Color,
+    pub bold: bool,
+}
+
+impl StyleSpan {
+    // The escape sequence that starts the span; `\x1b[0m` ends it.
+    pub fn escape(&self) -> String {
+        match self.bold {
+            true => format!("\x1b[1;{}m", self.fg.code()),
+            false => format!("\x1b[{}m", self.fg.code()),
+        }
+    }
+}
+
+// Styles the text of the buffer a line at a time. Lines are only asked for
+// when they're on screen and changed since they were last asked for, so the
+// spans should depend on nothing but `line_idx` and `text`.
+pub trait Highlighter {
+    fn highlight_line(&mut self, line_idx: usize, text: &str) -> Vec<StyleSpan>;
+}
+
+// A small example: line comments and double-quoted strings, for the
+// extensions `for_path` knows.
+#[derive(Debug, Clone)]
+pub struct BasicHighlighter {
+    line_comment: &'static str,
+}
+
+impl BasicHighlighter {
+    pub fn new(line_comment: &'static str) -> Self {
+        BasicHighlighter { line_comment }
+    }
+
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        match extension {
+            "rs" | "c" | "h" | "cpp" | "js" | "ts" | "go" | "java" => Some(BasicHighlighter::new("//")),
+            "py" | "sh" | "rb" | "toml" | "yml" | "yaml" => Some(BasicHighlighter::new("#")),
+            _ => None,
+        }
+    }
+}
+
+impl Highlighter for BasicHighlighter {
+    // A string runs to the next unescaped quote or the end of the line, and
+    // a comment marker inside one doesn't count.
+    fn highlight_line(&mut self, _line_idx: usize, text: &str) -> Vec<StyleSpan> {
+        let mut spans = Vec::new();
+        let mut string_start = None;
+        let mut escaped = false;
+        for (col, (byte, c)) in text.char_indices().enumerate() {
+            match string_start {
+                Some(start) => {
+                    if c == '"' && !escaped {
+                        spans.push(StyleSpan { start_col: start, end_col: col + 1, fg: Color::Green, bold: false });
+                        string_start = None;
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+                None if text[byte..].starts_with(self.line_comment) => {
+                    let end_col = col + text[byte..].chars().count();
+                    spans.push(StyleSpan { start_col: col, end_col, fg: Color::Cyan, bold: false });
+                    return spans;
+                }
+                None if c == '"' => string_start = Some(col),
+                None => {}
+            }
+        }
+        if let Some(start) = string_start {
+            spans.push(StyleSpan { start_col: start, end_col: text.chars().count(), fg: Color::Green, bold: false });
+        }
+        spans
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Entries kept when nothing else is configured.
+pub const DEFAULT_HISTORY_SIZE: usize = 50;
+
+// Past command lines, oldest first, and where Up/Down currently are in them.
+// `draft` holds what was typed before the first Up, so going back down past
+// the newest entry gives it back.
+#[derive(Debug, Clone)]
+pub struct History {
+    entries: Vec<String>,
+    capacity: usize,
+    position: Option<usize>,
+    draft: String,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        History { entries: Vec::new(), capacity, position: None, draft: String::new() }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Drops the oldest entries beyond `capacity`.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    // Adds an executed line and ends any navigation. Empty lines and a repeat
+    // of the newest entry are not added.
+    pub fn push(&mut self, entry: &str) {
+        self.reset();
+        if entry.is_empty() || self.entries.last().is_some_and(|last| last == entry) {
+            return;
+        }
+        self.entries.push(entry.to_string());
+        self.trim();
+    }
+
+    // Steps to the next older entry. `typed` is the current input, kept as the
+    // draft when this is the first step. Stops at the oldest entry.
+    pub fn older(&mut self, typed: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = typed.to_string();
+                self.entries.len() - 1
+            }
+            Some(position) => position.saturating_sub(1),
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    // Steps to the next newer entry, or back to the draft after the newest
+    // one. `None` when not navigating.
+    pub fn newer(&mut self) -> Option<&str> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    // Forgets the navigation and the draft.
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+
+    // One entry per line, oldest first. A missing file is an empty history.
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        self.entries.clear();
+        for line in text.lines() {
+            self.push(line);
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text: String = self.entries.iter().map(|entry| format!("{}\n", entry)).collect();
+        fs::write(path, text)
+    }
+
+    fn trim(&mut self) {
+        if self.entries.len() > self.capacity {
+            self.entries.drain(..self.entries.len() - self.capacity);
+        }
+    }
+}
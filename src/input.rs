@@ -1,60 +1,261 @@
-use std::io::{self, Read};
+use std::cell::Cell;
+use std::io;
 
+// How long to wait for the rest of an escape sequence before taking the ESC
+// as a key of its own.
+pub const ESC_TIMEOUT_MS: i32 = 50;
+
+thread_local! {
+    // A byte read past the end of the last key, the first of the next one.
+    static UNREAD: Cell<Option<u8>> = const { Cell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Key {
     Char(char),
+    // Ctrl plus a letter, given in lower case.
+    Ctrl(char),
     Tab,
     Escape,
     Space,
     Enter,
     Backspace,
+    Delete,
+    CtrlDelete,
     ArrowUp,
     ArrowDown,
     ArrowLeft,
     ArrowRight,
+    CtrlArrowUp,
+    CtrlArrowDown,
+    CtrlArrowLeft,
+    CtrlArrowRight,
+    Home,
+    End,
+    PageUp,
+    PageDown,
     OptionSpace,
+    Mouse(MouseEvent),
     Unknown,
 }
 
-pub fn read_key() -> Key {
-    let mut buffer = [0; 1];
-    let stdin = io::stdin();
+// A mouse report, at the 1-based terminal cell it happened on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseEvent {
+    pub kind: MouseKind,
+    pub column: usize,
+    pub row: usize,
+}
 
-    if stdin.lock().read_exact(&mut buffer).is_err() {
-        return Key::Unknown;
+// Only the left button is reported, the other ones give `Key::Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseKind {
+    Press,
+    Drag,
+    Release,
+    ScrollUp,
+    ScrollDown,
+}
+
+// A key with the modifiers it was pressed with. `Key::Ctrl` and the Ctrl
+// variants of arrows and Delete always come with `ctrl` set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyPress {
+    pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl KeyPress {
+    pub fn alt(key: Key) -> Self {
+        KeyPress { alt: true, ..KeyPress::from(key) }
     }
+}
 
-    match buffer[0] {
+impl From<Key> for KeyPress {
+    fn from(key: Key) -> Self {
+        let ctrl = matches!(
+            key,
+            Key::Ctrl(_)
+                | Key::CtrlDelete
+                | Key::CtrlArrowUp
+                | Key::CtrlArrowDown
+                | Key::CtrlArrowLeft
+                | Key::CtrlArrowRight
+        );
+        KeyPress { key, ctrl, alt: false }
+    }
+}
+
+// Blocks for the first byte of a key, then gives up on the rest after
+// `ESC_TIMEOUT_MS`, so a lone ESC doesn't wait for the next key. Reads go
+// straight to the file descriptor: bytes held in a userspace buffer would be
+// invisible to `poll`.
+pub fn read_key() -> KeyPress {
+    read_key_timeout(-1).unwrap_or_else(|| Key::Unknown.into())
+}
+
+// Like `read_key`, but waits at most `timeout_ms` for the first byte and
+// gives `None` when no key came.
+pub fn read_key_timeout(timeout_ms: i32) -> Option<KeyPress> {
+    let first = UNREAD.take().or_else(|| read_byte(timeout_ms))?;
+    let mut bytes = std::iter::once(first).chain(std::iter::from_fn(|| read_byte(ESC_TIMEOUT_MS)));
+    let mut unread = None;
+    let key = decode_key(&mut bytes, &mut unread);
+    UNREAD.set(unread);
+    Some(key)
+}
+
+fn read_byte(timeout_ms: i32) -> Option<u8> {
+    let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    loop {
+        match unsafe { libc::poll(&mut fd, 1, timeout_ms) } {
+            0 => return None,
+            n if n < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => continue,
+            n if n < 0 => return None,
+            _ => break,
+        }
+    }
+
+    let mut byte = 0u8;
+    let read = unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) };
+    (read == 1).then_some(byte)
+}
+
+// Decodes the first key in `bytes`, see `decode_key`.
+pub fn decode_bytes(bytes: &[u8]) -> KeyPress {
+    decode_key(&mut bytes.iter().copied(), &mut None)
+}
+
+// Decodes one key from `bytes`, pulling only as many bytes as the key takes.
+// Running out of bytes mid-sequence gives `Escape` for a started escape
+// sequence and `Unknown` otherwise. `unread` carries a byte pulled past the
+// end of a key over to the next call: an ESC right after an ESC is a key of
+// its own, not Alt+Escape, so a quick double ESC gives two.
+pub fn decode_key(bytes: &mut impl Iterator<Item = u8>, unread: &mut Option<u8>) -> KeyPress {
+    let Some(byte) = unread.take().or_else(|| bytes.next()) else {
+        return Key::Unknown.into();
+    };
+
+    if byte == b'\x1b' {
+        return decode_escape(bytes, unread);
+    }
+    decode_byte(byte, bytes).into()
+}
+
+fn decode_byte(byte: u8, bytes: &mut impl Iterator<Item = u8>) -> Key {
+    match byte {
         b'\t' => Key::Tab,
         b' ' => Key::Space,
         b'\n' => Key::Enter,
         b'\x7f' => Key::Backspace,
-        b'\x1b' => {
-            let mut seq = [0, 2];
-            if stdin.lock().read_exact(&mut seq[0..1]).is_ok() {
-                if seq[0] == b'[' {
-                    if stdin.lock().read_exact(&mut seq[1..2]).is_ok() {
-                        return match seq {
-                            [b'[', b'A'] => Key::ArrowUp,
-                            [b'[', b'B'] => Key::ArrowDown,
-                            [b'[', b'C'] => Key::ArrowRight,
-                            [b'[', b'D'] => Key::ArrowLeft,
-                            _ => Key::Escape,
-                        }; 
-                    }
-                }
-                Key::Escape
-            } else {
-                Key::Escape
-            }
+        b'\x1b' => Key::Escape,
+        0x01..=0x08 | 0x0B..=0x1A => Key::Ctrl((b'a' + byte - 1) as char),
+        33..=126 => Key::Char(byte as char),
+        0xC0..=0xF4 => decode_utf8(byte, bytes),
+        _ => Key::Unknown,
+    }
+}
+
+// Reads the continuation bytes that the leading `byte` announces. A no-break
+// space (Option+Space on macOS) is kept apart from other chars.
+fn decode_utf8(byte: u8, bytes: &mut impl Iterator<Item = u8>) -> Key {
+    let len = match byte {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    };
+
+    let mut encoded = vec![byte];
+    encoded.extend(bytes.take(len - 1));
+    match std::str::from_utf8(&encoded).ok().and_then(|s| s.chars().next()) {
+        Some('\u{A0}') => Key::OptionSpace,
+        Some(c) => Key::Char(c),
+        None => Key::Unknown,
+    }
+}
+
+// Handles what follows an ESC: `[` starts a CSI sequence like `1;5C` or `5~`,
+// `O` is the SS3 form some terminals send for Home and End. `[<` starts an
+// SGR mouse report. A second ESC is left in `unread`, any other key right
+// after the ESC is that key with Alt.
+fn decode_escape(bytes: &mut impl Iterator<Item = u8>, unread: &mut Option<u8>) -> KeyPress {
+    match bytes.next() {
+        Some(b'\x1b') => {
+            *unread = Some(b'\x1b');
+            return Key::Escape.into();
         }
-        b'\xC2' => {
-            let mut seq = [0; 1];
-            if stdin.lock().read_exact(&mut seq).is_ok() && seq[0] == b'\xA0' {
-               return Key::OptionSpace
+        Some(b'[') => {}
+        Some(b'O') => {
+            return match bytes.next() {
+                Some(b'H') => Key::Home,
+                Some(b'F') => Key::End,
+                _ => Key::Escape,
             }
-            Key::Unknown
+            .into();
+        }
+        Some(byte) => return KeyPress::alt(decode_byte(byte, bytes)),
+        None => return Key::Escape.into(),
+    }
+
+    // Parameter bytes are digits, ';' and the '<' of mouse reports, the
+    // sequence ends at the first byte in '@'..='~'.
+    let mut params = String::new();
+    let final_byte = loop {
+        match bytes.next() {
+            Some(b @ (b'0'..=b'9' | b';' | b'<')) => params.push(b as char),
+            Some(b @ b'@'..=b'~') => break b,
+            _ => return Key::Escape.into(),
         }
-        32..=126 => Key::Char(buffer[0] as char),
-        _=> Key::Unknown,
+    };
+
+    if let Some(mouse) = params.strip_prefix('<') {
+        return decode_mouse(mouse, final_byte).into();
     }
+
+    // The modifier parameter is one more than a bit set of Shift 1, Alt 2 and
+    // Ctrl 4.
+    let modifiers = params.split(';').nth(1).and_then(|m| m.parse::<u8>().ok()).unwrap_or(1).saturating_sub(1);
+    let ctrl = modifiers & 4 != 0;
+    let alt = modifiers & 2 != 0;
+    let key = match (final_byte, params.split(';').next().unwrap_or("")) {
+        (b'A', _) if ctrl => Key::CtrlArrowUp,
+        (b'B', _) if ctrl => Key::CtrlArrowDown,
+        (b'C', _) if ctrl => Key::CtrlArrowRight,
+        (b'D', _) if ctrl => Key::CtrlArrowLeft,
+        (b'A', _) => Key::ArrowUp,
+        (b'B', _) => Key::ArrowDown,
+        (b'C', _) => Key::ArrowRight,
+        (b'D', _) => Key::ArrowLeft,
+        (b'H', _) => Key::Home,
+        (b'F', _) => Key::End,
+        (b'~', "1" | "7") => Key::Home,
+        (b'~', "3") if ctrl => Key::CtrlDelete,
+        (b'~', "3") => Key::Delete,
+        (b'~', "4" | "8") => Key::End,
+        (b'~', "5") => Key::PageUp,
+        (b'~', "6") => Key::PageDown,
+        _ => Key::Unknown,
+    };
+    KeyPress { key, ctrl, alt }
+}
+
+// `params` is `button;column;row` and `final_byte` is 'M' for a press or
+// motion and 'm' for a release. Bit 32 of the button marks motion with the
+// button held, 64 and 65 are the wheel.
+fn decode_mouse(params: &str, final_byte: u8) -> Key {
+    let numbers: Vec<usize> = params.split(';').filter_map(|n| n.parse().ok()).collect();
+    let [button, column, row] = numbers[..] else {
+        return Key::Unknown;
+    };
+
+    let kind = match (button, final_byte) {
+        (0, b'M') => MouseKind::Press,
+        (32, b'M') => MouseKind::Drag,
+        (0, b'm') => MouseKind::Release,
+        (64, b'M') => MouseKind::ScrollUp,
+        (65, b'M') => MouseKind::ScrollDown,
+        _ => return Key::Unknown,
+    };
+    Key::Mouse(MouseEvent { kind, column, row })
 }
@@ -0,0 +1,54 @@
+use std::ops::Range;
+
+// Windows need at least this many text rows each.
+pub const MIN_WINDOW_HEIGHT: usize = 1;
+
+// How the text area is shared between windows stacked top to bottom. Rows are
+// counted from 0 at the top of the text area. A lone window gets every row,
+// split windows each get a status row right below their text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub windows: Vec<Range<usize>>,
+}
+
+impl Layout {
+    // `None` when `count` windows don't fit in `height` rows. Rows that don't
+    // divide evenly go to the top windows, one each.
+    pub fn compute(height: usize, count: usize) -> Option<Layout> {
+        match count {
+            0 => None,
+            1 => Some(Layout::single(height)),
+            _ => {
+                let text_rows = height.checked_sub(count)?;
+                let base = text_rows / count;
+                if base < MIN_WINDOW_HEIGHT {
+                    return None;
+                }
+
+                let mut start = 0;
+                let windows = (0..count)
+                    .map(|i| {
+                        let rows = base + usize::from(i < text_rows % count);
+                        let window = start..start + rows;
+                        start += rows + 1;
+                        window
+                    })
+                    .collect();
+                Some(Layout { windows })
+            }
+        }
+    }
+
+    pub fn single(height: usize) -> Layout {
+        Layout { windows: std::iter::once(0..height).collect() }
+    }
+
+    // The row below window `index` that shows its status, if it has one.
+    pub fn status_row(&self, index: usize) -> Option<usize> {
+        if self.windows.len() > 1 {
+            self.windows.get(index).map(|window| window.end)
+        } else {
+            None
+        }
+    }
+}
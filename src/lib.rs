@@ -1 +1,13 @@
-pub mod rope;
\ No newline at end of file
+pub mod brackets;
+pub mod buffer;
+pub mod highlight;
+pub mod history;
+pub mod input;
+pub mod layout;
+pub mod rope;
+pub mod status;
+pub mod swap;
+pub mod terminal;
+pub mod unicode;
+pub mod undo;
+pub mod wrap;
@@ -1,32 +1,61 @@
-mod terminal;
-mod input;
-mod buffer;
-
-use ctrlc;
-use terminal::{enable_raw_mode, disable_raw_mode};
+use rawdeo::terminal::{restore_terminal, TerminalGuard};
 use terminal_size::{Height, Width, terminal_size};
-use input::read_key;
-use buffer::TextBuffer;
+use rawdeo::input::read_key_timeout;
+use rawdeo::buffer::TextBuffer;
+use rawdeo::highlight::BasicHighlighter;
+use rawdeo::status::Severity;
+use rawdeo::swap::TICK_MS;
+use std::time::Instant;
 
 fn main() {
-    enable_raw_mode().expect("Failed to enable raw mode");
+    let guard = TerminalGuard::raw_mode().expect("Failed to enable raw mode");
+    guard.install_panic_hook();
 
+    // Ctrl+C is a key in raw mode, this only catches SIGINT sent from outside.
     ctrlc::set_handler(move || {
-        disable_raw_mode();
+        restore_terminal();
         println!("\nRestoring terminal settings... Exiting.");
         std::process::exit(0);
     }).expect("Error setting Ctrl-C handler");
 
     print!("\x1b[2J\x1b[H");
 
-    let (_, Height(h)) = terminal_size().unwrap_or((Width(80), Height(24)));
-    let mut buffer = TextBuffer::new(h as usize -2);
+    let (Width(w), Height(h)) = terminal_size().unwrap_or((Width(80), Height(24)));
+    let mut buffer = match std::env::args().nth(1) {
+        Some(path) => TextBuffer::new_with_path(h as usize, &path).unwrap_or_else(|e| {
+            restore_terminal();
+            eprintln!("Failed to open {}: {}", path, e);
+            std::process::exit(1);
+        }),
+        None => TextBuffer::new(h as usize),
+    };
+    buffer.screen_width = w as usize;
+    if let Some(highlighter) = buffer.filename.as_deref().and_then(|name| BasicHighlighter::for_path(name.as_ref())) {
+        buffer.set_highlighter(Box::new(highlighter));
+    }
 
-    println!("Raw mode enabled! Start typing... (Ctrl+C to exit)");
+    if buffer.message.is_none() {
+        buffer.notify(Severity::Info, "Start typing... (:q to exit)");
+    }
 
-    loop {
+    // Reads give up after a tick, so a resize shows up without a key and
+    // autosave runs while the user is idle.
+    let mut size = (w, h);
+    while !buffer.should_quit {
+        if let Some((Width(w), Height(h))) = terminal_size()
+            && (w, h) != size
+        {
+            size = (w, h);
+            buffer.handle_resize(w as usize, h as usize);
+        }
         buffer.render();
-        let key = read_key();
-        buffer.handle_keypress(key);
+        if let Some(key) = read_key_timeout(TICK_MS) {
+            buffer.handle_keypress(key);
+        }
+        buffer.tick(Instant::now());
     }
+    buffer.discard_swap();
+
+    print!("\x1b[2J\x1b[H");
+    drop(guard);
 }
@@ -1,139 +1,860 @@
-use std::rc::Rc;
-use std::cell::RefCell;
+use std::sync::Arc;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
 
-#[derive(Debug)]
+use crate::undo::{UndoAction, UndoStack};
+use crate::unicode::{self, CharClass};
+
+// Children are shared, not owned: cloning a node is O(1) and edits go
+// through `Arc::make_mut`, which copies a child only while someone else still
+// holds it.
+#[derive(Debug, Clone)]
 enum RopeNode {
     Leaf(String),
     Internal {
-        left: Rc<RefCell<Rope>>,
-        right: Rc<RefCell<Rope>>,
+        left: Arc<Rope>,
+        right: Arc<Rope>,
         left_size: usize,
+        // UTF-8 length of the left subtree, the byte twin of `left_size`.
+        left_bytes: usize,
+        // Same again in UTF-16 code units, for LSP positions.
+        left_utf16: usize,
+        // Subtree totals, kept so line counts and the balance check don't
+        // need a scan.
+        newlines: usize,
+        depth: usize,
+        leaves: usize,
     },
 }
 
-impl Clone for RopeNode {
-    fn clone(&self) -> Self {
-        match self {
-            RopeNode::Leaf(text) => RopeNode::Leaf(text.clone()),
-            RopeNode::Internal { left, right, left_size } => RopeNode::Internal {
-                left: Rc::new(RefCell::new(left.borrow().clone())),
-                right: Rc::new(RefCell::new(right.borrow().clone())),
-                left_size: *left_size,
-            },
-        }
+impl RopeNode {
+    fn internal(left: Arc<Rope>, right: Arc<Rope>, left_size: usize) -> Self {
+        let newlines = left.newline_count() + right.newline_count();
+        let depth = 1 + left.depth().max(right.depth());
+        let leaves = left.leaf_count() + right.leaf_count();
+        let left_bytes = left.byte_size();
+        let left_utf16 = left.utf16_size();
+        RopeNode::Internal { left, right, left_size, left_bytes, left_utf16, newlines, depth, leaves }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SplitStrategy {
     LineBased,
     FixedSize(usize),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    // The first line terminator decides; text without any newline is treated as Lf.
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(pos) if text[..pos].ends_with('\r') => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+
+    // The name vim uses for the format, as in `:set ff=unix`.
+    pub fn file_format(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "unix",
+            LineEnding::CrLf => "dos",
+        }
+    }
+
+    pub fn from_file_format(name: &str) -> Option<Self> {
+        match name {
+            "unix" => Some(LineEnding::Lf),
+            "dos" => Some(LineEnding::CrLf),
+            _ => None,
+        }
+    }
+}
+
+// Why a rope refused an index or an edit. `len` is the size the index was
+// checked against, in chars or for byte offsets in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RopeError {
+    IndexOutOfBounds { index: usize, len: usize },
+    // The byte offset falls inside a multi-byte char.
+    NotCharBoundary { byte: usize },
+    // `start..end` is backwards or runs past the `len` chars of the text.
+    InvalidRange { start: usize, end: usize, len: usize },
+    // Two edits given to `apply_edits` cover some of the same chars.
+    OverlappingEdits { first: Range<usize>, second: Range<usize> },
+}
+
+impl fmt::Display for RopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RopeError::IndexOutOfBounds { index, len } => write!(f, "index {} is past the end at {}", index, len),
+            RopeError::NotCharBoundary { byte } => write!(f, "byte {} is inside a char", byte),
+            RopeError::InvalidRange { start, end, len } => {
+                write!(f, "range {}..{} is not inside a text of {} chars", start, end, len)
+            }
+            RopeError::OverlappingEdits { first, second } => {
+                write!(f, "edits {}..{} and {}..{} overlap", first.start, first.end, second.start, second.end)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RopeError {}
+
+// One effective edit: the chars in `range` of the text before the edit were
+// replaced by `inserted_len` new chars starting at `range.start`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub range: Range<usize>,
+    pub inserted_len: usize,
+    pub deleted_text_len: usize,
+    pub line_delta: isize,
+}
+
+// Replace the chars `start..end` with `replacement`. `Rope::diff` gives these
+// in the indices of the text they apply to, ready for `apply_edits`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl From<Edit> for (usize, usize, String) {
+    fn from(edit: Edit) -> Self {
+        (edit.start, edit.end, edit.replacement)
+    }
+}
+
+// Cells `Rope::diff` may use for its table over the lines between the shared
+// start and end. Past this the whole middle becomes a single edit.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+// A place in the text the way the Language Server Protocol spells it: a
+// zero-based line and an offset into it in UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Change {
+    fn new(start: usize, deleted: &str, inserted: &str) -> Self {
+        let deleted_text_len = deleted.chars().count();
+        Change {
+            range: start..start + deleted_text_len,
+            inserted_len: inserted.chars().count(),
+            deleted_text_len,
+            line_delta: inserted.matches('\n').count() as isize - deleted.matches('\n').count() as isize,
+        }
+    }
+}
+
+// Tree health at a glance. `bytes_allocated_estimate` adds leaf string
+// capacity to the size of every node, so it ignores allocator overhead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RopeStats {
+    pub depth: usize,
+    pub leaf_count: usize,
+    pub total_chars: usize,
+    pub bytes_allocated_estimate: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct Rope {
     root: Option<RopeNode>,
     split_strategy: SplitStrategy,
+    line_ending: LineEnding,
+    undo_stack: UndoStack,
+    saved_generation: u64,
+    // Leaves shorter than this are merged with their neighbours when the root
+    // rebalances. 0, the default, keeps every leaf as its strategy cut it.
+    min_leaf_size: usize,
+    // Only recorded after `track_changes`, so the log can't grow unbounded in
+    // ropes nobody drains.
+    changes: Option<Vec<Change>>,
 }
 
 impl Rope {
     pub fn new(strategy: SplitStrategy) -> Self {
-        Rope { root: None, split_strategy: strategy }
+        Rope {
+            root: None,
+            split_strategy: strategy,
+            line_ending: LineEnding::Lf,
+            undo_stack: UndoStack::new(),
+            saved_generation: 0,
+            min_leaf_size: 0,
+            changes: None,
+        }
     }
 
     pub fn from_string(text: &str, strategy: SplitStrategy) -> Self {
         let mut rope = Rope::new(strategy);
+        rope.line_ending = LineEnding::detect(text);
 
-        if text.contains('\n') || text.len() > 512 {
-            let (left_part, right_part) = rope.split_leaf(text, text.len() / 2);
+        // Leaves only ever store '\n', the original ending is restored on output.
+        let text = text.replace("\r\n", "\n");
+        rope.root = Self::tree_from_text(&text, strategy);
+        rope
+    }
 
-            rope.root = Some(RopeNode::Internal { 
-                left: Rc::new(RefCell::new(Rope::from_string(&left_part, strategy))), 
-                right: Rc::new(RefCell::new(Rope::from_string(&right_part, strategy))), 
-                left_size: left_part.len(),
-             });
-        } else {
-            rope.root = Some(RopeNode::Leaf(text.to_string()));
+    // Chunks `text` into leaves according to `strategy` and builds a balanced
+    // tree over them.
+    fn tree_from_text(text: &str, strategy: SplitStrategy) -> Option<RopeNode> {
+        let chunks = Self::chunk_text(text, strategy);
+        if chunks.len() < 2 {
+            return Some(RopeNode::Leaf(text.to_string()));
         }
-        rope
+
+        let leaves: Vec<_> = chunks.into_iter().map(|chunk| Self::leaf(chunk.to_string(), strategy)).collect();
+        let (root, _) = Self::build_balanced_tree(&leaves, strategy);
+        Arc::unwrap_or_clone(root).root
+    }
+
+    // `LineBased` gives every line its own leaf. `FixedSize(max)` caps leaves at
+    // `max` chars, breaking after the last whitespace before the limit, or
+    // exactly at the limit when there is none (e.g. minified files).
+    fn chunk_text(text: &str, strategy: SplitStrategy) -> Vec<&str> {
+        match strategy {
+            SplitStrategy::LineBased => text.split_inclusive('\n').collect(),
+            SplitStrategy::FixedSize(max_size) => {
+                let max_size = max_size.max(1);
+                let mut chunks = Vec::new();
+                let mut rest = text;
+
+                while rest.chars().count() > max_size {
+                    let max_byte = char_to_byte(rest, max_size);
+                    let cut = match rest[..max_byte].char_indices().rfind(|(_, c)| c.is_whitespace()) {
+                        Some((pos, c)) => pos + c.len_utf8(),
+                        None => max_byte,
+                    };
+                    chunks.push(&rest[..cut]);
+                    rest = &rest[cut..];
+                }
+
+                if !rest.is_empty() {
+                    chunks.push(rest);
+                }
+                chunks
+            }
+        }
+    }
+
+    // Loads the rope a line at a time, building leaves straight from the
+    // reader without holding the whole file as one string. `FixedSize` packs
+    // the lines into chunks of at most `max` chars.
+    pub fn from_reader<R: BufRead>(mut reader: R, strategy: SplitStrategy) -> io::Result<Self> {
+        let mut rope = Rope::new(strategy);
+        let mut line_ending = None;
+        let mut leaves = Vec::new();
+        let mut pending = String::new();
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+
+            if line.ends_with(b"\r\n") {
+                line.truncate(line.len() - 2);
+                line.push(b'\n');
+                line_ending.get_or_insert(LineEnding::CrLf);
+            } else if line.ends_with(b"\n") {
+                line_ending.get_or_insert(LineEnding::Lf);
+            }
+            let text = std::str::from_utf8(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            match strategy {
+                SplitStrategy::LineBased => leaves.push(Self::leaf(text.to_string(), strategy)),
+                SplitStrategy::FixedSize(_) => {
+                    pending.push_str(text);
+                    let mut chunks: Vec<String> = Self::chunk_text(&pending, strategy).into_iter().map(String::from).collect();
+                    pending = chunks.pop().unwrap_or_default();
+                    leaves.extend(chunks.into_iter().map(|chunk| Self::leaf(chunk, strategy)));
+                }
+            }
+        }
+
+        if !pending.is_empty() {
+            leaves.push(Self::leaf(pending, strategy));
+        }
+
+        rope.line_ending = line_ending.unwrap_or(LineEnding::Lf);
+        if !leaves.is_empty() {
+            let (root, _) = Self::build_balanced_tree(&leaves, strategy);
+            rope.root = Arc::unwrap_or_clone(root).root;
+        }
+        Ok(rope)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P, strategy: SplitStrategy) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?), strategy)
+    }
+
+    fn leaf(text: String, strategy: SplitStrategy) -> (Arc<Rope>, usize) {
+        let size = text.chars().count();
+        let mut leaf = Rope::new(strategy);
+        leaf.root = Some(RopeNode::Leaf(text));
+        (Arc::new(leaf), size)
+    }
+
+    // Streams the text leaf by leaf, restoring the detected line ending.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut result = Ok(());
+        self.visit_leaves(&mut |text| {
+            result = match self.line_ending {
+                LineEnding::Lf => writer.write_all(text.as_bytes()),
+                LineEnding::CrLf => text.split_inclusive('\n').try_for_each(|piece| match piece.strip_suffix('\n') {
+                    Some(line) => {
+                        writer.write_all(line.as_bytes())?;
+                        writer.write_all(b"\r\n")
+                    }
+                    None => writer.write_all(piece.as_bytes()),
+                }),
+            };
+            result.is_ok()
+        });
+        result
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    // Converts the whole document to `line_ending` on output. Leaves only hold
+    // '\n', so this is a single undoable action rather than an edit per line.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if line_ending == self.line_ending {
+            return;
+        }
+
+        self.undo_stack.break_undo_group();
+        self.undo_stack.push(UndoAction::SetLineEnding { old: self.line_ending, new: line_ending, cursor: None });
+        self.undo_stack.break_undo_group();
+        self.line_ending = line_ending;
     }
 
+    // Indices past the end append, and the undo entry records where the text
+    // really went.
     pub fn insert(&mut self, index: usize, text: &str) {
+        self.try_insert(index.min(self.len()), text).expect("a clamped index is valid");
+    }
+
+    // `insert` that refuses an index past the end instead of appending.
+    pub fn try_insert(&mut self, index: usize, text: &str) -> Result<(), RopeError> {
+        if index > self.len() {
+            return Err(RopeError::IndexOutOfBounds { index, len: self.len() });
+        }
+        let text = text.replace("\r\n", "\n");
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let cursor = Some(self.line_col(index));
+        self.record_change(Change::new(index, "", &text));
+        self.insert_without_undo(index, &text);
+        self.undo_stack.push(UndoAction::Insert { index, text, cursor });
+        Ok(())
+    }
+
+    // `insert` for a single typed char. The leaf is edited in place rather
+    // than rebuilt, so typing skips the copies `insert` makes.
+    pub fn insert_char(&mut self, index: usize, c: char) {
+        let text = c.to_string();
+        let index = index.min(self.len());
+        let cursor = Some(self.line_col(index));
+        self.record_change(Change::new(index, "", &text));
+        self.insert_char_without_undo(index, c);
+        self.undo_stack.push(UndoAction::Insert { index, text, cursor });
+    }
+
+    // Removes the chars in `start..end`, which has to lie inside the text.
+    // An empty range removes nothing and isn't recorded.
+    pub fn remove(&mut self, start: usize, end: usize) -> Result<(), RopeError> {
+        if start > end || end > self.len() {
+            return Err(RopeError::InvalidRange { start, end, len: self.len() });
+        }
+        if start == end {
+            return Ok(());
+        }
+
+        let text = self.text_range(start, end);
+        let cursor = Some(self.line_col(start));
+        self.record_change(Change::new(start, &text, ""));
+        self.delete_without_undo(start, end);
+        self.undo_stack.push(UndoAction::Delete { index: start, text, cursor });
+        Ok(())
+    }
+
+    // `remove` for editors: the range is cut off at the end of the text, and
+    // a backwards one removes nothing.
+    pub fn remove_clamped(&mut self, start: usize, end: usize) {
+        let end = end.min(self.len());
+        if start < end {
+            self.remove(start, end).expect("a clamped range is valid");
+        }
+    }
+
+    // Makes the text end in exactly the one '\n' a POSIX text file wants, or
+    // takes a final '\n' away. Only that one newline is ever added or
+    // removed, so "a\n\n" stays as it is with `yes`.
+    pub fn ensure_trailing_newline(&mut self, yes: bool) {
+        let len = self.len();
+        let ends_in_newline = len > 0 && self.get_char(len - 1) == Some('\n');
+        if yes && !ends_in_newline {
+            self.insert(len, "\n");
+        } else if !yes && ends_in_newline {
+            self.remove_clamped(len - 1, len);
+        }
+    }
+
+    // Inserts `text`, without a terminator, as line `line_idx`, pushing that
+    // line and the ones after it down. `lines()` or past it adds a line after
+    // the last one, so the new separator goes in front of the text instead.
+    // Recorded as a replace so it never merges with typing.
+    pub fn insert_line(&mut self, line_idx: usize, text: &str) {
+        match self.line_to_char(line_idx) {
+            Some(start) => self.replace_range(start, start, &format!("{}\n", text)),
+            None => {
+                let len = self.len();
+                self.replace_range(len, len, &format!("\n{}", text));
+            }
+        }
+    }
+
+    // Removes line `line_idx` along with one separator and returns its text.
+    // The last line takes the newline before it, so "a\nb" becomes "a", and
+    // removing the only line leaves an empty document. A line past the end
+    // removes nothing.
+    pub fn remove_line(&mut self, line_idx: usize) -> String {
+        let Some(start) = self.line_to_char(line_idx) else {
+            return String::new();
+        };
+        let (start, end) = match self.line_to_char(line_idx + 1) {
+            Some(next) => (start, next),
+            None => (start.saturating_sub(1), self.len()),
+        };
+        let line = self.get_line(line_idx).unwrap_or_default();
+        self.replace_range(start, end, "");
+        line
+    }
+
+    // Exchanges the text of lines `a` and `b` as one undo entry, leaving the
+    // separators where they are. Undo puts the cursor back at the start of
+    // line `a`. Nothing happens if either is past the end or they're the
+    // same line.
+    pub fn swap_lines(&mut self, a: usize, b: usize) {
+        let (first, second) = (a.min(b), a.max(b));
+        if first == second || second >= self.lines() {
+            return;
+        }
+        let (Some(first_text), Some(second_text)) = (self.get_line(first), self.get_line(second)) else {
+            return;
+        };
+        let start = self.line_to_char(first).unwrap_or(0);
+        let second_start = self.line_to_char(second).unwrap_or(0);
+        let end = second_start + second_text.chars().count();
+        let between = self.text_range(start + first_text.chars().count(), second_start);
+
+        let old = self.text_range(start, end);
+        let new = format!("{}{}{}", second_text, between, first_text);
+        self.record_change(Change::new(start, &old, &new));
+        self.replace_without_undo(start, end, &new);
+        self.undo_stack.push(UndoAction::Replace { index: start, old, new, cursor: Some((a, 0)) });
+    }
+
+    // Replaces the chars in `start..end` with `text` in one pass down the tree
+    // and records it as a single undo entry. Like `insert`, the range is cut
+    // off at the end of the text, and a backwards one replaces nothing and
+    // just inserts at `start`, so the undo entry records where the text went.
+    pub fn replace_range(&mut self, start: usize, end: usize, text: &str) {
+        let start = start.min(self.len());
+        let end = end.clamp(start, self.len());
+        let text = text.replace("\r\n", "\n");
+        let old = self.text_range(start, end);
+        if old.is_empty() && text.is_empty() {
+            return;
+        }
+
+        let cursor = Some(self.line_col(start));
+        self.record_change(Change::new(start, &old, &text));
+        self.replace_without_undo(start, end, &text);
+        self.undo_stack.push(UndoAction::Replace { index: start, old, new: text, cursor });
+    }
+
+    // Applies every `(start, end, replacement)` as one undo entry. The ranges
+    // may come in any order but must lie inside the text and not overlap;
+    // otherwise nothing is changed. They're applied from the right so the
+    // indices of the ones still to go stay valid.
+    pub fn apply_edits(&mut self, edits: &[(usize, usize, String)]) -> Result<(), RopeError> {
+        let mut sorted: Vec<&(usize, usize, String)> = edits.iter().collect();
+        sorted.sort_by_key(|(start, end, _)| (*start, *end));
+        if let Some((start, end, _)) = sorted.iter().find(|(start, end, _)| start > end || *end > self.len()) {
+            return Err(RopeError::InvalidRange { start: *start, end: *end, len: self.len() });
+        }
+        if let Some(pair) = sorted.windows(2).find(|pair| pair[0].1 > pair[1].0) {
+            return Err(RopeError::OverlappingEdits { first: pair[0].0..pair[0].1, second: pair[1].0..pair[1].1 });
+        }
+
+        let cursor = sorted.first().map(|(start, _, _)| self.line_col(*start));
+        let mut actions = Vec::new();
+        for (start, end, text) in sorted.into_iter().rev() {
+            let text = text.replace("\r\n", "\n");
+            let old = self.text_range(*start, *end);
+            if old.is_empty() && text.is_empty() {
+                continue;
+            }
+            self.record_change(Change::new(*start, &old, &text));
+            self.replace_without_undo(*start, *end, &text);
+            actions.push(UndoAction::Replace { index: *start, old, new: text, cursor: None });
+        }
+
+        if !actions.is_empty() {
+            self.undo_stack.push(UndoAction::Group { actions, cursor });
+        }
+        Ok(())
+    }
+
+    // The edits that turn this text into `other`'s, in order and in this
+    // text's indices, so `apply_edits` can apply them all at once. Lines are
+    // compared whole, terminator included, through their longest common
+    // subsequence, and each run of lines that differs becomes one edit.
+    pub fn diff(&self, other: &Rope) -> Vec<Edit> {
+        let (mut old_text, mut new_text) = (String::new(), String::new());
+        self.collect_text(&mut old_text);
+        other.collect_text(&mut new_text);
+        let old: Vec<&str> = old_text.split_inclusive('\n').collect();
+        let new: Vec<&str> = new_text.split_inclusive('\n').collect();
+
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+        let (old_middle, new_middle) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+        // Pairs of (old, new) line numbers in the middle that stay.
+        let mut kept = Vec::new();
+        if old_middle.len().saturating_mul(new_middle.len()) <= MAX_DIFF_CELLS {
+            let width = new_middle.len() + 1;
+            let mut table = vec![0u32; (old_middle.len() + 1) * width];
+            for i in (0..old_middle.len()).rev() {
+                for j in (0..new_middle.len()).rev() {
+                    table[i * width + j] = match old_middle[i] == new_middle[j] {
+                        true => table[(i + 1) * width + j + 1] + 1,
+                        false => table[(i + 1) * width + j].max(table[i * width + j + 1]),
+                    };
+                }
+            }
+            let (mut i, mut j) = (0, 0);
+            while i < old_middle.len() && j < new_middle.len() {
+                if old_middle[i] == new_middle[j] {
+                    kept.push((i, j));
+                    (i, j) = (i + 1, j + 1);
+                } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+                    i += 1;
+                } else {
+                    j += 1;
+                }
+            }
+        }
+        kept.push((old_middle.len(), new_middle.len()));
+
+        let mut offset: usize = old[..prefix].iter().map(|line| line.chars().count()).sum();
+        let mut edits = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        for (next_i, next_j) in kept {
+            if next_i > i || next_j > j {
+                let removed: usize = old_middle[i..next_i].iter().map(|line| line.chars().count()).sum();
+                edits.push(Edit { start: offset, end: offset + removed, replacement: new_middle[j..next_j].concat() });
+                offset += removed;
+            }
+            if let Some(line) = old_middle.get(next_i) {
+                offset += line.chars().count();
+            }
+            (i, j) = (next_i + 1, next_j + 1);
+        }
+        edits
+    }
+
+    // Reverts the most recent action and returns the (line, col) cursor position
+    // recorded before it, or `None` when there is nothing to undo. Actions pushed
+    // without a cursor fall back to the position of the edit.
+    pub fn undo(&mut self) -> Option<(usize, usize)> {
+        let action = self.undo_stack.pop()?;
+        let cursor = action.cursor();
+        let index = action.index();
+        self.apply_action(action);
+        Some(cursor.unwrap_or_else(|| self.line_col(index)))
+    }
+
+    // Applies the most recently undone action again and returns the (line, col)
+    // where it starts. Any edit made after an undo drops what could be redone.
+    pub fn redo(&mut self) -> Option<(usize, usize)> {
+        let action = self.undo_stack.pop_redo()?;
+        let index = action.index();
+        self.replay_action(action);
+        Some(self.line_col(index))
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.undo_stack.can_redo()
+    }
+
+    // Starts recording a `Change` for every edit, undo included, to be picked
+    // up in order with `take_changes`.
+    pub fn track_changes(&mut self) {
+        self.changes.get_or_insert_with(Vec::new);
+    }
+
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        self.changes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn record_change(&mut self, change: Change) {
+        if let Some(changes) = &mut self.changes {
+            changes.push(change);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    // Whether the text differs from the state at the last `mark_saved`. Undoing
+    // back to exactly that state makes the rope clean again.
+    pub fn is_modified(&self) -> bool {
+        self.undo_stack.generation() != self.saved_generation
+    }
+
+    // Changes with every edit, undo and redo, so comparing two values tells
+    // whether the text may have changed in between.
+    pub fn generation(&self) -> u64 {
+        self.undo_stack.generation()
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved_generation = self.undo_stack.generation();
+    }
+
+    // Limits the undo history to the `limit` most recent actions.
+    pub fn set_undo_limit(&mut self, limit: usize) {
+        self.undo_stack.set_capacity(Some(limit));
+    }
+
+    // Stops the next single-char insert from being merged into the previous one.
+    pub fn break_undo_group(&mut self) {
+        self.undo_stack.break_undo_group();
+    }
+
+    // Makes the newest undo entry part of the one before it, for editor
+    // commands that repeat an edit but should undo in one step.
+    pub fn merge_last_undo(&mut self) {
+        self.undo_stack.join_last_two();
+    }
+
+    // Makes every undo entry added since `generation()` was `generation` into
+    // one.
+    pub fn merge_undo_since(&mut self, generation: u64) {
+        self.undo_stack.group_since(generation);
+    }
+
+    // Applies the inverse of `action` without adding to the undo history.
+    fn apply_action(&mut self, action: UndoAction) {
+        match action {
+            UndoAction::Insert { index, text, .. } => {
+                self.record_change(Change::new(index, &text, ""));
+                self.delete_without_undo(index, index + text.chars().count());
+            }
+            UndoAction::Delete { index, text, .. } => {
+                self.record_change(Change::new(index, "", &text));
+                self.insert_without_undo(index, &text);
+            }
+            UndoAction::Replace { index, old, new, .. } => {
+                self.record_change(Change::new(index, &new, &old));
+                self.replace_without_undo(index, index + new.chars().count(), &old);
+            }
+            UndoAction::SetLineEnding { old, .. } => self.line_ending = old,
+            UndoAction::Group { actions, .. } => {
+                for action in actions.into_iter().rev() {
+                    self.apply_action(action);
+                }
+            }
+        }
+    }
+
+    // Applies `action` itself again, the opposite of `apply_action`.
+    fn replay_action(&mut self, action: UndoAction) {
+        match action {
+            UndoAction::Insert { index, text, .. } => {
+                self.record_change(Change::new(index, "", &text));
+                self.insert_without_undo(index, &text);
+            }
+            UndoAction::Delete { index, text, .. } => {
+                self.record_change(Change::new(index, &text, ""));
+                self.delete_without_undo(index, index + text.chars().count());
+            }
+            UndoAction::Replace { index, old, new, .. } => {
+                self.record_change(Change::new(index, &old, &new));
+                self.replace_without_undo(index, index + old.chars().count(), &new);
+            }
+            UndoAction::SetLineEnding { new, .. } => self.line_ending = new,
+            UndoAction::Group { actions, .. } => {
+                for action in actions {
+                    self.replay_action(action);
+                }
+            }
+        }
+    }
+
+    fn insert_without_undo(&mut self, index: usize, text: &str) {
         match self.root.take() {
             Some(RopeNode::Leaf(existing_text)) => {
+                let byte_index = char_to_byte(&existing_text, index);
                 let new_text = format!(
                     "{}{}{}",
-                    &existing_text[..index], text, &existing_text[index..]
+                    &existing_text[..byte_index], text, &existing_text[byte_index..]
                 );
 
-                match self.split_strategy {
-                    SplitStrategy::LineBased => {
-                        if let Some(pos) = new_text[..index].rfind('\n') {
-                            let (left_part, right_part) = new_text.split_at(pos + 1);
-
-                            self.root = Some(RopeNode::Internal {
-                                left: Rc::new(RefCell::new(Rope::from_string(left_part, self.split_strategy))),
-                                right: Rc::new(RefCell::new(Rope::from_string(right_part, self.split_strategy))),
-                                left_size: left_part.len(),
-                            });
-                        } else {
-                            self.root = Some(RopeNode::Leaf(new_text));
-                        }
-                    }
-                    SplitStrategy::FixedSize(max_size) => {
-                        if new_text.len() > max_size {
-                            let split_index = match new_text[..max_size].rfind(' ') {
-                                Some(pos) => pos +1,
-                                None => max_size,
-                            };
-
-                            let (left_part, right_part) = new_text.split_at(split_index);
-
-                            self.root = Some(RopeNode::Internal {
-                                left: Rc::new(RefCell::new(Rope::from_string(left_part, self.split_strategy))),
-                                right: Rc::new(RefCell::new(Rope::from_string(right_part, self.split_strategy))),
-                                left_size: left_part.len(),
-                            });
-                        } else {
-                            self.root = Some(RopeNode::Leaf(new_text));
-                        }
-                    }
+                self.rebuild_leaf(new_text);
+            }
+            Some(RopeNode::Internal { mut left, mut right, left_size, .. }) => {
+                let mut left_size = left_size;
+
+                if index < left_size {
+                    Arc::make_mut(&mut left).insert_without_undo(index, text);
+                    left_size += text.chars().count();
+                } else {
+                    Arc::make_mut(&mut right).insert_without_undo(index - left_size, text);
                 }
+
+                self.root = Some(RopeNode::internal(left.clone(), right.clone(), left_size));
+                self.rebalance_if_skewed();
             }
-            Some(RopeNode::Internal {left, right, left_size }) => {
+            None => {
+                self.rebuild_leaf(text.to_string());
+            }
+        }
+    }
+
+    // Puts `c` straight into its leaf. Only a char that breaks the leaf's
+    // split strategy, such as a newline in a line-based rope, re-chunks it.
+    fn insert_char_without_undo(&mut self, index: usize, c: char) {
+        match self.root.take() {
+            Some(RopeNode::Leaf(mut text)) => {
+                let byte_index = char_to_byte(&text, index);
+                let fits = match self.split_strategy {
+                    SplitStrategy::LineBased => c != '\n' && !(byte_index == text.len() && text.ends_with('\n')),
+                    SplitStrategy::FixedSize(max_size) => text.chars().count() < max_size.max(1),
+                };
+                text.insert(byte_index, c);
+                if fits {
+                    self.root = Some(RopeNode::Leaf(text));
+                } else {
+                    self.rebuild_leaf(text);
+                }
+            }
+            Some(RopeNode::Internal { mut left, mut right, left_size, .. }) => {
+                let mut left_size = left_size;
+
                 if index < left_size {
-                    left.borrow_mut().insert(index, text);
+                    Arc::make_mut(&mut left).insert_char_without_undo(index, c);
+                    left_size += 1;
                 } else {
-                    right.borrow_mut().insert(index - left_size, text);
+                    Arc::make_mut(&mut right).insert_char_without_undo(index - left_size, c);
                 }
 
-                self.root = Some(RopeNode::Internal { 
-                    left: left.clone(), 
-                    right: right.clone(), 
-                    left_size: left_size,
-                });
+                self.root = Some(RopeNode::internal(left, right, left_size));
+                self.rebalance_if_skewed();
             }
             None => {
-                self.root = Some(RopeNode::Leaf(text.to_string()));
+                self.root = Some(RopeNode::Leaf(c.to_string()));
             }
         }
     }
 
-    pub fn delete(&mut self, start: usize, end: usize) {
+    fn replace_without_undo(&mut self, start: usize, end: usize, text: &str) {
+        match self.root.take() {
+            Some(RopeNode::Leaf(existing_text)) => {
+                let start_byte = char_to_byte(&existing_text, start);
+                let new_text = format!(
+                    "{}{}{}",
+                    &existing_text[..start_byte], text, &existing_text[char_to_byte(&existing_text, end)..]
+                );
+
+                if new_text.is_empty() {
+                    return;
+                }
+                self.rebuild_leaf(new_text);
+            }
+            Some(RopeNode::Internal { mut left, right, left_size, .. }) if end <= left_size && start < left_size => {
+                Arc::make_mut(&mut left).replace_without_undo(start, end, text);
+                let left_size = left.len();
+
+                self.root = Some(RopeNode::internal(left, right, left_size));
+                self.collapse_empty_child();
+                self.rebalance_if_skewed();
+            }
+            Some(RopeNode::Internal { left, mut right, left_size, .. }) if start >= left_size => {
+                Arc::make_mut(&mut right).replace_without_undo(start - left_size, end - left_size, text);
+
+                self.root = Some(RopeNode::internal(left, right, left_size));
+                self.collapse_empty_child();
+                self.rebalance_if_skewed();
+            }
+            root => {
+                // The range spans both children.
+                self.root = root;
+                self.delete_without_undo(start, end);
+                self.insert_without_undo(start, text);
+            }
+        }
+    }
+
+    // Re-chunks an edited leaf, so a paste or a line that outgrew its limit
+    // becomes a small balanced subtree instead of one oversized leaf.
+    fn rebuild_leaf(&mut self, new_text: String) {
+        self.root = Self::tree_from_text(&new_text, self.split_strategy);
+    }
+
+    fn delete_without_undo(&mut self, start: usize, end: usize) {
         if start >= end {
             return;
         }
 
         match self.root.take() {
             Some(RopeNode::Leaf(existing_text)) => {
-                if start >= existing_text.len() || end > existing_text.len() {
+                let char_count = existing_text.chars().count();
+                if start >= char_count || end > char_count {
                     panic!("Invalid delete range")
                 }
 
                 let new_text = format!(
                     "{}{}",
-                    &existing_text[..start],
-                    &existing_text[end..]
+                    &existing_text[..char_to_byte(&existing_text, start)],
+                    &existing_text[char_to_byte(&existing_text, end)..]
                 );
 
                 if new_text.is_empty() {
@@ -141,123 +862,551 @@ impl Rope {
                     return;
                 }
 
-                match self.split_strategy {
-                    SplitStrategy::LineBased => {
-                        if new_text.contains('\n') {
-                            let (left_part, right_part) = self.split_leaf(&new_text, new_text.len() / 2);
-                            self.root = Some(RopeNode::Internal {
-                                left: Rc::new(RefCell::new(Rope::from_string(&left_part, self.split_strategy))),
-                                right: Rc::new(RefCell::new(Rope::from_string(&right_part, self.split_strategy))),
-                                left_size: left_part.len(),
-                            });
-                        } else {
-                            self.root = Some(RopeNode::Leaf(new_text));
-                        }
-                    }
-                    SplitStrategy::FixedSize(max_size) => {
-                        if new_text.len() > max_size {
-                            let split_index = match new_text[..max_size].rfind(' ') {
-                                Some(pos) => pos + 1, // Split at nearest space
-                                None => max_size, // Hard split at max_size if no space is found
-                            };
-
-                            let (left_part, right_part) = new_text.split_at(split_index);
-
-                            self.root = Some(RopeNode::Internal {
-                                left: Rc::new(RefCell::new(Rope::from_string(left_part, self.split_strategy))),
-                                right: Rc::new(RefCell::new(Rope::from_string(right_part, self.split_strategy))),
-                                left_size: left_part.len(),
-                            });
-                        } else {
-                            self.root = Some(RopeNode::Leaf(new_text));
-                        }
-                    }    
-                }
-            }
-            Some(RopeNode::Internal { left, right, left_size }) => {
+                self.rebuild_leaf(new_text);
+            }
+            Some(RopeNode::Internal { mut left, mut right, left_size, .. }) => {
                 if end < left_size {
-                    left.borrow_mut().delete(start, end);
+                    Arc::make_mut(&mut left).delete_without_undo(start, end);
                 } else if start >= left_size {
-                    right.borrow_mut().delete(start - left_size, end - left_size);
+                    Arc::make_mut(&mut right).delete_without_undo(start - left_size, end - left_size);
+                } else {
+                    Arc::make_mut(&mut left).delete_without_undo(start, left_size);
+                    Arc::make_mut(&mut right).delete_without_undo(0, end - left_size);
+                }
+
+                let left_size = left.len();
+                self.root = Some(RopeNode::internal(left, right, left_size));
+                self.collapse_empty_child();
+                self.rebalance_if_skewed();
+            }
+            None => {}
+        }
+    }
+
+    // Replaces an internal node that lost a child with the surviving one.
+    fn collapse_empty_child(&mut self) {
+        if let Some(RopeNode::Internal { left, right, .. }) = &self.root {
+            let left_empty = left.root.is_none();
+            let right_empty =  right.root.is_none();
+
+            self.root = match (left_empty, right_empty) {
+                (true, true) => None,
+                (true, false) => right.root.clone(),
+                (false, true) => left.root.clone(),
+                (false, false) => return,
+            };
+        }
+    }
+
+    // Zero-based (line, col) of the char at `index`, with col counted in chars.
+    // Indices past the end map to the end of the last line.
+    pub fn line_col(&self, index: usize) -> (usize, usize) {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => {
+                let mut line = 0;
+                let mut col = 0;
+                for c in text.chars().take(index) {
+                    if c == '\n' {
+                        line += 1;
+                        col = 0;
+                    } else {
+                        col += 1;
+                    }
+                }
+                (line, col)
+            }
+            Some(RopeNode::Internal { left, right, left_size, .. }) => {
+                if index <= *left_size {
+                    return left.line_col(index);
+                }
+
+                let (line, col) = right.line_col(index - left_size);
+                if line == 0 {
+                    let (left_line, left_col) = left.line_col(*left_size);
+                    (left_line, left_col + col)
+                } else {
+                    (left.newline_count() + line, col)
+                }
+            }
+            None => (0, 0),
+        }
+    }
+
+    // Char index of the next grapheme boundary after `index`, so a cursor never
+    // stops between a base char and its combining marks or inside an emoji.
+    pub fn next_grapheme_boundary(&self, index: usize) -> usize {
+        unicode::next_boundary(|i| self.get_char(i), index, self.len())
+    }
+
+    pub fn prev_grapheme_boundary(&self, index: usize) -> usize {
+        unicode::prev_boundary(|i| self.get_char(i), index, self.len())
+    }
+
+    // The grapheme boundary after `index`, which should be one itself. With
+    // the `unicode` feature it follows the full Unicode segmentation rules,
+    // otherwise the built-in ones `next_grapheme_boundary` uses.
+    pub fn grapheme_after(&self, index: usize) -> usize {
+        #[cfg(feature = "unicode")]
+        return self.segment_boundary(index, true);
+        #[cfg(not(feature = "unicode"))]
+        return self.next_grapheme_boundary(index);
+    }
+
+    // The grapheme boundary before `index`, see `grapheme_after`.
+    pub fn grapheme_before(&self, index: usize) -> usize {
+        #[cfg(feature = "unicode")]
+        return self.segment_boundary(index, false);
+        #[cfg(not(feature = "unicode"))]
+        return self.prev_grapheme_boundary(index);
+    }
+
+    // Feeds `GraphemeCursor` windows of the text around `index`, widening
+    // them whenever it asks for more, so a long line is never copied whole.
+    #[cfg(feature = "unicode")]
+    fn segment_boundary(&self, index: usize, forward: bool) -> usize {
+        use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+        const WINDOW: usize = 64;
+
+        let len = self.len();
+        let index = index.min(len);
+        if (forward && index == len) || (!forward && index == 0) {
+            return index;
+        }
+
+        let mut cursor = GraphemeCursor::new(self.char_to_byte(index), self.byte_size(), true);
+        let (mut start, mut end) = (index.saturating_sub(WINDOW), (index + WINDOW).min(len));
+        loop {
+            let chunk = self.text_range(start, end);
+            let chunk_start = self.char_to_byte(start);
+            let found = match forward {
+                true => cursor.next_boundary(&chunk, chunk_start),
+                false => cursor.prev_boundary(&chunk, chunk_start),
+            };
+            match found {
+                Ok(Some(byte)) => return self.byte_to_char(byte),
+                Ok(None) => return if forward { len } else { 0 },
+                Err(GraphemeIncomplete::PreContext(byte)) => {
+                    let context_end = self.byte_to_char(byte);
+                    let context_start = context_end.saturating_sub(WINDOW);
+                    cursor.provide_context(&self.text_range(context_start, context_end), self.char_to_byte(context_start));
+                }
+                Err(GraphemeIncomplete::NextChunk) => (start, end) = (end, (end + WINDOW).min(len)),
+                Err(GraphemeIncomplete::PrevChunk) => (start, end) = (start.saturating_sub(WINDOW), start),
+                Err(GraphemeIncomplete::InvalidOffset) => return index,
+            }
+        }
+    }
+
+    fn class_at(&self, index: usize) -> Option<CharClass> {
+        self.get_char(index).map(unicode::char_class)
+    }
+
+    // Start of the next word after `index`, the way vim's `w` finds it: skip
+    // the rest of the current word, then the whitespace after it. An empty
+    // line stops the scan as a word of its own. Gives the length when no word
+    // follows.
+    pub fn next_word_boundary(&self, index: usize) -> usize {
+        let len = self.len();
+        let mut i = index.min(len);
+
+        if let Some(class) = self.class_at(i).filter(|&class| class != CharClass::Space) {
+            while i < len && self.class_at(i) == Some(class) {
+                i = self.next_grapheme_boundary(i);
+            }
+        }
+
+        while i < len {
+            let c = self.get_char(i).unwrap_or('\0');
+            if !c.is_whitespace() || (c == '\n' && i > index && self.get_char(i - 1) == Some('\n')) {
+                break;
+            }
+            i = self.next_grapheme_boundary(i);
+        }
+        i
+    }
+
+    // Start of the word before `index`, like vim's `b`, with the same rule for
+    // empty lines.
+    pub fn prev_word_boundary(&self, index: usize) -> usize {
+        let mut i = index.min(self.len());
+
+        while i > 0 {
+            let prev = self.prev_grapheme_boundary(i);
+            let c = self.get_char(prev).unwrap_or('\0');
+            i = prev;
+            if c == '\n' {
+                // `i` is now the end of the previous line.
+                if i == 0 || self.get_char(i - 1) == Some('\n') {
+                    break;
+                }
+            } else if !c.is_whitespace() {
+                let class = unicode::char_class(c);
+                while i > 0 {
+                    let prev = self.prev_grapheme_boundary(i);
+                    if self.class_at(prev) != Some(class) {
+                        break;
+                    }
+                    i = prev;
+                }
+                break;
+            }
+        }
+        i
+    }
+
+    // Last grapheme of the next word end after `index`, like vim's `e`. Gives
+    // the length when no word follows.
+    pub fn next_word_end(&self, index: usize) -> usize {
+        let len = self.len();
+        let mut i = self.next_grapheme_boundary(index.min(len));
+        while i < len && self.class_at(i) == Some(CharClass::Space) {
+            i = self.next_grapheme_boundary(i);
+        }
+
+        let Some(class) = self.class_at(i) else {
+            return len;
+        };
+        loop {
+            let next = self.next_grapheme_boundary(i);
+            if next >= len || self.class_at(next) != Some(class) {
+                return i;
+            }
+            i = next;
+        }
+    }
+
+    pub fn try_char_at(&self, index: usize) -> Result<char, RopeError> {
+        self.get_char(index).ok_or(RopeError::IndexOutOfBounds { index, len: self.len() })
+    }
+
+    pub fn get_char(&self, index: usize) -> Option<char> {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => text.chars().nth(index),
+            Some(RopeNode::Internal { left, right, left_size, .. }) => {
+                if index < *left_size {
+                    left.get_char(index)
+                } else {
+                    right.get_char(index - left_size)
+                }
+            }
+            None => None,
+        }
+    }
+
+    // Length of the text in UTF-8 bytes.
+    pub fn byte_size(&self) -> usize {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => text.len(),
+            Some(RopeNode::Internal { right, left_bytes, .. }) => left_bytes + right.byte_size(),
+            None => 0,
+        }
+    }
+
+    // Byte offset where the `index`-th char starts; past the end gives
+    // `byte_size()`.
+    pub fn char_to_byte(&self, index: usize) -> usize {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => char_to_byte(text, index),
+            Some(RopeNode::Internal { left, right, left_size, left_bytes, .. }) => {
+                if index < *left_size {
+                    left.char_to_byte(index)
                 } else {
-                    left.borrow_mut().delete(start, left_size);
-                    right.borrow_mut().delete(0, end - left_size);
+                    left_bytes + right.char_to_byte(index - left_size)
                 }
+            }
+            None => 0,
+        }
+    }
+
+    // Char index of the char holding byte `index`. An offset inside a
+    // multi-byte char rounds down to where that char starts; past the end
+    // gives `len()`.
+    pub fn byte_to_char(&self, index: usize) -> usize {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => {
+                let mut boundary = index.min(text.len());
+                while !text.is_char_boundary(boundary) {
+                    boundary -= 1;
+                }
+                text[..boundary].chars().count()
+            }
+            Some(RopeNode::Internal { left, right, left_size, left_bytes, .. }) => {
+                if index < *left_bytes {
+                    left.byte_to_char(index)
+                } else {
+                    left_size + right.byte_to_char(index - left_bytes)
+                }
+            }
+            None => 0,
+        }
+    }
+
+    // `byte_to_char` that refuses offsets past the end or inside a char
+    // instead of rounding them.
+    pub fn try_byte_to_char(&self, index: usize) -> Result<usize, RopeError> {
+        if index > self.byte_size() {
+            return Err(RopeError::IndexOutOfBounds { index, len: self.byte_size() });
+        }
+        let char_index = self.byte_to_char(index);
+        if self.char_to_byte(char_index) != index {
+            return Err(RopeError::NotCharBoundary { byte: index });
+        }
+        Ok(char_index)
+    }
+
+    // The text between two byte offsets, each rounded down to a char
+    // boundary the way `byte_to_char` does.
+    pub fn byte_range(&self, start: usize, end: usize) -> String {
+        self.text_range(self.byte_to_char(start), self.byte_to_char(end))
+    }
+
+    // Length of the text in UTF-16 code units; chars above U+FFFF take two.
+    pub fn utf16_size(&self) -> usize {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => text.encode_utf16().count(),
+            Some(RopeNode::Internal { right, left_utf16, .. }) => left_utf16 + right.utf16_size(),
+            None => 0,
+        }
+    }
+
+    // UTF-16 offset where the `index`-th char starts; past the end gives
+    // `utf16_size()`.
+    pub fn char_to_utf16_cu(&self, index: usize) -> usize {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => text.chars().take(index).map(char::len_utf16).sum(),
+            Some(RopeNode::Internal { left, right, left_size, left_utf16, .. }) => {
+                if index < *left_size {
+                    left.char_to_utf16_cu(index)
+                } else {
+                    left_utf16 + right.char_to_utf16_cu(index - left_size)
+                }
+            }
+            None => 0,
+        }
+    }
+
+    // Char index of the char holding UTF-16 offset `index`, rounding down
+    // from the middle of a surrogate pair.
+    fn utf16_to_char(&self, index: usize) -> usize {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => {
+                let mut units = 0;
+                text.chars()
+                    .take_while(|c| {
+                        units += c.len_utf16();
+                        units <= index
+                    })
+                    .count()
+            }
+            Some(RopeNode::Internal { left, right, left_size, left_utf16, .. }) => {
+                if index < *left_utf16 {
+                    left.utf16_to_char(index)
+                } else {
+                    left_size + right.utf16_to_char(index - left_utf16)
+                }
+            }
+            None => 0,
+        }
+    }
+
+    // Char index of code unit `cu` on `line`. As LSP asks, an offset past the
+    // end of the line means the end of the line, and a line past the last one
+    // means the end of the text.
+    pub fn utf16_cu_to_char(&self, line: usize, cu: usize) -> usize {
+        let Some(start) = self.line_to_char(line) else {
+            return self.len();
+        };
+        let end = self.line_to_char(line + 1).map_or(self.len(), |next| next - 1);
+        self.utf16_to_char(self.char_to_utf16_cu(start) + cu).min(end)
+    }
 
-                let left_empty = left.borrow().root.is_none();
-                let right_empty =  right.borrow().root.is_none();
+    pub fn position_to_char(&self, position: Position) -> usize {
+        self.utf16_cu_to_char(position.line, position.character)
+    }
 
-                self.root = match (left_empty, right_empty) {
-                    (true, true) => None,
-                    (true, false) => Some(right.borrow().root.clone().unwrap()),
-                    (false, true) => Some(left.borrow().root.clone().unwrap()),
-                    (false, false) => Some(RopeNode::Internal {
-                        left: left.clone(), 
-                        right: right.clone(), 
-                        left_size,
-                    }),
-                };
+    pub fn char_to_position(&self, index: usize) -> Position {
+        let index = index.min(self.len());
+        let (line, _) = self.line_col(index);
+        let start = self.line_to_char(line).unwrap_or(0);
+        Position { line, character: self.char_to_utf16_cu(index) - self.char_to_utf16_cu(start) }
+    }
+
+    // The chars in `start..end`, clamped to the rope's length.
+    pub fn text_range(&self, start: usize, end: usize) -> String {
+        let mut result = String::new();
+        self.collect_range(start, end, &mut result);
+        result
+    }
+
+    // Only descends into subtrees that overlap `start..end`.
+    fn collect_range(&self, start: usize, end: usize, out: &mut String) {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => out.extend(text.chars().skip(start).take(end.saturating_sub(start))),
+            Some(RopeNode::Internal { left, right, left_size, .. }) => {
+                if start < *left_size {
+                    left.collect_range(start, end.min(*left_size), out);
+                }
+                if end > *left_size {
+                    right.collect_range(start.saturating_sub(*left_size), end - left_size, out);
+                }
             }
             None => {}
         }
     }
 
-    pub fn get_char(&self, index: usize) -> Option<char> {
+    // Text of line `line_number` without its terminator. CRLF files are stored
+    // with plain '\n', so no line ever ends in '\r'.
+    pub fn get_line(&self, line_number: usize) -> Option<String> {
+        let start = self.line_to_char(line_number)?;
+        let end = match self.line_to_char(line_number + 1) {
+            Some(next) => next - 1,
+            None => self.len(),
+        };
+        Some(self.text_range(start, end))
+    }
+
+    // Chars on line `line_number` without its '\n', from the cached newline
+    // counts alone. After a trailing '\n' the last line is empty, so "a\n"
+    // has lines of 1 and 0 chars. `None` past the last line.
+    pub fn line_len(&self, line_number: usize) -> Option<usize> {
+        let start = self.line_to_char(line_number)?;
+        let end = match self.line_to_char(line_number + 1) {
+            Some(next) => next - 1,
+            None => self.len(),
+        };
+        Some(end - start)
+    }
+
+    // Char index where line `line_number` starts, found through the cached
+    // newline counts instead of a scan.
+    pub fn line_to_char(&self, line_number: usize) -> Option<usize> {
+        match line_number {
+            0 => Some(0),
+            n if n > self.newline_count() => None,
+            n => Some(self.nth_newline(n) + 1),
+        }
+    }
+
+    // Char index of the `n`th newline, counting from 1.
+    fn nth_newline(&self, n: usize) -> usize {
         match &self.root {
-            Some(RopeNode::Leaf(text)) => text.chars().nth(index),
-            Some(RopeNode::Internal { left, right, left_size }) => {
-                if index < *left_size {
-                    left.borrow().get_char(index)
+            Some(RopeNode::Leaf(text)) => text
+                .chars()
+                .enumerate()
+                .filter(|&(_, c)| c == '\n')
+                .nth(n - 1)
+                .map_or(0, |(index, _)| index),
+            Some(RopeNode::Internal { left, right, left_size, .. }) => {
+                let left_newlines = left.newline_count();
+                if n <= left_newlines {
+                    left.nth_newline(n)
                 } else {
-                    right.borrow().get_char(index - left_size)
+                    left_size + right.nth_newline(n - left_newlines)
                 }
             }
-            None => None,
+            None => 0,
         }
     }
 
-    pub fn get_line(&self, line_number: usize) -> Option<String> {
-        let mut current_line = 0;
-        let mut result = String::new();
+    pub fn find(&self, pattern: &str) -> Option<usize> {
+        self.find_from(0, pattern)
+    }
+
+    // Char index of the first match starting at or after `start`.
+    pub fn find_from(&self, start: usize, pattern: &str) -> Option<usize> {
+        let mut found = None;
+        self.scan_matches(start, pattern, false, &mut |index| {
+            found = Some(index);
+            false
+        });
+        found
+    }
+
+    // Char index of the last match in the rope. Overlapping candidates are
+    // considered, so "aaa".rfind("aa") is 1.
+    pub fn rfind(&self, pattern: &str) -> Option<usize> {
+        let mut found = None;
+        self.scan_matches(0, pattern, true, &mut |index| {
+            found = Some(index);
+            true
+        });
+        found
+    }
+
+    // Char index of the last match starting before `end`, found by reading
+    // backwards from there, so a search up from the cursor only reads what's
+    // between it and the match.
+    pub fn rfind_from(&self, end: usize, pattern: &str) -> Option<usize> {
+        let pattern: Vec<char> = pattern.chars().rev().collect();
+        if pattern.is_empty() {
+            return None;
+        }
 
-        self.traverse_lines(line_number, &mut current_line, &mut result);
-        if result.is_empty() {
-            None
-        } else {
-            Some(result)
+        let from = (end + pattern.len() - 1).min(self.len());
+        let mut window = VecDeque::with_capacity(pattern.len());
+        for (start, c) in (0..from).rev().zip(self.chars_before(from)) {
+            if window.len() == pattern.len() {
+                window.pop_front();
+            }
+            window.push_back(c);
+            if window.len() == pattern.len() && start < end && window.iter().eq(pattern.iter()) {
+                return Some(start);
+            }
         }
+        None
     }
 
-    fn traverse_lines(&self, target_line: usize, current_line: &mut usize, result: &mut String) {
-        if let Some(node) = &self.root {
-            match node {
-                RopeNode::Leaf(text) => {
-                    for line in text.lines() {
-                        if *current_line == target_line {
-                            result.push_str(line);
-                            return;
-                        }
-                        *current_line += 1;
-                    }
+    // Start indices of all non-overlapping matches, left to right. After each
+    // match the scan resumes past it, so "aaaa".find_all("aa") is [0, 2].
+    pub fn find_all(&self, pattern: &str) -> Vec<usize> {
+        let mut found = Vec::new();
+        self.scan_matches(0, pattern, false, &mut |index| {
+            found.push(index);
+            true
+        });
+        found
+    }
+
+    // Streams the text from `start` on leaf by leaf through a sliding window,
+    // so matches that straddle leaf boundaries are found without flattening
+    // the rope and nothing before `start` is read. `on_match` gets the char
+    // index of each match and returns false to stop the scan.
+    fn scan_matches(&self, start: usize, pattern: &str, overlapping: bool, on_match: &mut dyn FnMut(usize) -> bool) {
+        let pattern: Vec<char> = pattern.chars().collect();
+        if pattern.is_empty() {
+            return;
+        }
+
+        let mut window = VecDeque::with_capacity(pattern.len());
+        for (end, c) in (start + 1..).zip(self.chars_at(start)) {
+            if window.len() == pattern.len() {
+                window.pop_front();
+            }
+            window.push_back(c);
+
+            if window.len() == pattern.len() && window.iter().eq(pattern.iter()) {
+                if !on_match(end - pattern.len()) {
+                    return;
                 }
-                RopeNode::Internal { left, right, .. } => {
-                    left.borrow().traverse_lines(target_line, current_line, result);
-                    right.borrow().traverse_lines(target_line, current_line, result);
+                if !overlapping {
+                    window.clear();
                 }
             }
         }
     }
 
     pub fn split_leaf(&self, text: &str, index: usize) -> (String, String) {
-        if index >= text.len() {
+        let char_count = text.chars().count();
+        if index >= char_count {
             return (text.to_string(), "".to_string());
         }
 
         match self.split_strategy {
             SplitStrategy::LineBased => {
-                let split_index = match text[..index].rfind('\n') {
+                let byte_index = char_to_byte(text, index);
+                let split_index = match text[..byte_index].rfind('\n') {
                     Some(pos) => pos + 1,
-                    None => index,
+                    None => byte_index,
                 };
 
                 if split_index == 0 || split_index >= text.len() {
@@ -270,13 +1419,14 @@ impl Rope {
                 )
             }
             SplitStrategy::FixedSize(max_size) => {
-                if text.len() <= max_size {
+                if char_count <= max_size {
                     return (text.to_string(), "".to_string());
                 }
 
-                let split_index = match text[..max_size].rfind(' ') {
+                let max_byte = char_to_byte(text, max_size);
+                let split_index = match text[..max_byte].rfind(' ') {
                     Some(pos) => pos + 1,
-                    None => max_size,
+                    None => max_byte,
                 };
 
                 if split_index == 0 || split_index >= text.len() {
@@ -291,63 +1441,414 @@ impl Rope {
         }
     }
 
+    // Cuts the rope at char offset `index`, keeping `0..index` in `self` and
+    // returning the rest. Only the nodes along the cut are touched, every other
+    // subtree moves over as is. This isn't undoable: the returned rope starts
+    // with no history, and `self` drops its own since recorded offsets past the
+    // cut no longer exist.
     pub fn split_at(&mut self, index: usize) -> Rope {
-        match &mut self.root.take() {
-            Some(RopeNode::Leaf(text)) => {
-                let (left_part, right_part) = self.split_leaf(&text, index);
+        let right = self.split_off(index);
+        if !right.is_empty() {
+            self.record_change(Change {
+                range: self.len()..self.len() + right.len(),
+                inserted_len: 0,
+                deleted_text_len: right.len(),
+                line_delta: -(right.newline_count() as isize),
+            });
+        }
+        self.undo_stack.clear();
+        self.undo_stack.advance_generation();
+        right
+    }
 
-                self.root = Some(RopeNode::Leaf(left_part));
-                Rope {
-                    root: Some(RopeNode::Leaf(right_part)),
-                    split_strategy: self.split_strategy,
+    fn split_off(&mut self, index: usize) -> Rope {
+        let mut tail = Rope::new(self.split_strategy);
+        tail.line_ending = self.line_ending;
+        tail.min_leaf_size = self.min_leaf_size;
+
+        match self.root.take() {
+            Some(RopeNode::Leaf(text)) => {
+                let (left_part, right_part) = text.split_at(char_to_byte(&text, index));
+                if !left_part.is_empty() {
+                    self.root = Some(RopeNode::Leaf(left_part.to_string()));
+                }
+                if !right_part.is_empty() {
+                    tail.root = Some(RopeNode::Leaf(right_part.to_string()));
                 }
             }
-            Some(RopeNode::Internal { left, right, left_size }) => {
-                if index < *left_size {
-                    let new_right = left.borrow_mut().split_at(index);
-                    let mut new_rope = Rope::new(self.split_strategy);
-                    new_rope.root = Some(RopeNode::Internal {
-                        left: Rc::new(RefCell::new(new_right)),
-                        right: right.clone(),
-                        left_size: index,
-                    });
-                    new_rope
+            Some(RopeNode::Internal { mut left, mut right, left_size, .. }) => {
+                let left = Arc::make_mut(&mut left).take_root(self.split_strategy);
+                let right = Arc::make_mut(&mut right).take_root(self.split_strategy);
+
+                if index < left_size {
+                    let mut left = left;
+                    let middle = left.split_off(index);
+                    self.root = left.root;
+                    tail.root = Self::join(middle, right).root;
                 } else {
-                    right.borrow_mut().split_at(index - *left_size)
+                    let mut right = right;
+                    let rest = right.split_off(index - left_size);
+                    self.root = Self::join(left, right).root;
+                    tail.root = rest.root;
                 }
             }
-            None => Rope::new(self.split_strategy),
+            None => {}
+        }
+        tail
+    }
+
+    // Moves the node out of this rope, leaving it empty.
+    fn take_root(&mut self, strategy: SplitStrategy) -> Rope {
+        let mut rope = Rope::new(strategy);
+        rope.root = self.root.take();
+        rope
+    }
+
+    // Joins two ropes under a new node without touching either side's leaves.
+    fn join(left: Rope, right: Rope) -> Rope {
+        if right.root.is_none() {
+            return left;
+        }
+        if left.root.is_none() {
+            return right;
+        }
+
+        let mut rope = Rope::new(left.split_strategy);
+        let left_size = left.len();
+        rope.root = Some(RopeNode::internal(Arc::new(left), Arc::new(right), left_size));
+        rope.rebalance_if_skewed();
+        rope
+    }
+
+    // Appends `other` in O(log n). `self` keeps its undo history, which stays
+    // valid since nothing before the old end moves; `other`'s history is
+    // dropped and the append itself can't be undone.
+    pub fn append(&mut self, other: Rope) {
+        if !other.is_empty() {
+            self.undo_stack.advance_generation();
+            self.record_change(Change {
+                range: self.len()..self.len(),
+                inserted_len: other.len(),
+                deleted_text_len: 0,
+                line_delta: other.newline_count() as isize,
+            });
         }
+        let left = self.take_root(self.split_strategy);
+        let joined = Self::join(left, other);
+        self.root = joined.root;
     }
 
+    // Same as `append`, kept for older callers.
     pub fn merge(&mut self, other: Rope) {
-        let left_size = self.len();
+        self.append(other);
+    }
 
-        let new_left = Rc::new(RefCell::new(self.clone()));
-        let new_right = Rc::new(RefCell::new(other));
+    // Joins `a` and `b` into a new rope that keeps `a`'s settings and history.
+    pub fn concat(mut a: Rope, b: Rope) -> Rope {
+        a.append(b);
+        a
+    }
 
-        self.root = Some(RopeNode::Internal {
-            left: new_left,
-            right: new_right,
-            left_size,
-        });
+    // A read-only copy of the text as it is now, made in O(1): it shares the
+    // tree, and later edits copy the nodes they touch instead of changing
+    // them under the snapshot.
+    pub fn snapshot(&self) -> RopeSnapshot {
+        let mut rope = Rope::new(self.split_strategy);
+        rope.line_ending = self.line_ending;
+        rope.root = self.root.clone();
+        RopeSnapshot { rope }
+    }
+
+    // Goes back to the text and line ending of `snapshot`, as one undo entry
+    // made of the lines that differ and the ending conversion, so a single
+    // undo brings back the rope from before the restore. The rest of the undo
+    // history stays and the redo history is dropped, the same as after any
+    // other edit. The rope then shares the snapshot's tree again, unless it
+    // has been rechunked since; then the text is the same but stays chunked
+    // by the rope's own strategy.
+    pub fn restore(&mut self, snapshot: &RopeSnapshot) -> Result<(), RopeError> {
+        let edits: Vec<(usize, usize, String)> = self.diff(&snapshot.rope).into_iter().map(Into::into).collect();
+        self.apply_edits(&edits)?;
+        if self.split_strategy == snapshot.rope.split_strategy {
+            self.root = snapshot.rope.root.clone();
+        }
+        if snapshot.rope.line_ending != self.line_ending {
+            self.set_line_ending(snapshot.rope.line_ending);
+            if !edits.is_empty() {
+                self.undo_stack.join_last_two();
+            }
+        }
+        Ok(())
     }
 
     pub fn len(&self) -> usize {
         match &self.root {
-            Some(RopeNode::Leaf(text)) => text.len(),
-            Some(RopeNode::Internal { left, right, .. }) => left.borrow().len() + right.borrow().len(),
+            Some(RopeNode::Leaf(text)) => text.chars().count(),
+            Some(RopeNode::Internal { right, left_size, .. }) => left_size + right.len(),
+            None => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        match &self.root {
+            Some(RopeNode::Leaf(_)) => 1,
+            Some(RopeNode::Internal { leaves, .. }) => *leaves,
+            None => 0,
+        }
+    }
+
+    pub fn stats(&self) -> RopeStats {
+        RopeStats {
+            depth: self.depth(),
+            leaf_count: self.leaf_count(),
+            total_chars: self.len(),
+            bytes_allocated_estimate: self.allocated_bytes(),
+        }
+    }
+
+    // Walks the whole tree, so it's only meant for `stats`. Shared nodes count
+    // once per path to them.
+    fn allocated_bytes(&self) -> usize {
+        let own = std::mem::size_of::<Rope>();
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => own + text.capacity(),
+            Some(RopeNode::Internal { left, right, .. }) => {
+                // Each child sits in an `Arc` next to its two counters.
+                own + 2 * (2 * std::mem::size_of::<usize>()) + left.allocated_bytes() + right.allocated_bytes()
+            }
+            None => own,
+        }
+    }
+
+    // Levels from the root down to the deepest leaf; a single leaf has depth 1.
+    pub fn depth(&self) -> usize {
+        match &self.root {
+            Some(RopeNode::Leaf(_)) => 1,
+            Some(RopeNode::Internal { depth, .. }) => *depth,
+            None => 0,
+        }
+    }
+
+    // A tree counts as balanced while its depth stays within twice the depth
+    // of a perfectly balanced tree over the same leaves.
+    pub fn is_balanced(&self) -> bool {
+        let leaves = self.leaf_count();
+        if leaves <= 2 {
+            return true;
+        }
+
+        let ideal_depth = leaves.next_power_of_two().trailing_zeros() as usize + 1;
+        self.depth() <= 2 * ideal_depth
+    }
+
+    pub fn split_strategy(&self) -> SplitStrategy {
+        self.split_strategy
+    }
+
+    // Cuts the text into leaves again under `strategy`, which later edits
+    // follow too. The text and the undo history stay as they are.
+    pub fn rechunk(&mut self, strategy: SplitStrategy) {
+        let mut text = String::with_capacity(self.byte_size());
+        self.collect_text(&mut text);
+        self.split_strategy = strategy;
+        self.root = match text.is_empty() {
+            true => None,
+            false => Self::tree_from_text(&text, strategy),
+        };
+    }
+
+    pub fn min_leaf_size(&self) -> usize {
+        self.min_leaf_size
+    }
+
+    // Turns on coalescing for later rebalances, merging runs of adjacent
+    // leaves while they add up to at most `size` chars. `LineBased` leaves are
+    // only merged at line ends, so every leaf still holds whole lines, and
+    // `FixedSize` ones never grow past their limit. 0 turns it off again.
+    pub fn set_min_leaf_size(&mut self, size: usize) {
+        self.min_leaf_size = size;
+    }
+
+    // Rebuilds the internal skeleton over the existing leaves. Leaves are
+    // shared, not copied, so no text is moved around, except for the small
+    // ones coalescing merges.
+    pub fn rebalance(&mut self) {
+        let mut leaves = Vec::with_capacity(self.leaf_count());
+        self.collect_leaves(&mut leaves);
+        if self.min_leaf_size > 0 {
+            leaves = self.coalesce_leaves(leaves);
+        }
+        if leaves.len() < 2 {
+            if let [(leaf, _)] = leaves.as_slice() {
+                self.root = leaf.root.clone();
+            }
+            return;
+        }
+
+        let (root, _) = Self::build_balanced_tree(&leaves, self.split_strategy);
+        self.root = Arc::unwrap_or_clone(root).root;
+    }
+
+    // Called on every node along an edit path on the way back up, so the lowest
+    // skewed subtree is rebuilt rather than the whole tree.
+    fn rebalance_if_skewed(&mut self) {
+        if !self.is_balanced() {
+            self.rebalance();
+        }
+    }
+
+    // Appends each leaf to the one before it for as long as the two fit in
+    // `min_leaf_size` (and the `FixedSize` limit), leaving larger ones alone.
+    fn coalesce_leaves(&self, leaves: Vec<(Arc<Rope>, usize)>) -> Vec<(Arc<Rope>, usize)> {
+        let limit = match self.split_strategy {
+            SplitStrategy::LineBased => self.min_leaf_size,
+            SplitStrategy::FixedSize(max_size) => self.min_leaf_size.min(max_size.max(1)),
+        };
+
+        let mut merged: Vec<(Arc<Rope>, usize)> = Vec::with_capacity(leaves.len());
+        for (leaf, size) in leaves {
+            if let Some((last, last_size)) = merged.last_mut()
+                && *last_size + size <= limit
+                && let (Some(RopeNode::Leaf(last_text)), Some(RopeNode::Leaf(text))) = (&last.root, &leaf.root)
+                && (!matches!(self.split_strategy, SplitStrategy::LineBased) || last_text.ends_with('\n'))
+            {
+                *last = Self::leaf(format!("{}{}", last_text, text), self.split_strategy).0;
+                *last_size += size;
+                continue;
+            }
+            merged.push((leaf, size));
+        }
+        merged
+    }
+
+    fn collect_leaves(&self, out: &mut Vec<(Arc<Rope>, usize)>) {
+        if let Some(RopeNode::Internal { left, right, .. }) = &self.root {
+            for child in [left, right] {
+                if matches!(child.root, Some(RopeNode::Leaf(_))) {
+                    out.push((child.clone(), child.len()));
+                } else {
+                    child.collect_leaves(out);
+                }
+            }
+        }
+    }
+
+    // Returns the subtree over `leaves` along with its char length.
+    fn build_balanced_tree(leaves: &[(Arc<Rope>, usize)], strategy: SplitStrategy) -> (Arc<Rope>, usize) {
+        if let [(leaf, size)] = leaves {
+            return (leaf.clone(), *size);
+        }
+
+        let mid = leaves.len() / 2;
+        let (left, left_size) = Self::build_balanced_tree(&leaves[..mid], strategy);
+        let (right, right_size) = Self::build_balanced_tree(&leaves[mid..], strategy);
+
+        let mut node = Rope::new(strategy);
+        node.root = Some(RopeNode::internal(left, right, left_size));
+        (Arc::new(node), left_size + right_size)
+    }
+
+    // Number of lines, i.e. newlines + 1. A trailing '\n' starts one more,
+    // empty line, so "a\n" has 2 and every line number up to `lines() - 1` is
+    // one a cursor can be on. Only leaves are scanned, internal nodes answer
+    // from their cached count.
+    pub fn lines(&self) -> usize {
+        self.newline_count() + 1
+    }
+
+    // Lines the way `str::lines` counts them, with a trailing '\n' ending the
+    // last line instead of starting a new one: "" has 0, "a" and "a\n" have 1.
+    pub fn display_lines(&self) -> usize {
+        match self.len() {
+            0 => 0,
+            len => self.newline_count() + usize::from(self.get_char(len - 1) != Some('\n')),
+        }
+    }
+
+    fn newline_count(&self) -> usize {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => text.matches('\n').count(),
+            Some(RopeNode::Internal { newlines, .. }) => *newlines,
             None => 0,
         }
     }
 
+    // Recomputes every cached newline count from the leaves and reports the
+    // first node whose cache disagrees.
+    pub fn verify_line_counts(&self) -> Result<usize, String> {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => Ok(text.matches('\n').count()),
+            Some(RopeNode::Internal { left, right, newlines, .. }) => {
+                let actual = left.verify_line_counts()? + right.verify_line_counts()?;
+                if actual != *newlines {
+                    return Err(format!("cached newlines {} but subtree has {}", newlines, actual));
+                }
+                Ok(actual)
+            }
+            None => Ok(0),
+        }
+    }
+
+    // Calls `f` on every leaf in order until it returns false. Returns false if
+    // the traversal was stopped early.
+    fn visit_leaves(&self, f: &mut dyn FnMut(&str) -> bool) -> bool {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => f(text),
+            Some(RopeNode::Internal { left, right, .. }) => {
+                left.visit_leaves(f) && right.visit_leaves(f)
+            }
+            None => true,
+        }
+    }
+
+    // The chars from `index` on. Each leaf is found from the root once, so a
+    // scan from the middle of a large rope doesn't start at its beginning.
+    pub fn chars_at(&self, index: usize) -> Chars<'_> {
+        Chars { rope: self, leaf: "".chars(), index }
+    }
+
+    // The chars before `index`, nearest first.
+    pub fn chars_before(&self, index: usize) -> CharsBefore<'_> {
+        CharsBefore { rope: self, leaf: "".chars(), index: index.min(self.len()) }
+    }
+
+    // The leaf holding the char at `index`, with the index its text starts at.
+    fn leaf_at(&self, index: usize) -> Option<(&str, usize)> {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => Some((text.as_str(), 0)),
+            Some(RopeNode::Internal { left, right, left_size, .. }) if index < *left_size => left.leaf_at(index),
+            Some(RopeNode::Internal { right, left_size, .. }) => {
+                let (text, start) = right.leaf_at(index - left_size)?;
+                Some((text, start + left_size))
+            }
+            None => None,
+        }
+    }
+
+    fn collect_text(&self, out: &mut String) {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => out.push_str(text),
+            Some(RopeNode::Internal { left, right, .. }) => {
+                left.collect_text(out);
+                right.collect_text(out);
+            }
+            None => {}
+        }
+    }
+
+    // Indented dump of the tree, one node per line. Leaf text is escaped so
+    // newlines inside a leaf don't break the layout.
     pub fn debug_string(&self) -> String {
         fn traverse(node: &Option<RopeNode>, depth: usize) -> String {
             match node {
-                Some(RopeNode::Leaf(text)) => format!("{}Leaf: \"{}\"\n", "  ".repeat(depth), text),
-                Some(RopeNode::Internal { left, right, left_size }) => {
-                    let left_str = traverse(&left.borrow().root, depth + 1);
-                    let right_str = traverse(&right.borrow().root, depth + 1);
+                Some(RopeNode::Leaf(text)) => format!("{}Leaf: {:?}\n", "  ".repeat(depth), text),
+                Some(RopeNode::Internal { left, right, left_size, .. }) => {
+                    let left_str = traverse(&left.root, depth + 1);
+                    let right_str = traverse(&right.root, depth + 1);
                     format!(
                         "{}Internal (left_size = {}):\n{}{}",
                         "  ".repeat(depth),
@@ -361,4 +1862,224 @@ impl Rope {
         }
         traverse(&self.root, 0)
     }
-}
\ No newline at end of file
+
+    pub fn print_structure(&self) {
+        print!("{}", self.debug_string());
+    }
+
+    pub fn leaf_texts(&self) -> Vec<String> {
+        let mut texts = Vec::with_capacity(self.leaf_count());
+        self.visit_leaves(&mut |text| {
+            texts.push(text.to_string());
+            true
+        });
+        texts
+    }
+
+    // Checks the structural invariants every edit has to keep: cached sizes and
+    // counts match the children, no internal node has an empty child, and each
+    // leaf has the shape its split strategy allows.
+    pub fn validate(&self) -> Result<(), String> {
+        self.validate_node(self.min_leaf_size > 0)
+    }
+
+    // `coalesced` lets `LineBased` leaves hold several lines, since only the
+    // root knows whether coalescing is on.
+    fn validate_node(&self, coalesced: bool) -> Result<(), String> {
+        match &self.root {
+            Some(RopeNode::Leaf(text)) => match self.split_strategy {
+                SplitStrategy::LineBased if !coalesced && text.trim_end_matches('\n').contains('\n') => {
+                    Err(format!("leaf {:?} holds more than one line", text))
+                }
+                SplitStrategy::FixedSize(max_size) if text.chars().count() > max_size.max(1) => {
+                    Err(format!("leaf {:?} is longer than {} chars", text, max_size))
+                }
+                _ => Ok(()),
+            },
+            Some(RopeNode::Internal { left, right, left_size, left_bytes, left_utf16, newlines, depth, leaves }) => {
+                left.validate_node(coalesced)?;
+                right.validate_node(coalesced)?;
+
+                if left.root.is_none() || right.root.is_none() || left.is_empty() || right.is_empty() {
+                    return Err("internal node has an empty child".to_string());
+                }
+                if *left_size != left.len() {
+                    return Err(format!("left_size is {} but the left subtree holds {} chars", left_size, left.len()));
+                }
+                if *left_bytes != left.byte_size() {
+                    return Err(format!("left_bytes is {} but the left subtree holds {} bytes", left_bytes, left.byte_size()));
+                }
+                if *left_utf16 != left.utf16_size() {
+                    return Err(format!("left_utf16 is {} but the left subtree holds {} code units", left_utf16, left.utf16_size()));
+                }
+                if *newlines != left.newline_count() + right.newline_count() {
+                    return Err(format!("cached newline count {} is stale", newlines));
+                }
+                if *depth != 1 + left.depth().max(right.depth()) || *leaves != left.leaf_count() + right.leaf_count() {
+                    return Err("cached depth or leaf count is stale".to_string());
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+// Byte offset of the `index`-th char, or the end of the text when out of range.
+fn char_to_byte(text: &str, index: usize) -> usize {
+    text.char_indices().nth(index).map_or(text.len(), |(byte, _)| byte)
+}
+
+// What a serialized rope holds. The tree itself is never stored: loading
+// rebuilds a balanced one from the text.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StoredRope {
+    text: String,
+    split_strategy: SplitStrategy,
+    line_ending: LineEnding,
+    undo_stack: UndoStack,
+    saved_generation: u64,
+    #[serde(default)]
+    min_leaf_size: usize,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Rope {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut text = String::new();
+        self.collect_text(&mut text);
+        StoredRope {
+            text,
+            split_strategy: self.split_strategy,
+            line_ending: self.line_ending,
+            undo_stack: self.undo_stack.clone(),
+            saved_generation: self.saved_generation,
+            min_leaf_size: self.min_leaf_size,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Rope {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let stored = StoredRope::deserialize(deserializer)?;
+        let mut rope = Rope::new(stored.split_strategy);
+        rope.root = Self::tree_from_text(&stored.text, stored.split_strategy);
+        rope.line_ending = stored.line_ending;
+        rope.undo_stack = stored.undo_stack;
+        rope.saved_generation = stored.saved_generation;
+        rope.min_leaf_size = stored.min_leaf_size;
+        Ok(rope)
+    }
+}
+
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = String::with_capacity(self.len());
+        self.collect_text(&mut text);
+
+        match self.line_ending {
+            LineEnding::Lf => f.write_str(&text),
+            LineEnding::CrLf => f.write_str(&text.replace('\n', LineEnding::CrLf.as_str())),
+        }
+    }
+}
+
+// See `Rope::chars_at`. `index` is where the next leaf is looked up once
+// the current one runs out.
+pub struct Chars<'a> {
+    rope: &'a Rope,
+    leaf: std::str::Chars<'a>,
+    index: usize,
+}
+
+impl Iterator for Chars<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(c) = self.leaf.next() {
+            self.index += 1;
+            return Some(c);
+        }
+        if self.index >= self.rope.len() {
+            return None;
+        }
+        let (text, start) = self.rope.leaf_at(self.index)?;
+        self.leaf = text[char_to_byte(text, self.index - start)..].chars();
+        self.index += 1;
+        self.leaf.next()
+    }
+}
+
+// See `Rope::chars_before`.
+pub struct CharsBefore<'a> {
+    rope: &'a Rope,
+    leaf: std::str::Chars<'a>,
+    index: usize,
+}
+
+impl Iterator for CharsBefore<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(c) = self.leaf.next_back() {
+            self.index -= 1;
+            return Some(c);
+        }
+        if self.index == 0 {
+            return None;
+        }
+        let (text, start) = self.rope.leaf_at(self.index - 1)?;
+        self.leaf = text[..char_to_byte(text, self.index - start)].chars();
+        self.index -= 1;
+        self.leaf.next_back()
+    }
+}
+
+// The text of a rope at one point in time, see `Rope::snapshot`.
+pub struct RopeSnapshot {
+    rope: Rope,
+}
+
+impl RopeSnapshot {
+    pub fn len(&self) -> usize {
+        self.rope.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rope.is_empty()
+    }
+
+    pub fn lines(&self) -> usize {
+        self.rope.lines()
+    }
+
+    pub fn line(&self, line_number: usize) -> Option<String> {
+        self.rope.get_line(line_number)
+    }
+
+    pub fn text_range(&self, start: usize, end: usize) -> String {
+        self.rope.text_range(start, end)
+    }
+
+    // Walks the shared tree leaf by leaf, without copying the text.
+    pub fn chars(&self) -> Chars<'_> {
+        self.rope.chars_at(0)
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.rope.line_ending()
+    }
+
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.rope.write_to(writer)
+    }
+}
+
+impl fmt::Display for RopeSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.rope.fmt(f)
+    }
+}
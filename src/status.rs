@@ -0,0 +1,75 @@
+// How a message on the bottom line is shown: errors in red, warnings in
+// yellow, plain info as is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn paint(self, text: &str) -> String {
+        match self {
+            Severity::Info => text.to_string(),
+            Severity::Warning => format!("\x1b[33m{}\x1b[0m", text),
+            Severity::Error => format!("\x1b[31m{}\x1b[0m", text),
+        }
+    }
+}
+
+// What the status bar above the message line shows. `line` and `column` are
+// 1-based, `column` counted in screen columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusBar<'a> {
+    pub mode: &'a str,
+    pub filename: Option<&'a str>,
+    pub modified: bool,
+    pub file_format: &'a str,
+    pub recording: Option<char>,
+    pub line: usize,
+    pub column: usize,
+    pub line_count: usize,
+}
+
+impl StatusBar<'_> {
+    // The bar padded to exactly `width` chars, mode and file on the left and
+    // the position on the right. When that's too wide the file name loses its
+    // start first, then the right side goes, then whatever is left is cut.
+    pub fn format(&self, width: usize) -> String {
+        let modified = if self.modified { " [+]" } else { "" };
+        let recording = self.recording.map(|register| format!("recording @{}  ", register)).unwrap_or_default();
+        let right = format!(
+            "{}[{}]  {}:{}  {}%",
+            recording,
+            self.file_format,
+            self.line,
+            self.column,
+            self.line * 100 / self.line_count.max(1),
+        );
+
+        let name = self.filename.unwrap_or("[No Name]");
+        let fixed = self.mode.chars().count() + 2 + modified.len();
+        let right_len = right.chars().count();
+        let (name, right) = if fixed + name.chars().count() + 1 + right_len <= width {
+            (name.to_string(), right)
+        } else if fixed + 2 + right_len <= width {
+            (truncate_start(name, width - fixed - 1 - right_len), right)
+        } else {
+            (truncate_start(name, width.saturating_sub(fixed).max(1)), String::new())
+        };
+
+        let left = format!("{}  {}{}", self.mode, name, modified);
+        let gap = width.saturating_sub(left.chars().count() + right.chars().count());
+        format!("{}{}{}", left, " ".repeat(gap), right).chars().take(width).collect()
+    }
+}
+
+// The last `width` chars of `name`, with a '<' in front when some were cut.
+fn truncate_start(name: &str, width: usize) -> String {
+    let count = name.chars().count();
+    if count <= width {
+        return name.to_string();
+    }
+    let kept: String = name.chars().skip(count - width.saturating_sub(1)).collect();
+    format!("<{}", kept)
+}
@@ -0,0 +1,63 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::rope::RopeSnapshot;
+
+// How often the main loop wakes up without a key, so autosave can run.
+pub const TICK_MS: i32 = 500;
+
+// What to do with a swap file left next to a file being opened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recovery {
+    // Load the swap file's text in place of the file's.
+    Recover,
+    // Keep the file as it is and delete the swap file.
+    Ignore,
+}
+
+impl Recovery {
+    // The answer to the recovery prompt, `None` for keys it doesn't take.
+    pub fn from_key(c: char) -> Option<Self> {
+        match c.to_ascii_lowercase() {
+            'r' => Some(Recovery::Recover),
+            'i' | 'd' => Some(Recovery::Ignore),
+            _ => None,
+        }
+    }
+}
+
+// `dir/name` is backed up in `dir/.name.swp`.
+pub fn swap_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!(".{}.swp", name))
+}
+
+// A swap file is worth recovering when it was written after the file was
+// last changed, or when the file is gone.
+pub fn swap_is_newer(path: &Path, swap: &Path) -> bool {
+    let Ok(swapped) = fs::metadata(swap).and_then(|meta| meta.modified()) else {
+        return false;
+    };
+    match fs::metadata(path).and_then(|meta| meta.modified()) {
+        Ok(saved) => swapped > saved,
+        Err(_) => true,
+    }
+}
+
+// Streams the snapshot leaf by leaf, with the final terminator a save adds,
+// so the swap file reads back like the file itself.
+pub fn write_swap(snapshot: &RopeSnapshot, swap: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(swap)?);
+    snapshot.write_to(&mut writer)?;
+    writer.write_all(snapshot.line_ending().as_str().as_bytes())?;
+    writer.flush()
+}
+
+// Deletes the swap file, which may not exist.
+pub fn remove_swap(swap: &Path) -> io::Result<()> {
+    match fs::remove_file(swap) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -1,40 +1,129 @@
-use std::io;
-use std::os::unix::io::AsRawFd;
-use libc::{tcgetattr, tcsetattr, termios, TCSAFLUSH, ECHO, ICANON};
+use std::io::{self, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::{Arc, Mutex};
+use libc::{tcgetattr, tcsetattr, termios, TCSAFLUSH, ECHO, ICANON, ISIG};
 
-static mut ORIGINAL_TERMIOS: Option<termios> = None;
+// The settings from before raw mode, `None` while raw mode is off.
+static ORIGINAL_TERMIOS: Mutex<Option<termios>> = Mutex::new(None);
 
 pub fn enable_raw_mode() -> io::Result<()> {
-    let stdin_fd = io::stdin().as_raw_fd();
+    enable_raw_mode_on(io::stdin().as_raw_fd())
+}
+
+pub fn disable_raw_mode() {
+    disable_raw_mode_on(io::stdin().as_raw_fd());
+}
+
+// Calling this again while raw mode is on changes nothing, the settings
+// restored later stay the ones from the first call.
+pub fn enable_raw_mode_on(fd: RawFd) -> io::Result<()> {
+    let mut original = ORIGINAL_TERMIOS.lock().unwrap_or_else(|e| e.into_inner());
+    if original.is_some() {
+        return Ok(());
+    }
 
     let original_termios: termios = unsafe {
         let mut termios = std::mem::zeroed();
-        if tcgetattr(stdin_fd, &mut termios) !=0 {
+        if tcgetattr(fd, &mut termios) != 0 {
             return Err(io::Error::last_os_error());
         }
         termios
     };
 
-    unsafe {
-        ORIGINAL_TERMIOS = Some(original_termios);
-    }
-    
     let mut raw_termios = original_termios;
-    raw_termios.c_lflag &= !(ECHO | ICANON );
+    // Without ISIG, Ctrl+C and Ctrl+Z arrive as keys instead of signals.
+    raw_termios.c_lflag &= !(ECHO | ICANON | ISIG);
 
-    if unsafe { tcsetattr(stdin_fd, TCSAFLUSH, &raw_termios) } != 0 {
+    if unsafe { tcsetattr(fd, TCSAFLUSH, &raw_termios) } != 0 {
         return Err(io::Error::last_os_error());
     }
 
+    *original = Some(original_termios);
     Ok(())
 }
 
-pub fn disable_raw_mode() {
-    let stdin_fd = io::stdin().as_raw_fd();
-
-    unsafe {
-        if let Some(original) = ORIGINAL_TERMIOS {
-            tcsetattr(stdin_fd, TCSAFLUSH, &original);
+pub fn disable_raw_mode_on(fd: RawFd) {
+    let mut original = ORIGINAL_TERMIOS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(saved) = original.take() {
+        unsafe {
+            tcsetattr(fd, TCSAFLUSH, &saved);
         }
     }
 }
+
+// The settings raw mode will restore, if it is on.
+pub fn original_termios() -> Option<termios> {
+    *ORIGINAL_TERMIOS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Asks the terminal for SGR mouse reports of clicks, drags with the button
+// held and the wheel.
+pub fn enable_mouse_capture() {
+    print!("\x1b[?1002h\x1b[?1006h");
+    let _ = io::stdout().flush();
+}
+
+pub fn disable_mouse_capture() {
+    print!("\x1b[?1006l\x1b[?1002l");
+    let _ = io::stdout().flush();
+}
+
+// Draws on a screen of its own, so the shell's scrollback is back untouched
+// once the editor leaves it.
+pub fn enter_alternate_screen() {
+    print!("\x1b[?1049h");
+    let _ = io::stdout().flush();
+}
+
+pub fn leave_alternate_screen() {
+    print!("\x1b[?1049l");
+    let _ = io::stdout().flush();
+}
+
+// Leaves the terminal the way the shell expects it: no mouse reports, cursor
+// shown, back on the main screen, raw mode off. Safe to call more than once.
+pub fn restore_terminal() {
+    disable_mouse_capture();
+    print!("\x1b[?25h");
+    leave_alternate_screen();
+    let _ = io::stdout().flush();
+    disable_raw_mode();
+}
+
+// Runs its cleanup when dropped, so the terminal is put back however the
+// scope is left, unwinding from a panic included.
+pub struct TerminalGuard {
+    cleanup: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl TerminalGuard {
+    pub fn new(cleanup: impl Fn() + Send + Sync + 'static) -> Self {
+        TerminalGuard { cleanup: Arc::new(cleanup) }
+    }
+
+    // Enables raw mode, the alternate screen and mouse capture, restored by
+    // `restore_terminal`.
+    pub fn raw_mode() -> io::Result<Self> {
+        enable_raw_mode()?;
+        enter_alternate_screen();
+        enable_mouse_capture();
+        Ok(TerminalGuard::new(restore_terminal))
+    }
+
+    // Drop only runs once the panic message is out, on a screen still in raw
+    // mode. The hook runs the cleanup before that message instead.
+    pub fn install_panic_hook(&self) {
+        let cleanup = Arc::clone(&self.cleanup);
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            cleanup();
+            default_hook(info);
+        }));
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        (self.cleanup)();
+    }
+}
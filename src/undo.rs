@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+
+use crate::rope::LineEnding;
+
+// Consecutive single-char inserts are merged into one action up to this many
+// chars, so undo removes a burst of typing instead of a single keystroke.
+const MAX_GROUP_CHARS: usize = 64;
+
+// Every action stores enough to be reverted and replayed: `Insert` and `Delete`
+// hold the text that was added or removed at `index`, `Replace` holds both
+// sides, and `SetLineEnding` the endings before and after a conversion.
+// `Group` holds several actions in the order they were applied, undone and
+// redone together.
+// `cursor` is the (line, col) position, both zero-based and col in chars, where
+// the cursor was before the edit.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UndoAction {
+    Insert { index: usize, text: String, cursor: Option<(usize, usize)> },
+    Delete { index: usize, text: String, cursor: Option<(usize, usize)> },
+    Replace { index: usize, old: String, new: String, cursor: Option<(usize, usize)> },
+    SetLineEnding { old: LineEnding, new: LineEnding, cursor: Option<(usize, usize)> },
+    Group { actions: Vec<UndoAction>, cursor: Option<(usize, usize)> },
+}
+
+impl UndoAction {
+    pub fn index(&self) -> usize {
+        match self {
+            UndoAction::Insert { index, .. } | UndoAction::Delete { index, .. } | UndoAction::Replace { index, .. } => *index,
+            UndoAction::SetLineEnding { .. } => 0,
+            UndoAction::Group { actions, .. } => actions.iter().map(UndoAction::index).min().unwrap_or(0),
+        }
+    }
+
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        match self {
+            UndoAction::Insert { cursor, .. }
+            | UndoAction::Delete { cursor, .. }
+            | UndoAction::Replace { cursor, .. }
+            | UndoAction::SetLineEnding { cursor, .. }
+            | UndoAction::Group { cursor, .. } => *cursor,
+        }
+    }
+}
+
+// Every document state gets a generation number. Each action is stored with
+// the generation it was pushed over, so popping it restores that number, while
+// new pushes always take a fresh one from `last_generation`. Comparing against
+// a saved generation then tells whether the text is back to its saved state.
+// Undone actions move to `redone` along with the generation they undid, and
+// any new push drops them.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UndoStack {
+    actions: VecDeque<(UndoAction, u64)>,
+    redone: Vec<(UndoAction, u64)>,
+    capacity: Option<usize>,
+    // Set while the newest action is typing, single-char inserts other than
+    // '\n', so the next typed char can join it.
+    group_open: bool,
+    generation: u64,
+    last_generation: u64,
+}
+
+impl UndoStack {
+    pub fn new() -> Self {
+        UndoStack {
+            actions: VecDeque::new(),
+            redone: Vec::new(),
+            capacity: None,
+            group_open: false,
+            generation: 0,
+            last_generation: 0,
+        }
+    }
+
+    // Keeps at most `capacity` actions, dropping the oldest ones first.
+    pub fn with_capacity(capacity: usize) -> Self {
+        UndoStack { capacity: Some(capacity), ..UndoStack::new() }
+    }
+
+    // Drops every action but keeps the capacity and the current generation.
+    pub fn clear(&mut self) {
+        self.actions.clear();
+        self.redone.clear();
+        self.group_open = false;
+    }
+
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    pub fn push(&mut self, action: UndoAction) {
+        let generation = self.generation;
+        self.advance_generation();
+        self.redone.clear();
+        if self.group_open && self.try_coalesce(&action) {
+            return;
+        }
+
+        self.group_open = is_typed_char(&action);
+        self.actions.push_back((action, generation));
+        self.trim();
+    }
+
+    pub fn pop(&mut self) -> Option<UndoAction> {
+        self.group_open = false;
+        let (action, generation) = self.actions.pop_back()?;
+        self.redone.push((action.clone(), self.generation));
+        self.generation = generation;
+        Some(action)
+    }
+
+    // Takes back the most recently undone action so it can be applied again.
+    pub fn pop_redo(&mut self) -> Option<UndoAction> {
+        self.group_open = false;
+        let (action, generation) = self.redone.pop()?;
+        self.actions.push_back((action.clone(), self.generation));
+        self.generation = generation;
+        self.trim();
+        Some(action)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redone.is_empty()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // Moves to a fresh generation, for changes that aren't recorded as actions.
+    pub fn advance_generation(&mut self) {
+        self.last_generation += 1;
+        self.generation = self.last_generation;
+    }
+
+    // The next push starts a new action even if it could have been merged.
+    pub fn break_undo_group(&mut self) {
+        self.group_open = false;
+    }
+
+    // Folds the newest action into the one before it as a `Group`, keeping
+    // the older one's cursor and generation, so both are undone together.
+    pub fn join_last_two(&mut self) {
+        if self.actions.len() < 2 {
+            return;
+        }
+        let (newest, _) = self.actions.pop_back().expect("two actions");
+        let (older, generation) = self.actions.pop_back().expect("two actions");
+        let cursor = older.cursor();
+        let mut actions = match older {
+            UndoAction::Group { actions, .. } => actions,
+            older => vec![older],
+        };
+        actions.push(newest);
+        self.actions.push_back((UndoAction::Group { actions, cursor }, generation));
+    }
+
+    // Folds every action pushed since the document was at `generation` into
+    // one `Group`, keeping the first one's cursor and generation, so a command
+    // made of several edits undoes at once. Stored generations only grow up
+    // the stack, so those are the newest actions, however many `trim` has
+    // dropped from the bottom meanwhile. Groups among them are flattened, and
+    // a `Group` at the start is extended in place so folding after every step
+    // of a long command stays cheap.
+    pub fn group_since(&mut self, generation: u64) {
+        let count = self.actions.iter().rev().take_while(|(_, pushed_over)| *pushed_over >= generation).count();
+        if count < 2 {
+            return;
+        }
+        let newer: Vec<UndoAction> = self.actions.drain(self.actions.len() - count + 1..).map(|(action, _)| action).collect();
+        let (first, _) = self.actions.back_mut().expect("count is at least two");
+        if !matches!(first, UndoAction::Group { .. }) {
+            let cursor = first.cursor();
+            let single = std::mem::replace(first, UndoAction::Group { actions: Vec::new(), cursor });
+            if let UndoAction::Group { actions, .. } = first {
+                actions.push(single);
+            }
+        }
+        if let UndoAction::Group { actions, .. } = first {
+            for action in newer {
+                match action {
+                    UndoAction::Group { actions: inner, .. } => actions.extend(inner),
+                    action => actions.push(action),
+                }
+            }
+        }
+        self.group_open = false;
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    fn try_coalesce(&mut self, action: &UndoAction) -> bool {
+        let UndoAction::Insert { index, text, .. } = action else {
+            return false;
+        };
+        let Some((UndoAction::Insert { index: prev_index, text: prev_text, .. }, _)) = self.actions.back_mut() else {
+            return false;
+        };
+
+        let prev_len = prev_text.chars().count();
+        if !is_typed_char(action) || *index != *prev_index + prev_len || prev_len >= MAX_GROUP_CHARS {
+            return false;
+        }
+
+        prev_text.push_str(text);
+        true
+    }
+
+    fn trim(&mut self) {
+        if let Some(capacity) = self.capacity {
+            while self.actions.len() > capacity {
+                self.actions.pop_front();
+            }
+        }
+    }
+}
+
+// A single typed char. A line break ends the run of typing it's in.
+fn is_typed_char(action: &UndoAction) -> bool {
+    matches!(action, UndoAction::Insert { text, .. } if text.chars().count() == 1 && text != "\n")
+}
@@ -0,0 +1,221 @@
+// Minimal built-in grapheme and width rules. They cover combining marks,
+// variation selectors, emoji modifiers and ZWJ sequences, flag pairs and CRLF,
+// which is what cursor movement needs to never stop inside a visible char.
+
+const ZWJ: char = '\u{200D}';
+
+fn is_extend(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F
+        | 0x0483..=0x0489
+        | 0x0591..=0x05BD
+        | 0x0610..=0x061A
+        | 0x064B..=0x065F
+        | 0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200C | 0x200D
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+        | 0x1F3FB..=0x1F3FF
+        | 0xE0020..=0xE007F
+        | 0xE0100..=0xE01EF
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+// Whether `c` belongs to the same cluster as `prev`. `ri_run` is the number of
+// regional indicators in a row ending at `prev`, so flags pair up correctly.
+fn continues_cluster(prev: char, c: char, ri_run: usize) -> bool {
+    (prev == '\r' && c == '\n')
+        || is_extend(c)
+        || prev == ZWJ
+        || (is_regional_indicator(prev) && is_regional_indicator(c) && ri_run % 2 == 1)
+}
+
+// Next boundary after `index`, where `char_at` looks up the `len` chars of
+// the text. `index` itself is assumed to be a boundary.
+pub fn next_boundary(char_at: impl Fn(usize) -> Option<char>, index: usize, len: usize) -> usize {
+    if index >= len {
+        return len;
+    }
+
+    let mut prev = char_at(index).unwrap_or('\0');
+    let mut ri_run = usize::from(is_regional_indicator(prev));
+    let mut i = index + 1;
+
+    while let Some(c) = char_at(i).filter(|_| i < len) {
+        if !continues_cluster(prev, c, ri_run) {
+            break;
+        }
+        ri_run = if is_regional_indicator(c) { ri_run + 1 } else { 0 };
+        prev = c;
+        i += 1;
+    }
+    i
+}
+
+// Last boundary before `index`. Steps back to a char that surely starts a
+// cluster, then walks forward so flag pairs keep their parity.
+pub fn prev_boundary(char_at: impl Fn(usize) -> Option<char>, index: usize, len: usize) -> usize {
+    if index == 0 {
+        return 0;
+    }
+
+    let mut start = index.min(len) - 1;
+    while start > 0 {
+        let c = char_at(start).unwrap_or('\0');
+        let prev = char_at(start - 1).unwrap_or('\0');
+        if !(is_extend(c) || prev == ZWJ || is_regional_indicator(c) || (prev == '\r' && c == '\n')) {
+            break;
+        }
+        start -= 1;
+    }
+
+    let mut boundary = start;
+    loop {
+        let next = next_boundary(&char_at, boundary, len);
+        if next >= index {
+            return boundary;
+        }
+        boundary = next;
+    }
+}
+
+// Byte offset of the grapheme boundary after `byte` in `text`.
+pub fn next_boundary_in(text: &str, byte: usize) -> usize {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let index = chars.partition_point(|&(b, _)| b < byte);
+    let next = next_boundary(|i| chars.get(i).map(|&(_, c)| c), index, chars.len());
+    chars.get(next).map_or(text.len(), |&(b, _)| b)
+}
+
+// Byte offset of the grapheme boundary before `byte` in `text`.
+pub fn prev_boundary_in(text: &str, byte: usize) -> usize {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let index = chars.partition_point(|&(b, _)| b < byte);
+    let prev = prev_boundary(|i| chars.get(i).map(|&(_, c)| c), index, chars.len());
+    chars.get(prev).map_or(text.len(), |&(b, _)| b)
+}
+
+// Moves `byte` back to the start of the grapheme it falls in.
+pub fn floor_boundary(text: &str, byte: usize) -> usize {
+    if byte >= text.len() {
+        return text.len();
+    }
+    prev_boundary_in(text, byte + 1)
+}
+
+// Terminal columns taken by `c`: 0 for combining marks, 2 for wide East Asian
+// chars and most emoji, 1 otherwise. A flag's two indicators count 1 each.
+// With the `unicode` feature the widths come from `unicode-width`, but marks
+// that extend a cluster still take none, so an emoji keeps its 2 columns
+// with a skin tone after it.
+pub fn char_width(c: char) -> usize {
+    if is_extend(c) {
+        return 0;
+    }
+    #[cfg(feature = "unicode")]
+    return unicode_width::UnicodeWidthChar::width(c).unwrap_or(1);
+    #[cfg(not(feature = "unicode"))]
+    return builtin_width(c);
+}
+
+#[cfg(not(feature = "unicode"))]
+fn builtin_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+pub fn str_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+// Column reached after `text` starting at column 0, with tabs snapping to the
+// next multiple of `tab_width`.
+pub fn display_width(text: &str, tab_width: usize) -> usize {
+    column_after(0, text, tab_width)
+}
+
+// Column reached after `text` when it starts at `column`.
+pub fn column_after(column: usize, text: &str, tab_width: usize) -> usize {
+    text.chars().fold(column, |column, c| advance(column, c, tab_width))
+}
+
+// Byte offset of the grapheme covering `column`, or the end of `text` when the
+// line is shorter than that.
+pub fn byte_at_column(text: &str, column: usize, tab_width: usize) -> usize {
+    let mut width = 0;
+    for (byte, c) in text.char_indices() {
+        let next = advance(width, c, tab_width);
+        if next > column {
+            return floor_boundary(text, byte);
+        }
+        width = next;
+    }
+    text.len()
+}
+
+// `text` with every tab replaced by the spaces up to its tab stop.
+pub fn expand_tabs(text: &str, tab_width: usize) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut column = 0;
+    for c in text.chars() {
+        let next = advance(column, c, tab_width);
+        if c == '\t' {
+            expanded.extend(std::iter::repeat_n(' ', next - column));
+        } else {
+            expanded.push(c);
+        }
+        column = next;
+    }
+    expanded
+}
+
+fn advance(column: usize, c: char, tab_width: usize) -> usize {
+    match c {
+        '\t' => {
+            let tab_width = tab_width.max(1);
+            column + tab_width - column % tab_width
+        }
+        _ => column + char_width(c),
+    }
+}
+
+// Char kinds for word motions, like vim: runs of word chars and runs of other
+// symbols are separate words, whitespace separates them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+pub fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Space
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
@@ -0,0 +1,132 @@
+use std::ops::Range;
+
+use crate::unicode;
+
+// One logical line cut into screen rows of at most `width` columns. Rows
+// break after whitespace where they can, so words move to the next row whole,
+// and a word longer than a row breaks at the grapheme that doesn't fit. Tab
+// stops keep counting from the start of the line, not of the row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineWrap {
+    text: String,
+    tab_width: usize,
+    // Byte range of each row and the column it starts at in the unwrapped line.
+    rows: Vec<(Range<usize>, usize)>,
+}
+
+impl LineWrap {
+    pub fn new(text: &str, width: usize, tab_width: usize) -> Self {
+        let width = width.max(1);
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let byte_of = |i: usize| chars.get(i).map_or(text.len(), |&(b, _)| b);
+
+        let mut rows = Vec::new();
+        let (mut row_start, mut row_column) = (0, 0);
+        let (mut index, mut column) = (0, 0);
+        // Byte offset and column just past the last whitespace seen.
+        let mut last_break: Option<(usize, usize)> = None;
+        while index < chars.len() {
+            let next = unicode::next_boundary(|i| chars.get(i).map(|&(_, c)| c), index, chars.len());
+            let (start, end) = (byte_of(index), byte_of(next));
+            let end_column = unicode::column_after(column, &text[start..end], tab_width);
+            if end_column - row_column > width && start > row_start {
+                let (break_byte, break_column) = last_break.filter(|&(b, _)| b > row_start).unwrap_or((start, column));
+                rows.push((row_start..break_byte, row_column));
+                row_start = break_byte;
+                row_column = break_column;
+
+                // What's left of the word may still not fit.
+                if end_column - row_column > width && start > row_start {
+                    rows.push((row_start..start, row_column));
+                    row_start = start;
+                    row_column = column;
+                }
+            }
+            if chars[index].1.is_whitespace() {
+                last_break = Some((end, end_column));
+            }
+            index = next;
+            column = end_column;
+        }
+        rows.push((row_start..text.len(), row_column));
+
+        LineWrap { text: text.to_string(), tab_width, rows }
+    }
+
+    // The whole line on a single row, however long it is.
+    pub fn unwrapped(text: &str, tab_width: usize) -> Self {
+        LineWrap::new(text, usize::MAX, tab_width)
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn row_range(&self, row: usize) -> Range<usize> {
+        self.rows[row.min(self.rows.len() - 1)].0.clone()
+    }
+
+    // Screen (row, column) of byte offset `x`. An offset where one row ends and
+    // the next begins belongs to the next row.
+    pub fn position(&self, x: usize) -> (usize, usize) {
+        let row = self.rows.iter().rposition(|(range, _)| range.start <= x).unwrap_or(0);
+        (row, self.column_in_row(row, x))
+    }
+
+    // Column of byte offset `x` counted from the start of `row`, with `x`
+    // clamped into the row.
+    pub fn column_in_row(&self, row: usize, x: usize) -> usize {
+        let (range, start_column) = &self.rows[row.min(self.rows.len() - 1)];
+        let x = x.clamp(range.start, range.end);
+        unicode::column_after(*start_column, &self.text[range.start..x], self.tab_width) - start_column
+    }
+
+    // Byte offset of the grapheme at `column` on `row`. Past the end of a row
+    // this is its last grapheme, or the end of the line on the last row.
+    pub fn byte_at(&self, row: usize, column: usize) -> usize {
+        let row = row.min(self.rows.len() - 1);
+        let (range, start_column) = &self.rows[row];
+        let target = start_column + column;
+
+        // Rows start and end on grapheme boundaries, so the row's own chars
+        // are enough to step through it, collected once.
+        let text = &self.text[range.clone()];
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let char_at = |i: usize| chars.get(i).map(|&(_, c)| c);
+        let byte_of = |i: usize| range.start + chars.get(i).map_or(text.len(), |&(b, _)| b);
+
+        let mut index = 0;
+        let mut current = *start_column;
+        while index < chars.len() {
+            let next = unicode::next_boundary(char_at, index, chars.len());
+            current = unicode::column_after(current, &self.text[byte_of(index)..byte_of(next)], self.tab_width);
+            if current > target {
+                return byte_of(index);
+            }
+            index = next;
+        }
+
+        if row + 1 < self.rows.len() {
+            byte_of(unicode::prev_boundary(char_at, chars.len(), chars.len()))
+        } else {
+            range.end
+        }
+    }
+
+    // What `row` shows on screen, tabs expanded to their stops.
+    pub fn row_text(&self, row: usize) -> String {
+        let (range, start_column) = &self.rows[row.min(self.rows.len() - 1)];
+        let mut expanded = String::new();
+        let mut column = *start_column;
+        for c in self.text[range.clone()].chars() {
+            let next = unicode::column_after(column, c.encode_utf8(&mut [0; 4]), self.tab_width);
+            if c == '\t' {
+                expanded.extend(std::iter::repeat_n(' ', next - column));
+            } else {
+                expanded.push(c);
+            }
+            column = next;
+        }
+        expanded
+    }
+}
@@ -0,0 +1,66 @@
+use rawdeo::rope::{Rope, RopeError, SplitStrategy};
+
+fn edit(start: usize, end: usize, text: &str) -> (usize, usize, String) {
+    (start, end, text.to_string())
+}
+
+fn sample() -> Rope {
+    Rope::from_string("let a = 1;\nlet b = 2;\nlet c = 3;\n", SplitStrategy::FixedSize(4))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_use_indices_of_the_original_text() {
+        let mut rope = sample();
+        // Out of order on purpose, the first one grows the text before the others.
+        rope.apply_edits(&[edit(15, 16, "bee"), edit(4, 5, "alpha"), edit(30, 31, ""), edit(33, 33, "// end\n")])
+            .unwrap();
+        assert_eq!(rope.to_string(), "let alpha = 1;\nlet bee = 2;\nlet c = ;\n// end\n");
+        rope.validate().unwrap();
+    }
+
+    #[test]
+    fn test_edits_undo_and_redo_as_one_step() {
+        let mut rope = sample();
+        rope.insert(0, "// top\n");
+        rope.apply_edits(&[edit(7, 10, "const"), edit(18, 21, "const"), edit(29, 32, "const")]).unwrap();
+        assert_eq!(rope.to_string(), "// top\nconst a = 1;\nconst b = 2;\nconst c = 3;\n");
+
+        assert_eq!(rope.undo(), Some((1, 0)));
+        assert_eq!(rope.to_string(), "// top\nlet a = 1;\nlet b = 2;\nlet c = 3;\n");
+        rope.redo();
+        assert_eq!(rope.to_string(), "// top\nconst a = 1;\nconst b = 2;\nconst c = 3;\n");
+        rope.undo();
+        rope.undo();
+        assert_eq!(rope.to_string(), sample().to_string());
+        assert!(!rope.can_undo());
+    }
+
+    #[test]
+    fn test_inserts_at_the_same_place_keep_their_order() {
+        let mut rope = Rope::from_string("ab", SplitStrategy::LineBased);
+        rope.apply_edits(&[edit(1, 1, "1"), edit(1, 1, "2"), edit(0, 1, "A")]).unwrap();
+        assert_eq!(rope.to_string(), "A12b");
+    }
+
+    #[test]
+    fn test_bad_edits_change_nothing() {
+        let mut rope = sample();
+        let overlap = rope.apply_edits(&[edit(0, 5, "x"), edit(4, 6, "y")]);
+        assert_eq!(overlap, Err(RopeError::OverlappingEdits { first: 0..5, second: 4..6 }));
+        assert_eq!(overlap.unwrap_err().to_string(), "edits 0..5 and 4..6 overlap");
+        assert_eq!(rope.apply_edits(&[edit(2, 1, "")]), Err(RopeError::InvalidRange { start: 2, end: 1, len: 33 }));
+        assert_eq!(
+            rope.apply_edits(&[edit(0, 1, ""), edit(30, 34, "")]),
+            Err(RopeError::InvalidRange { start: 30, end: 34, len: 33 })
+        );
+        assert_eq!(rope.to_string(), sample().to_string());
+        assert!(!rope.can_undo());
+
+        rope.apply_edits(&[]).unwrap();
+        assert!(!rope.can_undo());
+    }
+}
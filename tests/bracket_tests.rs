@@ -0,0 +1,107 @@
+use rawdeo::brackets::{bracket_from, matching_bracket, MAX_SCAN};
+use rawdeo::input::Key;
+use rawdeo::rope::{Rope, SplitStrategy};
+
+mod common;
+
+use common::buffer_with;
+
+fn rope(text: &str) -> Rope {
+    Rope::from_string(text, SplitStrategy::FixedSize(4))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chars_at_and_before_cross_leaves() {
+        let text = "héllo wörld, ünïcode";
+        let rope = rope(text);
+        assert!(rope.leaf_count() > 3);
+        for index in 0..=rope.len() {
+            let after: String = rope.chars_at(index).collect();
+            let before: String = rope.chars_before(index).collect();
+            assert_eq!(after, text.chars().skip(index).collect::<String>());
+            assert_eq!(before, text.chars().take(index).collect::<String>().chars().rev().collect::<String>());
+        }
+        assert_eq!(rope.chars_at(99).next(), None);
+        assert_eq!(rope.chars_before(99).next(), Some('e'));
+    }
+
+    #[test]
+    fn test_nested_brackets_match() {
+        let r = rope("f(a[0], {b: (c)})");
+        assert_eq!(matching_bracket(&r, 1, MAX_SCAN), Some(16));
+        assert_eq!(matching_bracket(&r, 16, MAX_SCAN), Some(1));
+        assert_eq!(matching_bracket(&r, 3, MAX_SCAN), Some(5));
+        assert_eq!(matching_bracket(&r, 8, MAX_SCAN), Some(15));
+        assert_eq!(matching_bracket(&r, 12, MAX_SCAN), Some(14));
+        assert_eq!(matching_bracket(&r, 0, MAX_SCAN), None);
+    }
+
+    #[test]
+    fn test_unmatched_and_too_far_give_none() {
+        assert_eq!(matching_bracket(&rope("(a(b)"), 0, MAX_SCAN), None);
+        assert_eq!(matching_bracket(&rope("a)"), 1, MAX_SCAN), None);
+        // Other kinds of bracket don't close it.
+        assert_eq!(matching_bracket(&rope("(]"), 0, MAX_SCAN), None);
+
+        let long = format!("({})", "x".repeat(100));
+        assert_eq!(matching_bracket(&rope(&long), 0, 50), None);
+        assert_eq!(matching_bracket(&rope(&long), 0, 101), Some(101));
+    }
+
+    #[test]
+    fn test_brackets_match_across_lines() {
+        let r = rope("fn main() {\n    if x {\n    }\n}\n");
+        assert_eq!(matching_bracket(&r, 10, MAX_SCAN), Some(29));
+        assert_eq!(matching_bracket(&r, 29, MAX_SCAN), Some(10));
+        assert_eq!(bracket_from(&r, 0), Some(7));
+        assert_eq!(bracket_from(&r, 11), None);
+    }
+
+    #[test]
+    fn test_percent_jumps_between_brackets() {
+        let mut buffer = buffer_with("call(a, [b,\n  c])");
+        buffer.handle_keypress(Key::Char('%'));
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (4, 1));
+        buffer.handle_keypress(Key::Char('%'));
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (4, 0));
+
+        buffer.handle_keypress(Key::Char('%'));
+        buffer.handle_keypress(Key::Ctrl('o'));
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (4, 0));
+    }
+
+    #[test]
+    fn test_percent_reports_missing_brackets() {
+        let mut buffer = buffer_with("(open\nplain");
+        buffer.handle_keypress(Key::Char('%'));
+        assert_eq!(buffer.message_text(), "No match");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+
+        buffer.handle_keypress(Key::ArrowDown);
+        buffer.handle_keypress(Key::Char('%'));
+        assert!(buffer.message_text().contains("No bracket"));
+        assert_eq!(buffer.cursor_y, 1);
+    }
+
+    #[test]
+    fn test_insert_mode_highlights_the_matching_bracket() {
+        let mut buffer = buffer_with("(a)");
+        buffer.handle_keypress(Key::End);
+        assert_eq!(buffer.highlighted_bracket(), None);
+
+        buffer.handle_keypress(Key::Char('i'));
+        assert_eq!(buffer.highlighted_bracket(), Some(0));
+        assert_eq!(buffer.compose_frame()[2].1, "1 | \x1b[7m(\x1b[0ma)");
+
+        buffer.handle_keypress(Key::Home);
+        assert_eq!(buffer.highlighted_bracket(), Some(2));
+        buffer.handle_keypress(Key::ArrowRight);
+        assert_eq!(buffer.highlighted_bracket(), Some(2));
+        buffer.handle_keypress(Key::ArrowRight);
+        assert_eq!(buffer.highlighted_bracket(), Some(0));
+    }
+}
@@ -0,0 +1,959 @@
+use rawdeo::buffer::{parse_substitution, Mode, TextBuffer};
+use rawdeo::input::{decode_bytes, decode_key, Key, KeyPress};
+use rawdeo::rope::{LineEnding, Rope, SplitStrategy};
+use rawdeo::status::Severity;
+use std::fs;
+
+mod common;
+
+use common::{buffer_with, press};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hundred_lines() -> TextBuffer {
+        let mut lines: Vec<String> = (0..99).map(|i| format!("line {}", i)).collect();
+        lines.push("end".to_string());
+        buffer_with(&lines.join("\n"))
+    }
+
+    #[test]
+    fn test_yank_and_paste_line_below() {
+        let mut buffer = buffer_with("one\ntwo");
+        press(&mut buffer, "yyp");
+
+        assert_eq!(buffer.register, "one\n");
+        assert_eq!(buffer.lines(), vec!["one", "one", "two"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 1));
+    }
+
+    #[test]
+    fn test_delete_line_and_paste_above() {
+        let mut buffer = buffer_with("one\ntwo\nthree");
+        buffer.cursor_y = 1;
+        press(&mut buffer, "dd");
+
+        assert_eq!(buffer.lines(), vec!["one", "three"]);
+        assert_eq!(buffer.cursor_y, 1);
+
+        press(&mut buffer, "P");
+        assert_eq!(buffer.lines(), vec!["one", "two", "three"]);
+        assert_eq!(buffer.cursor_y, 1);
+    }
+
+    #[test]
+    fn test_delete_only_line_leaves_empty_buffer() {
+        let mut buffer = buffer_with("only");
+        press(&mut buffer, "dd");
+
+        assert_eq!(buffer.lines(), vec![""]);
+        assert_eq!(buffer.register, "only\n");
+    }
+
+    #[test]
+    fn test_paste_inline_after_cursor() {
+        let mut buffer = buffer_with("Hello world!");
+        buffer.register = "big ".to_string();
+        buffer.cursor_x = 5;
+        press(&mut buffer, "p");
+
+        assert_eq!(buffer.lines(), vec!["Hello big world!"]);
+        assert_eq!(buffer.cursor_x, 10);
+    }
+
+    #[test]
+    fn test_paste_multiline_text_inline() {
+        let mut buffer = buffer_with("ab\nend");
+        buffer.register = "1\n22\n333".to_string();
+        press(&mut buffer, "p");
+
+        assert_eq!(buffer.lines(), vec!["a1", "22", "333b", "end"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 2));
+
+        press(&mut buffer, "P");
+        assert_eq!(buffer.lines(), vec!["a1", "22", "3331", "22", "333b", "end"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 4));
+    }
+
+    #[test]
+    fn test_yank_line_and_paste_above() {
+        let mut buffer = buffer_with("one\ntwo");
+        buffer.cursor_y = 1;
+        buffer.cursor_x = 2;
+        press(&mut buffer, "yyP");
+
+        assert_eq!(buffer.lines(), vec!["one", "two", "two"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 1));
+    }
+
+    #[test]
+    fn test_interrupted_operator_does_nothing() {
+        let mut buffer = buffer_with("one\ntwo");
+        press(&mut buffer, "dyd");
+
+        assert_eq!(buffer.lines(), vec!["one", "two"]);
+        assert!(buffer.register.is_empty());
+    }
+
+    #[test]
+    fn test_incremental_search_moves_cursor_per_keystroke() {
+        let mut buffer = buffer_with("one\nbeta\nalphabet");
+        press(&mut buffer, "/a");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 1));
+
+        press(&mut buffer, "l");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 2));
+
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 1));
+    }
+
+    #[test]
+    fn test_search_escape_restores_cursor_and_scroll() {
+        let lines: Vec<String> = (0..40).map(|i| format!("line {}", i)).collect();
+        let mut buffer = buffer_with(&lines.join("\n"));
+        press(&mut buffer, "/line 35");
+        assert_eq!(buffer.cursor_y, 35);
+        assert!(buffer.scroll_y > 0);
+
+        buffer.handle_keypress(Key::OptionSpace);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y, buffer.scroll_y), (0, 0, 0));
+        assert!(buffer.last_search.is_none());
+    }
+
+    #[test]
+    fn test_search_query_accumulates_keys() {
+        let mut buffer = buffer_with("say hello world\nhello");
+        press(&mut buffer, "/hello wx");
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!(buffer.search_input, "hello w");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (4, 0));
+
+        buffer.handle_keypress(Key::Enter);
+        assert_eq!(buffer.last_search.as_deref(), Some("hello w"));
+        assert!(matches!(buffer.mode, Mode::Normal));
+    }
+
+    #[test]
+    fn test_search_wraps_to_start_of_document() {
+        let mut buffer = buffer_with("target\nother\nlast");
+        buffer.cursor_y = 2;
+        press(&mut buffer, "/target");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+        assert_eq!(buffer.message_text(), "search wrapped");
+
+        press(&mut buffer, "\n/missing\n");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+        assert_eq!(buffer.message_text(), "Pattern not found: missing");
+    }
+
+    #[test]
+    fn test_backspace_on_empty_search_cancels() {
+        let mut buffer = buffer_with("one");
+        press(&mut buffer, "/");
+        buffer.handle_keypress(Key::Backspace);
+        assert!(matches!(buffer.mode, Mode::Normal));
+    }
+
+    #[test]
+    fn test_search_next_and_previous_wrap() {
+        let mut buffer = buffer_with("foo\nbar foo\nfoo");
+        press(&mut buffer, "/foo");
+        buffer.handle_keypress(Key::Enter);
+        assert_eq!(buffer.last_search.as_deref(), Some("foo"));
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (4, 1));
+
+        press(&mut buffer, "n");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 2));
+
+        press(&mut buffer, "n");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+        assert_eq!(buffer.message_text(), "search wrapped");
+
+        press(&mut buffer, "N");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 2));
+        assert_eq!(buffer.message_text(), "search wrapped");
+
+        press(&mut buffer, "N");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (4, 1));
+        assert!(buffer.message_text().is_empty());
+    }
+
+    #[test]
+    fn test_repeated_next_cycles_through_all_matches() {
+        let mut buffer = buffer_with("ab ab\nnone\nxab\nab");
+        press(&mut buffer, "/ab\n");
+
+        let mut visited = vec![(buffer.cursor_x, buffer.cursor_y)];
+        for _ in 0..4 {
+            press(&mut buffer, "n");
+            visited.push((buffer.cursor_x, buffer.cursor_y));
+        }
+        assert_eq!(visited, vec![(3, 0), (1, 2), (0, 3), (0, 0), (3, 0)]);
+
+        let mut visited = Vec::new();
+        for _ in 0..4 {
+            press(&mut buffer, "N");
+            visited.push((buffer.cursor_x, buffer.cursor_y));
+        }
+        assert_eq!(visited, vec![(0, 0), (0, 3), (1, 2), (3, 0)]);
+    }
+
+    #[test]
+    fn test_next_and_previous_remember_the_match() {
+        let mut buffer = buffer_with("ab ab\nxab");
+        press(&mut buffer, "/ab\n");
+        assert_eq!(buffer.search_match, Some(3));
+        press(&mut buffer, "n");
+        assert_eq!(buffer.search_match, Some(7));
+        press(&mut buffer, "NN");
+        assert_eq!(buffer.search_match, Some(0));
+
+        press(&mut buffer, "/zz\n");
+        assert_eq!(buffer.search_match, None);
+        assert_eq!(buffer.message_text(), "Pattern not found: zz");
+    }
+
+    #[test]
+    fn test_next_without_previous_search() {
+        let mut buffer = buffer_with("one");
+        press(&mut buffer, "n");
+        assert_eq!(buffer.message_text(), "No previous search pattern");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+    }
+
+    #[test]
+    fn test_word_forward_and_backward_on_one_line() {
+        let mut buffer = buffer_with("foo  bar.baz");
+
+        let mut columns = vec![buffer.cursor_x];
+        for _ in 0..4 {
+            press(&mut buffer, "w");
+            columns.push(buffer.cursor_x);
+        }
+        assert_eq!(columns, vec![0, 5, 8, 9, 11]);
+
+        let mut columns = Vec::new();
+        for _ in 0..4 {
+            press(&mut buffer, "b");
+            columns.push(buffer.cursor_x);
+        }
+        assert_eq!(columns, vec![9, 8, 5, 0]);
+    }
+
+    #[test]
+    fn test_word_motion_across_lines() {
+        let mut buffer = buffer_with("foo\n\n  bar baz");
+
+        press(&mut buffer, "w");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 1));
+        press(&mut buffer, "w");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 2));
+
+        press(&mut buffer, "b");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 1));
+        press(&mut buffer, "b");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+    }
+
+    #[test]
+    fn test_parse_substitution_with_escaped_slashes() {
+        let substitution = parse_substitution("%s/a\\/b/c\\/d/g").unwrap();
+
+        assert_eq!(substitution.pattern, "a/b");
+        assert_eq!(substitution.replacement, "c/d");
+        assert!(substitution.global);
+        assert!(substitution.whole_file);
+    }
+
+    #[test]
+    fn test_parse_substitution_rejects_invalid_commands() {
+        assert!(parse_substitution("s//bar/").is_none());
+        assert!(parse_substitution("s/foo/bar/x").is_none());
+        assert!(parse_substitution("s").is_none());
+        assert!(parse_substitution("s/foo").is_some());
+    }
+
+    #[test]
+    fn test_substitute_first_on_current_line() {
+        let mut buffer = buffer_with("foo foo\nfoo");
+        press(&mut buffer, ":s/foo/bar/\n");
+
+        assert_eq!(buffer.lines(), vec!["bar foo", "foo"]);
+        assert_eq!(buffer.message_text(), "1 substitution on 1 line");
+    }
+
+    #[test]
+    fn test_substitute_global_on_current_line() {
+        let mut buffer = buffer_with("foo foo\nfoo");
+        press(&mut buffer, ":s/foo/a b/g\n");
+
+        assert_eq!(buffer.lines(), vec!["a b a b", "foo"]);
+    }
+
+    #[test]
+    fn test_substitute_leaves_other_lines_alone() {
+        let mut buffer = buffer_with("foo\nfoo foo\nfoo");
+        buffer.handle_keypress(Key::ArrowDown);
+        press(&mut buffer, ":s/foo/x/g\n");
+
+        assert_eq!(buffer.lines(), vec!["foo", "x x", "foo"]);
+        assert_eq!(buffer.message_text(), "2 substitutions on 1 line");
+    }
+
+    #[test]
+    fn test_substitute_whole_file() {
+        let mut buffer = buffer_with("foo foo\nbar\nfoo");
+        press(&mut buffer, ":%s/foo/bar/g\n");
+
+        assert_eq!(buffer.lines(), vec!["bar bar", "bar", "bar"]);
+        assert_eq!(buffer.message_text(), "3 substitutions on 2 lines");
+    }
+
+    #[test]
+    fn test_substitute_whole_file_undoes_at_once() {
+        let mut buffer = buffer_with("foo\nfoo bar\nbaz\nfoo");
+        press(&mut buffer, ":%s/foo/x/g\n");
+        assert_eq!(buffer.lines(), vec!["x", "x bar", "baz", "x"]);
+
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["foo", "foo bar", "baz", "foo"]);
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_substitute_pattern_not_found() {
+        let mut buffer = buffer_with("foo");
+        press(&mut buffer, ":%s/baz/bar/g\n");
+
+        assert_eq!(buffer.lines(), vec!["foo"]);
+        assert_eq!(buffer.message_text(), "Pattern not found: baz");
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("rawdeo_buffer_{}_{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_write_to_current_file() {
+        let path = temp_path("write.txt");
+        fs::write(&path, "one\ntwo\n").unwrap();
+
+        let mut buffer = TextBuffer::new_with_path(12, &path).unwrap();
+        assert_eq!(buffer.lines(), vec!["one", "two"]);
+        assert!(!buffer.is_modified());
+
+        press(&mut buffer, "dd");
+        assert!(buffer.is_modified());
+        press(&mut buffer, ":w\n");
+
+        assert!(!buffer.is_modified());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two\n");
+        assert!(buffer.message_text().contains("written"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_to_given_path() {
+        let path = temp_path("write_as.txt");
+        let mut buffer = buffer_with("hell");
+        buffer.rope.insert(4, "o");
+
+        press(&mut buffer, &format!(":w {}\n", path));
+
+        assert_eq!(buffer.filename.as_deref(), Some(path.as_str()));
+        assert!(!buffer.is_modified());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_without_file_name() {
+        let mut buffer = buffer_with("hello");
+        press(&mut buffer, ":w\n");
+
+        assert_eq!(buffer.message_text(), "No file name");
+        assert!(buffer.filename.is_none());
+    }
+
+    #[test]
+    fn test_open_missing_file_starts_empty() {
+        let path = temp_path("missing.txt");
+        let buffer = TextBuffer::new_with_path(12, &path).unwrap();
+
+        assert_eq!(buffer.lines(), vec![""]);
+        assert_eq!(buffer.filename.as_deref(), Some(path.as_str()));
+    }
+
+    #[test]
+    fn test_quit_refuses_with_unsaved_changes() {
+        let mut buffer = buffer_with("one");
+        press(&mut buffer, "ix");
+        buffer.handle_keypress(Key::OptionSpace);
+        assert!(buffer.is_modified());
+
+        buffer.execute_command("q");
+        assert!(!buffer.should_quit);
+        assert_eq!(buffer.message_text(), "No write since last change (add ! to override)");
+
+        buffer.execute_command("q!");
+        assert!(buffer.should_quit);
+    }
+
+    #[test]
+    fn test_quit_when_clean() {
+        let mut buffer = buffer_with("one");
+        buffer.execute_command("q");
+        assert!(buffer.should_quit);
+    }
+
+    #[test]
+    fn test_write_quit() {
+        let path = temp_path("wq.txt");
+        let mut buffer = buffer_with("save");
+        buffer.rope.insert(4, "d");
+
+        buffer.execute_command("wq");
+        assert!(!buffer.should_quit);
+        assert_eq!(buffer.message_text(), "No file name");
+
+        buffer.execute_command(&format!("wq {}", path));
+        assert!(buffer.should_quit);
+        assert!(!buffer.is_modified());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "saved\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        let mut buffer = buffer_with("one");
+        press(&mut buffer, ":frobnicate\n");
+
+        assert_eq!(buffer.message_text(), "Not an editor command: frobnicate");
+        assert!(buffer.command_input.is_empty());
+    }
+
+    #[test]
+    fn test_edit_command_opens_file() {
+        let path = temp_path("edit.txt");
+        fs::write(&path, "alpha\nbeta\n").unwrap();
+        let mut buffer = buffer_with("old\ntext");
+        buffer.cursor_y = 1;
+        buffer.cursor_x = 2;
+
+        press(&mut buffer, &format!(":e {}\n", path));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(buffer.lines(), vec!["alpha", "beta"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+        assert_eq!(buffer.filename.as_deref(), Some(path.as_str()));
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_edit_command_keeps_unsaved_changes() {
+        let path = temp_path("edit_missing.txt");
+        let mut buffer = buffer_with("draf");
+        buffer.rope.insert(4, "t");
+
+        buffer.execute_command(&format!("e {}", path));
+        assert_eq!(buffer.lines(), vec!["draft"]);
+        assert_eq!(buffer.message_text(), "No write since last change (add ! to override)");
+
+        buffer.execute_command(&format!("e! {}", path));
+        assert_eq!(buffer.lines(), vec![""]);
+        assert_eq!(buffer.filename.as_deref(), Some(path.as_str()));
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_edit_command_reports_errors() {
+        let mut buffer = buffer_with("keep");
+        let dir = std::env::temp_dir().to_string_lossy().into_owned();
+        buffer.execute_command(&format!("e {}", dir));
+
+        assert_eq!(buffer.lines(), vec!["keep"]);
+        assert!(buffer.message_text().starts_with("Can't open"));
+    }
+
+    #[test]
+    fn test_open_crlf_file_and_convert() {
+        let path = temp_path("dos.txt");
+        fs::write(&path, "one\r\ntwo\r\n").unwrap();
+        let mut buffer = TextBuffer::new_with_path(12, &path).unwrap();
+
+        assert_eq!(buffer.lines(), vec!["one", "two"]);
+        assert_eq!(buffer.rope.line_ending(), LineEnding::CrLf);
+
+        press(&mut buffer, "i\n");
+        buffer.handle_keypress(Key::OptionSpace);
+        press(&mut buffer, ":w\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "\r\none\r\ntwo\r\n");
+
+        press(&mut buffer, ":set ff=unix\n");
+        assert!(buffer.is_modified());
+        press(&mut buffer, ":w\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "\none\ntwo\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mixed_file_is_saved_with_one_ending() {
+        let path = temp_path("mixed.txt");
+        fs::write(&path, "one\ntwo\r\nthree\n").unwrap();
+        let mut buffer = TextBuffer::new_with_path(12, &path).unwrap();
+
+        assert_eq!(buffer.lines(), vec!["one", "two", "three"]);
+        press(&mut buffer, ":w\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\nthree\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_set_invalid_file_format() {
+        let mut buffer = buffer_with("one");
+        press(&mut buffer, ":set ff=mac\n");
+        assert_eq!(buffer.message_text(), "Invalid argument: ff=mac");
+        assert_eq!(buffer.rope.line_ending(), LineEnding::Lf);
+
+        press(&mut buffer, ":set ff\n");
+        assert_eq!(buffer.message_text(), "fileformat=unix");
+    }
+
+    #[test]
+    fn test_insert_mode_edits_the_rope() {
+        let mut buffer = buffer_with("ab");
+        buffer.cursor_x = 1;
+        press(&mut buffer, "i\n");
+        buffer.handle_keypress(Key::Tab);
+        press(&mut buffer, "x");
+
+        assert_eq!(buffer.rope.to_string(), "a\n    xb");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (5, 1));
+
+        buffer.cursor_x = 0;
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!(buffer.rope.to_string(), "a    xb");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (1, 0));
+    }
+
+    #[test]
+    fn test_quick_double_escape_leaves_insert_mode() {
+        let mut buffer = buffer_with("ab");
+        press(&mut buffer, "i");
+        let (mut bytes, mut unread) = (b"\x1b\x1bx".iter().copied(), None);
+        while let key @ KeyPress { key: Key::Escape | Key::Char(_), .. } = decode_key(&mut bytes, &mut unread) {
+            buffer.handle_keypress(key);
+        }
+
+        // The ESC before the x made it Alt+x, which has no binding of its own.
+        assert!(matches!(buffer.mode, Mode::Normal));
+        assert_eq!(buffer.lines(), vec!["b"]);
+    }
+
+    #[test]
+    fn test_arrows_cross_line_ends() {
+        let mut buffer = buffer_with("ab\nc");
+        buffer.cursor_x = 2;
+
+        buffer.handle_keypress(Key::ArrowRight);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 1));
+        buffer.handle_keypress(Key::ArrowRight);
+        buffer.handle_keypress(Key::ArrowRight);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (1, 1));
+
+        buffer.handle_keypress(Key::ArrowLeft);
+        buffer.handle_keypress(Key::ArrowLeft);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 0));
+    }
+
+    #[test]
+    fn test_delete_last_line_and_paste_into_rope() {
+        let mut buffer = buffer_with("one\ntwo");
+        buffer.cursor_y = 1;
+        press(&mut buffer, "dd");
+        assert_eq!(buffer.rope.to_string(), "one");
+
+        press(&mut buffer, "pP");
+        assert_eq!(buffer.rope.to_string(), "one\ntwo\ntwo");
+        assert_eq!(buffer.rope.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_insert_multibyte_chars() {
+        let mut buffer = buffer_with("ab");
+        buffer.cursor_x = 1;
+        press(&mut buffer, "ié€");
+        buffer.handle_keypress(Key::Char('🦀'));
+
+        assert_eq!(buffer.rope.to_string(), "aé€🦀b");
+        assert_eq!(buffer.cursor_x, 10);
+    }
+
+    #[test]
+    fn test_page_down_near_bottom() {
+        let mut buffer = hundred_lines();
+        (buffer.cursor_x, buffer.cursor_y, buffer.scroll_y) = (7, 85, 80);
+
+        buffer.handle_keypress(Key::PageDown);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y, buffer.scroll_y), (7, 95, 90));
+
+        buffer.handle_keypress(Key::PageDown);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y, buffer.scroll_y), (3, 99, 90));
+
+        buffer.handle_keypress(Key::PageUp);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (89, 80));
+    }
+
+    #[test]
+    fn test_page_up_stops_at_top() {
+        let mut buffer = hundred_lines();
+        (buffer.cursor_y, buffer.scroll_y) = (5, 2);
+
+        buffer.handle_keypress(Key::PageUp);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (0, 0));
+
+        let mut empty = buffer_with("");
+        empty.handle_keypress(Key::PageDown);
+        assert_eq!((empty.cursor_x, empty.cursor_y, empty.scroll_y), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_compose_frame_rows() {
+        let mut buffer = buffer_with("one\ntwo");
+        buffer.notify(Severity::Info, "hi");
+        let frame = buffer.compose_frame();
+
+        assert_eq!(frame.len(), 12);
+        assert_eq!(frame[0].0, 11);
+        assert!(frame[0].1.starts_with("-- NORMAL --  [No Name]  "));
+        assert!(frame[0].1.ends_with("  [unix]  1:1  50%"));
+        assert_eq!(frame[1], (12, "hi".to_string()));
+        assert_eq!(frame[2], (1, "1 | one".to_string()));
+        assert_eq!(frame[3], (2, "2 | two".to_string()));
+        assert_eq!(frame[4], (3, String::new()));
+
+        buffer.handle_keypress(Key::Char('j'));
+        assert_eq!(buffer.compose_frame()[1], (12, String::new()));
+    }
+
+    #[test]
+    fn test_render_writes_only_changed_rows() {
+        let mut buffer = buffer_with("one\ntwo");
+        let mut out = Vec::new();
+        buffer.render_to(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("2 | two"));
+
+        let mut out = Vec::new();
+        buffer.render_to(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b[1;5H");
+
+        press(&mut buffer, "ix");
+        let mut out = Vec::new();
+        buffer.render_to(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\x1b[1;1H\x1b[K1 | xone"));
+        assert!(!out.contains("two"));
+    }
+
+    #[test]
+    fn test_buffer_edits_match_rope_operations() {
+        let mut buffer = buffer_with("hello\nworld");
+        buffer.cursor_x = 5;
+        press(&mut buffer, "i!\nnew");
+        buffer.cursor_y = 2;
+        buffer.cursor_x = 0;
+        buffer.handle_keypress(Key::Backspace);
+        buffer.handle_keypress(Key::Backspace);
+
+        let mut rope = Rope::from_string("hello\nworld", SplitStrategy::LineBased);
+        rope.insert(5, "!");
+        rope.insert(6, "\n");
+        rope.insert(7, "new");
+        rope.remove_clamped(10, 11);
+        rope.remove_clamped(9, 10);
+
+        assert_eq!(buffer.rope.to_string(), rope.to_string());
+        assert_eq!(buffer.rope.to_string(), "hello!\nneworld");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 1));
+    }
+
+    #[test]
+    fn test_buffer_edits_are_undoable_on_the_rope() {
+        let mut buffer = buffer_with("one\ntwo");
+        press(&mut buffer, "dd");
+        buffer.handle_keypress(Key::OptionSpace);
+        press(&mut buffer, "x");
+        assert_eq!(buffer.rope.to_string(), "xtwo");
+
+        while buffer.rope.undo().is_some() {}
+        assert_eq!(buffer.rope.to_string(), "one\ntwo");
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_undo_and_redo_keys() {
+        let mut buffer = buffer_with("one\ntwo");
+        buffer.cursor_y = 1;
+        press(&mut buffer, "dd");
+        assert_eq!(buffer.lines(), vec!["one"]);
+
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["one", "two"]);
+        assert!(!buffer.is_modified());
+
+        buffer.handle_keypress(Key::Ctrl('r'));
+        assert_eq!(buffer.lines(), vec!["one"]);
+        assert_eq!(buffer.cursor_y, 0);
+
+        buffer.handle_keypress(Key::Ctrl('z'));
+        assert_eq!(buffer.lines(), vec!["one", "two"]);
+    }
+
+    #[test]
+    fn test_undo_typing_burst_and_empty_history() {
+        let mut buffer = buffer_with("x");
+        press(&mut buffer, "u");
+        assert_eq!(buffer.message_text(), "Already at oldest change");
+        buffer.handle_keypress(Key::Ctrl('y'));
+        assert_eq!(buffer.message_text(), "Already at newest change");
+
+        press(&mut buffer, "iabc");
+        buffer.handle_keypress(Key::OptionSpace);
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["x"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+    }
+
+    #[test]
+    fn test_home_and_end() {
+        let mut buffer = buffer_with("first\na\tb");
+        buffer.cursor_y = 1;
+        buffer.handle_keypress(Key::End);
+        assert_eq!(buffer.cursor_x, 3);
+        buffer.handle_keypress(Key::Home);
+        assert_eq!(buffer.cursor_x, 0);
+
+        let mut empty = buffer_with("");
+        empty.handle_keypress(Key::End);
+        assert_eq!((empty.cursor_x, empty.cursor_y), (0, 0));
+        empty.handle_keypress(Key::Home);
+        assert_eq!((empty.cursor_x, empty.cursor_y), (0, 0));
+    }
+
+    #[test]
+    fn test_delete_key() {
+        let mut buffer = buffer_with("abc\nde");
+        buffer.cursor_x = 1;
+        buffer.handle_keypress(Key::Delete);
+        assert_eq!(buffer.lines(), vec!["ac", "de"]);
+        assert_eq!(buffer.cursor_x, 1);
+
+        buffer.handle_keypress(Key::End);
+        buffer.handle_keypress(Key::Delete);
+        assert_eq!(buffer.lines(), vec!["acde"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 0));
+        assert!(buffer.is_modified());
+
+        buffer.handle_keypress(Key::End);
+        buffer.handle_keypress(Key::Delete);
+        assert_eq!(buffer.lines(), vec!["acde"]);
+
+        let mut empty = buffer_with("");
+        empty.handle_keypress(Key::Delete);
+        assert_eq!(empty.lines(), vec![""]);
+        assert!(!empty.is_modified());
+    }
+
+    #[test]
+    fn test_delete_key_in_insert_mode() {
+        let mut buffer = buffer_with("ab\ncd");
+        buffer.handle_keypress(Key::Char('i'));
+        buffer.handle_keypress(Key::End);
+        buffer.handle_keypress(Key::Delete);
+        assert_eq!(buffer.lines(), vec!["abcd"]);
+        assert!(matches!(buffer.mode, Mode::Insert));
+
+        buffer.handle_keypress(Key::End);
+        buffer.handle_keypress(Key::Delete);
+        assert_eq!(buffer.lines(), vec!["abcd"]);
+        assert_eq!(buffer.cursor_x, 4);
+
+        buffer.handle_keypress(Key::OptionSpace);
+        buffer.handle_keypress(Key::Char('u'));
+        assert_eq!(buffer.lines(), vec!["ab", "cd"]);
+    }
+
+    #[test]
+    fn test_delete_key_removes_whole_grapheme() {
+        let mut buffer = buffer_with("e\u{301}x");
+        buffer.handle_keypress(Key::Char('i'));
+        buffer.handle_keypress(Key::Delete);
+        assert_eq!(buffer.lines(), vec!["x"]);
+    }
+
+    fn ten_lines() -> TextBuffer {
+        let lines: Vec<String> = (1..=10).map(|i| format!("line {}", i)).collect();
+        buffer_with(&lines.join("\n"))
+    }
+
+    #[test]
+    fn test_goto_line_clamps() {
+        let mut buffer = ten_lines();
+        buffer.cursor_x = 3;
+        press(&mut buffer, ":7\n");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 6));
+        assert!(matches!(buffer.mode, Mode::Normal));
+
+        press(&mut buffer, ":999999\n");
+        assert_eq!(buffer.cursor_y, 9);
+        press(&mut buffer, ":0\n");
+        assert_eq!(buffer.cursor_y, 0);
+        assert!(buffer.compose_frame()[0].1.ends_with("  1:1  10%"));
+    }
+
+    #[test]
+    fn test_goto_last_line_and_out_of_range() {
+        let mut buffer = ten_lines();
+        buffer.execute_command("$");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 9));
+        assert_eq!(buffer.message_text(), "");
+
+        buffer.execute_command("0");
+        buffer.execute_command("10");
+        assert_eq!((buffer.cursor_y, buffer.message_text()), (9, ""));
+
+        buffer.execute_command("0");
+        buffer.execute_command("11");
+        assert_eq!(buffer.cursor_y, 9);
+        assert_eq!(buffer.message_text(), "Line 11 out of range, moved to last line 10");
+
+        buffer.execute_command("3");
+        assert_eq!((buffer.cursor_y, buffer.message_text()), (2, ""));
+        buffer.execute_command("99999999999999999999999");
+        assert_eq!(buffer.cursor_y, 9);
+        buffer.execute_command("4x");
+        assert_eq!(buffer.message_text(), "Not an editor command: 4x");
+    }
+
+    #[test]
+    fn test_goto_line_centers_view() {
+        let mut buffer = hundred_lines();
+        buffer.execute_command("50");
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (49, 44));
+
+        buffer.execute_command("99");
+        assert_eq!(buffer.scroll_y, 90);
+    }
+
+    #[test]
+    fn test_jumplist_goes_back_in_order() {
+        let mut buffer = ten_lines();
+        buffer.cursor_x = 2;
+        press(&mut buffer, ":5\n/line 8\n");
+        press(&mut buffer, ":3\n");
+        buffer.handle_keypress(Key::ArrowDown);
+
+        buffer.handle_keypress(Key::Ctrl('o'));
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 7));
+        buffer.handle_keypress(Key::Ctrl('o'));
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 4));
+        buffer.handle_keypress(Key::Ctrl('o'));
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 0));
+        buffer.handle_keypress(Key::Ctrl('o'));
+        assert_eq!(buffer.message_text(), "Jump list is empty");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 0));
+    }
+
+    #[test]
+    fn test_jumplist_keeps_recent_positions() {
+        let mut buffer = hundred_lines();
+        for line in 1..=30 {
+            buffer.execute_command(&line.to_string());
+        }
+
+        let mut visited = Vec::new();
+        for _ in 0..21 {
+            buffer.handle_keypress(Key::Ctrl('o'));
+            visited.push(buffer.cursor_y);
+        }
+        assert_eq!(visited[0], 28);
+        assert_eq!(visited[19], 9);
+        assert_eq!(visited[20], 9);
+    }
+
+    #[test]
+    fn test_line_removal_ranges() {
+        assert_eq!(buffer_with("only").line_removal_range(0), (0, 4));
+        assert_eq!(buffer_with("").line_removal_range(0), (0, 0));
+
+        let buffer = buffer_with("one\ntwo\nthree");
+        assert_eq!(buffer.line_removal_range(0), (0, 4));
+        assert_eq!(buffer.line_removal_range(1), (4, 8));
+        assert_eq!(buffer.line_removal_range(2), (7, 13));
+    }
+
+    #[test]
+    fn test_dd_keeps_cursor_on_following_line() {
+        let mut buffer = buffer_with("one\ntwo\nthree");
+        buffer.cursor_y = 1;
+        buffer.cursor_x = 2;
+        press(&mut buffer, "dd");
+        assert_eq!(buffer.lines(), vec!["one", "three"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 1));
+
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn test_x_deletes_char_under_cursor() {
+        let mut buffer = buffer_with("ab\nc");
+        press(&mut buffer, "x");
+        assert_eq!(buffer.lines(), vec!["b", "c"]);
+        assert_eq!(buffer.register, "a");
+
+        buffer.cursor_x = 1;
+        press(&mut buffer, "x");
+        assert_eq!(buffer.lines(), vec!["b", "c"]);
+
+        press(&mut buffer, "uu");
+        assert_eq!(buffer.lines(), vec!["ab", "c"]);
+    }
+
+    #[test]
+    fn test_ctrl_c_cancels_instead_of_quitting() {
+        let mut buffer = buffer_with("one");
+        buffer.handle_keypress(Key::Ctrl('c'));
+        assert_eq!(buffer.message_text(), "Type :q to quit");
+        assert!(!buffer.should_quit);
+
+        press(&mut buffer, "ix");
+        buffer.handle_keypress(Key::Ctrl('c'));
+        assert!(matches!(buffer.mode, Mode::Normal));
+
+        press(&mut buffer, ":q");
+        buffer.handle_keypress(Key::Ctrl('c'));
+        assert!(matches!(buffer.mode, Mode::Normal));
+        assert!(!buffer.should_quit);
+        assert_eq!(buffer.lines(), vec!["xone"]);
+    }
+
+    #[test]
+    fn test_decoded_key_presses_drive_the_buffer() {
+        let mut buffer = buffer_with("one");
+        buffer.handle_keypress(decode_bytes(b"x"));
+        assert_eq!(buffer.lines(), vec!["ne"]);
+
+        // Alt+x isn't bound, so it's an x.
+        buffer.handle_keypress(decode_bytes(b"\x1bx"));
+        assert_eq!(buffer.lines(), vec!["e"]);
+
+        buffer.handle_keypress(decode_bytes(b"\x03"));
+        assert_eq!(buffer.message_text(), "Type :q to quit");
+    }
+}
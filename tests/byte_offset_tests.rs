@@ -0,0 +1,76 @@
+use rawdeo::rope::{Rope, SplitStrategy};
+
+fn ropes(text: &str) -> Vec<Rope> {
+    vec![
+        Rope::from_string(text, SplitStrategy::LineBased),
+        Rope::from_string(text, SplitStrategy::FixedSize(3)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_offsets_match() {
+        for rope in ropes("hello\nworld\n") {
+            assert_eq!(rope.byte_size(), 12);
+            for i in 0..=12 {
+                assert_eq!(rope.char_to_byte(i), i);
+                assert_eq!(rope.byte_to_char(i), i);
+            }
+            assert_eq!(rope.byte_range(3, 8), "lo\nwo");
+        }
+    }
+
+    #[test]
+    fn test_mixed_width_offsets() {
+        let text = "aé\n日本語\n🦀x";
+        for rope in ropes(text) {
+            rope.validate().unwrap();
+            assert_eq!(rope.byte_size(), text.len());
+            for (char_index, (byte, _)) in text.char_indices().enumerate() {
+                assert_eq!(rope.char_to_byte(char_index), byte);
+                assert_eq!(rope.byte_to_char(byte), char_index);
+            }
+            assert_eq!(rope.byte_range(4, 14), "日本語\n");
+        }
+    }
+
+    #[test]
+    fn test_offsets_inside_a_char_round_down() {
+        for rope in ropes("a日b") {
+            assert_eq!(rope.byte_to_char(2), 1);
+            assert_eq!(rope.byte_to_char(3), 1);
+            assert_eq!(rope.byte_to_char(4), 2);
+            assert_eq!(rope.byte_range(2, 5), "日b");
+        }
+    }
+
+    #[test]
+    fn test_offsets_at_both_ends() {
+        for rope in ropes("é🦀") {
+            assert_eq!(rope.char_to_byte(0), 0);
+            assert_eq!(rope.byte_to_char(0), 0);
+            assert_eq!(rope.char_to_byte(2), 6);
+            assert_eq!(rope.byte_to_char(6), 2);
+            assert_eq!(rope.char_to_byte(99), 6);
+            assert_eq!(rope.byte_to_char(99), 2);
+            assert_eq!(rope.byte_range(0, 99), "é🦀");
+        }
+
+        let empty = Rope::new(SplitStrategy::LineBased);
+        assert_eq!((empty.byte_size(), empty.char_to_byte(0), empty.byte_to_char(0)), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_byte_counts_follow_edits() {
+        let mut rope = Rope::from_string("abc\ndef\n", SplitStrategy::FixedSize(2));
+        rope.insert(2, "日本");
+        rope.remove_clamped(0, 1);
+        rope.replace_range(5, 7, "🦀");
+        rope.validate().unwrap();
+        assert_eq!(rope.byte_size(), rope.to_string().len());
+        assert_eq!(rope.char_to_byte(5), "b日本c\n".len());
+    }
+}
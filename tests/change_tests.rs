@@ -0,0 +1,92 @@
+use rawdeo::rope::{Change, Rope, SplitStrategy};
+
+// Small deterministic generator so failures are reproducible.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % bound.max(1)
+    }
+}
+
+// Applies `change` to `shadow`, taking the inserted chars from the rope's
+// current text.
+fn replay(shadow: &mut Vec<char>, change: &Change, rope: &Rope) {
+    let inserted = rope.text_range(change.range.start, change.range.start + change.inserted_len);
+    shadow.splice(change.range.clone(), inserted.chars());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_are_off_by_default() {
+        let mut rope = Rope::from_string("text", SplitStrategy::LineBased);
+        rope.insert(0, "more ");
+        assert!(rope.take_changes().is_empty());
+    }
+
+    #[test]
+    fn test_one_change_per_edit() {
+        let mut rope = Rope::from_string("one\ntwo", SplitStrategy::LineBased);
+        rope.track_changes();
+
+        rope.insert(3, "\nmid");
+        rope.remove_clamped(0, 4);
+        rope.replace_range(0, 3, "x\ny\n");
+        rope.undo();
+
+        assert_eq!(
+            rope.take_changes(),
+            vec![
+                Change { range: 3..3, inserted_len: 4, deleted_text_len: 0, line_delta: 1 },
+                Change { range: 0..4, inserted_len: 0, deleted_text_len: 4, line_delta: -1 },
+                Change { range: 0..3, inserted_len: 4, deleted_text_len: 3, line_delta: 2 },
+                Change { range: 0..4, inserted_len: 3, deleted_text_len: 4, line_delta: -2 },
+            ]
+        );
+        assert!(rope.take_changes().is_empty());
+    }
+
+    #[test]
+    fn test_replayed_changes_match_rope() {
+        let pieces = ["a", "\n", "bc\n", "héllo ", "\n\nd"];
+        let mut rng = Lcg(7);
+        let mut rope = Rope::from_string("start\ntext\n", SplitStrategy::FixedSize(6));
+        let mut shadow: Vec<char> = rope.to_string().chars().collect();
+        let mut lines = rope.lines() as isize;
+        rope.track_changes();
+
+        for _ in 0..400 {
+            let len = rope.len();
+            match rng.next(5) {
+                0 if len > 0 => {
+                    let start = rng.next(len);
+                    rope.remove_clamped(start, (start + 1 + rng.next(5)).min(len));
+                }
+                1 if rope.can_undo() => {
+                    rope.undo();
+                }
+                2 if len > 0 => {
+                    let start = rng.next(len);
+                    rope.replace_range(start, (start + rng.next(3)).min(len), pieces[rng.next(pieces.len())]);
+                }
+                3 if rope.can_redo() => {
+                    rope.redo();
+                }
+                _ => rope.insert(rng.next(len + 1), pieces[rng.next(pieces.len())]),
+            }
+
+            let changes = rope.take_changes();
+            assert!(changes.len() <= 1);
+            for change in &changes {
+                replay(&mut shadow, change, &rope);
+                lines += change.line_delta;
+            }
+            assert_eq!(shadow.iter().collect::<String>(), rope.to_string());
+            assert_eq!(lines, rope.lines() as isize);
+        }
+    }
+}
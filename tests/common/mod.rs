@@ -0,0 +1,47 @@
+// Helpers shared by the buffer tests. Each test file is its own crate and uses
+// only some of them.
+#![allow(dead_code)]
+
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+use std::path::PathBuf;
+
+// A buffer holding `text` in a 12-row terminal, with the cursor at the top.
+pub fn buffer_with(text: &str) -> TextBuffer {
+    let mut buffer = TextBuffer::new(12);
+    buffer.set_text(text);
+    buffer
+}
+
+// "line 1" through "line 100", enough to scroll a 12-row terminal.
+pub fn hundred_lines() -> TextBuffer {
+    let lines: Vec<String> = (1..=100).map(|i| format!("line {}", i)).collect();
+    buffer_with(&lines.join("\n"))
+}
+
+// Writes `contents` to a file in the temp directory, named after the test
+// process so parallel runs don't meet.
+pub fn temp_file(name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rawdeo_{}_{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+// Types `keys` one char at a time. ' ' is Space, '\n' is Enter and '~' stands
+// for Option+Space to leave Insert mode.
+pub fn press(buffer: &mut TextBuffer, keys: &str) {
+    for c in keys.chars() {
+        buffer.handle_keypress(match c {
+            ' ' => Key::Space,
+            '\n' => Key::Enter,
+            '~' => Key::OptionSpace,
+            c => Key::Char(c),
+        });
+    }
+}
+
+pub fn press_keys(buffer: &mut TextBuffer, keys: &[Key]) {
+    for &key in keys {
+        buffer.handle_keypress(key);
+    }
+}
@@ -0,0 +1,107 @@
+use rawdeo::buffer::{Motion, TextBuffer};
+use rawdeo::input::Key;
+
+mod common;
+
+use common::{buffer_with, press};
+
+fn five_lines() -> TextBuffer {
+    buffer_with("one\ntwo\nthree\nfour\nfive")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_motion_ranges() {
+        let mut buffer = five_lines();
+        buffer.cursor_y = 1;
+        assert_eq!(buffer.motion_range(Motion::Lines, 2), (4, 14));
+        assert_eq!(buffer.motion_range(Motion::Lines, 99), (3, 23));
+        assert_eq!(buffer.motion_range(Motion::Chars, 2), (4, 6));
+        assert_eq!(buffer.motion_range(Motion::Chars, 10), (4, 7));
+
+        let mut buffer = buffer_with("a bb ccc\ndd");
+        buffer.cursor_x = 2;
+        assert_eq!(buffer.motion_range(Motion::WordForward, 1), (2, 5));
+        assert_eq!(buffer.motion_range(Motion::WordForward, 2), (2, 8));
+        assert_eq!(buffer.motion_range(Motion::WordForward, 3), (2, 11));
+        assert_eq!(buffer.motion_range(Motion::WordBackward, 5), (0, 2));
+    }
+
+    #[test]
+    fn test_count_moves_down() {
+        let lines: Vec<String> = (1..=30).map(|i| format!("line {}", i)).collect();
+        let mut buffer = buffer_with(&lines.join("\n"));
+        press(&mut buffer, "12");
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!(buffer.cursor_y, 12);
+
+        press(&mut buffer, "999");
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!(buffer.cursor_y, 29);
+
+        buffer.handle_keypress(Key::ArrowUp);
+        assert_eq!(buffer.cursor_y, 28);
+    }
+
+    #[test]
+    fn test_count_dd_is_one_undo_entry() {
+        let mut buffer = five_lines();
+        buffer.cursor_y = 1;
+        press(&mut buffer, "3dd");
+        assert_eq!(buffer.lines(), vec!["one", "five"]);
+        assert_eq!(buffer.cursor_y, 1);
+
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["one", "two", "three", "four", "five"]);
+    }
+
+    #[test]
+    fn test_count_past_the_end_clamps() {
+        let mut buffer = five_lines();
+        press(&mut buffer, "999dd");
+        assert_eq!(buffer.lines(), vec![""]);
+
+        let mut buffer = five_lines();
+        buffer.cursor_y = 3;
+        press(&mut buffer, "999dd");
+        assert_eq!(buffer.lines(), vec!["one", "two", "three"]);
+        assert_eq!(buffer.cursor_y, 2);
+    }
+
+    #[test]
+    fn test_count_x_deletes_chars_on_the_line() {
+        let mut buffer = buffer_with("abcdefg\nnext");
+        buffer.cursor_x = 1;
+        press(&mut buffer, "5x");
+        assert_eq!(buffer.lines(), vec!["ag", "next"]);
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["abcdefg", "next"]);
+
+        press(&mut buffer, "20x");
+        assert_eq!(buffer.lines(), vec!["a", "next"]);
+    }
+
+    #[test]
+    fn test_count_yy_and_dw() {
+        let mut buffer = five_lines();
+        press(&mut buffer, "2yyp");
+        assert_eq!(buffer.line_count(), 7);
+        assert_eq!(buffer.lines()[1..3], ["one", "two"]);
+
+        let mut buffer = buffer_with("a bb ccc dd");
+        press(&mut buffer, "2dw");
+        assert_eq!(buffer.lines(), vec!["ccc dd"]);
+    }
+
+    #[test]
+    fn test_escape_cancels_count() {
+        let mut buffer = five_lines();
+        press(&mut buffer, "3");
+        buffer.handle_keypress(Key::Escape);
+        press(&mut buffer, "dd");
+        assert_eq!(buffer.lines(), vec!["two", "three", "four", "five"]);
+    }
+}
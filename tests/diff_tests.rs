@@ -0,0 +1,71 @@
+use rawdeo::rope::{Edit, Rope, SplitStrategy};
+
+fn rope(text: &str) -> Rope {
+    Rope::from_string(text, SplitStrategy::LineBased)
+}
+
+fn edit(start: usize, end: usize, text: &str) -> Edit {
+    Edit { start, end, replacement: text.to_string() }
+}
+
+// Applies `from.diff(to)` and checks it gives `to`'s text in one undo step.
+fn assert_diff_applies(from: &str, to: &str) -> Vec<Edit> {
+    let mut rope = rope(from);
+    let edits = rope.diff(&self::rope(to));
+    let tuples: Vec<(usize, usize, String)> = edits.iter().cloned().map(Into::into).collect();
+    rope.apply_edits(&tuples).unwrap();
+    assert_eq!(rope.to_string(), to);
+    rope.validate().unwrap();
+    if !edits.is_empty() {
+        rope.undo();
+        assert_eq!(rope.to_string(), from);
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inserted_line() {
+        let edits = assert_diff_applies("one\ntwo\nthree\n", "one\ntwo\nnew\nthree\n");
+        assert_eq!(edits, vec![edit(8, 8, "new\n")]);
+    }
+
+    #[test]
+    fn test_deleted_line() {
+        let edits = assert_diff_applies("one\ntwo\nthree\n", "one\nthree\n");
+        assert_eq!(edits, vec![edit(4, 8, "")]);
+    }
+
+    #[test]
+    fn test_modified_line() {
+        let edits = assert_diff_applies("one\ntwo\nthree\n", "one\nTWO\nthree\n");
+        assert_eq!(edits, vec![edit(4, 8, "TWO\n")]);
+    }
+
+    #[test]
+    fn test_unchanged_lines_between_changes_are_skipped() {
+        let edits = assert_diff_applies("a\nb\nc\nd\ne\n", "A\nb\nc\nd\ne\nf\n");
+        assert_eq!(edits, vec![edit(0, 2, "A\n"), edit(10, 10, "f\n")]);
+
+        let edits = assert_diff_applies("x\na\ny\nb\nz", "a\nq\nb\nz");
+        assert_eq!(edits, vec![edit(0, 2, ""), edit(4, 6, "q\n")]);
+    }
+
+    #[test]
+    fn test_equal_and_empty_texts() {
+        assert!(assert_diff_applies("same\ntext", "same\ntext").is_empty());
+        assert_eq!(assert_diff_applies("", "new"), vec![edit(0, 0, "new")]);
+        assert_eq!(assert_diff_applies("old\n", ""), vec![edit(0, 4, "")]);
+        // A last line that gains its newline is a changed line.
+        assert_eq!(assert_diff_applies("a\nb", "a\nb\nc"), vec![edit(2, 3, "b\nc")]);
+    }
+
+    #[test]
+    fn test_indices_count_chars() {
+        let edits = assert_diff_applies("ünï\ncödé\n", "ünï\nnew\ncödé\n");
+        assert_eq!(edits, vec![edit(4, 4, "new\n")]);
+    }
+}
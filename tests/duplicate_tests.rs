@@ -0,0 +1,64 @@
+use rawdeo::buffer::Mode;
+use rawdeo::input::Key;
+
+mod common;
+
+use common::{buffer_with, press_keys};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ctrl_d_duplicates_the_line_below() {
+        let mut buffer = buffer_with("one\ntwo\nthree");
+        press_keys(&mut buffer, &[Key::ArrowDown, Key::ArrowRight, Key::Ctrl('d')]);
+        assert_eq!(buffer.lines(), vec!["one", "two", "two", "three"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (1, 2));
+
+        press_keys(&mut buffer, &[Key::Char('u')]);
+        assert_eq!(buffer.lines(), vec!["one", "two", "three"]);
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_duplicating_the_last_line_adds_a_separator() {
+        let mut buffer = buffer_with("one\ntwo");
+        press_keys(&mut buffer, &[Key::ArrowDown, Key::Ctrl('d')]);
+        assert_eq!(buffer.rope.to_string(), "one\ntwo\ntwo");
+        assert_eq!(buffer.cursor_y, 2);
+    }
+
+    #[test]
+    fn test_duplicating_an_empty_line() {
+        let mut buffer = buffer_with("a\n\nb");
+        press_keys(&mut buffer, &[Key::ArrowDown, Key::Ctrl('d')]);
+        assert_eq!(buffer.lines(), vec!["a", "", "", "b"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 2));
+
+        let mut buffer = buffer_with("");
+        press_keys(&mut buffer, &[Key::Ctrl('d')]);
+        assert_eq!(buffer.lines(), vec!["", ""]);
+    }
+
+    #[test]
+    fn test_a_count_duplicates_that_many_lines() {
+        let mut buffer = buffer_with("a\nb\nc");
+        press_keys(&mut buffer, &[Key::Char('2'), Key::Ctrl('d')]);
+        assert_eq!(buffer.lines(), vec!["a", "b", "a", "b", "c"]);
+        assert_eq!(buffer.cursor_y, 2);
+    }
+
+    #[test]
+    fn test_ctrl_d_duplicates_the_selected_lines() {
+        let mut buffer = buffer_with("first\nsecond\nthird\nlast");
+        press_keys(&mut buffer, &[Key::ArrowDown, Key::ArrowDown, Key::Char('v'), Key::ArrowUp, Key::ArrowRight]);
+        press_keys(&mut buffer, &[Key::Ctrl('d')]);
+        assert_eq!(buffer.lines(), vec!["first", "second", "third", "second", "third", "last"]);
+        assert!(matches!(buffer.mode, Mode::Normal));
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (1, 3));
+
+        press_keys(&mut buffer, &[Key::Char('u')]);
+        assert_eq!(buffer.lines(), vec!["first", "second", "third", "last"]);
+    }
+}
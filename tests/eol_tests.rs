@@ -0,0 +1,60 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+
+mod common;
+
+use common::{buffer_with, press_keys, temp_file};
+
+fn cursor(buffer: &TextBuffer) -> (usize, usize) {
+    (buffer.cursor_x, buffer.cursor_y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailing_newline_gives_a_last_empty_line() {
+        let mut buffer = buffer_with("one\ntwo\n");
+        assert_eq!(buffer.lines(), vec!["one", "two", ""]);
+        press_keys(&mut buffer, &[Key::ArrowDown; 5]);
+        assert_eq!(cursor(&buffer), (0, 2));
+
+        let mut buffer = buffer_with("one\ntwo\n");
+        press_keys(&mut buffer, &[Key::ArrowDown, Key::End, Key::ArrowRight]);
+        assert_eq!(cursor(&buffer), (0, 2));
+        press_keys(&mut buffer, &[Key::Char('i'), Key::Char('x')]);
+        assert_eq!(buffer.rope.to_string(), "one\ntwo\nx");
+        assert_eq!(cursor(&buffer), (1, 2));
+    }
+
+    #[test]
+    fn test_without_trailing_newline_the_cursor_stops_on_the_last_line() {
+        let mut buffer = buffer_with("one\ntwo");
+        press_keys(&mut buffer, &[Key::ArrowDown; 5]);
+        assert_eq!(cursor(&buffer), (0, 1));
+        press_keys(&mut buffer, &[Key::End, Key::ArrowRight, Key::ArrowDown]);
+        assert_eq!(cursor(&buffer), (3, 1));
+
+        press_keys(&mut buffer, &[Key::Char('i'), Key::Char('!'), Key::Enter, Key::Char('z')]);
+        assert_eq!(buffer.rope.to_string(), "one\ntwo!\nz");
+        assert_eq!(cursor(&buffer), (1, 2));
+    }
+
+    #[test]
+    fn test_file_terminator_is_not_a_line() {
+        // The terminator of the last line is split off on load and written
+        // back on save, so it never shows up as an extra line.
+        for (name, contents) in [("eol_yes.txt", "one\ntwo\n"), ("eol_no.txt", "one\ntwo")] {
+            let path = temp_file(name, contents);
+            let mut buffer = TextBuffer::new_with_path(12, path.to_str().unwrap()).unwrap();
+            assert_eq!(buffer.line_count(), 2);
+            press_keys(&mut buffer, &[Key::ArrowDown, Key::ArrowDown, Key::End, Key::Char('i'), Key::Char('!')]);
+            assert_eq!(buffer.lines(), vec!["one", "two!"]);
+
+            press_keys(&mut buffer, &[Key::OptionSpace, Key::Char(':'), Key::Char('w'), Key::Enter]);
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo!\n");
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use rawdeo::rope::{LineEnding, Rope, SplitStrategy};
+use std::fs;
+use std::io::{BufReader, Read};
+
+mod common;
+
+use common::temp_file;
+
+// Hands out at most seven bytes per read, so lines, CRLF pairs and multi-byte
+// chars all end up split across reads.
+struct SevenByteReader<'a>(&'a [u8]);
+
+impl Read for SevenByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.0.len().min(buf.len()).min(7);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_file_matches_source() {
+        let source = "first line\nsecond line\nthird\n";
+        let path = temp_file("lines.txt", source.as_bytes());
+        let rope = Rope::from_file(&path, SplitStrategy::LineBased).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(rope.to_string(), source);
+        assert_eq!(rope.lines(), 4);
+        assert_eq!(rope.len(), source.chars().count());
+    }
+
+    #[test]
+    fn test_from_file_without_trailing_newline() {
+        let path = temp_file("no_newline.txt", "one\ntwo\nthree".as_bytes());
+        let rope = Rope::from_file(&path, SplitStrategy::LineBased).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(rope.to_string(), "one\ntwo\nthree");
+        assert_eq!(rope.lines(), 3);
+        assert_eq!(rope.len(), 13);
+        assert_eq!(rope.get_line(2), Some("three".to_string()));
+    }
+
+    #[test]
+    fn test_from_file_empty() {
+        let path = temp_file("empty.txt", b"");
+        let rope = Rope::from_file(&path, SplitStrategy::LineBased).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(rope.is_empty());
+        assert_eq!(rope.lines(), 1);
+        assert_eq!(rope.to_string(), "");
+    }
+
+    #[test]
+    fn test_from_reader_spans_many_chunks() {
+        let source: String = (0..20_000).map(|i| format!("línea {}\r\n", i)).collect();
+        let rope = Rope::from_reader(source.as_bytes(), SplitStrategy::LineBased).unwrap();
+
+        assert_eq!(rope.line_ending(), LineEnding::CrLf);
+        assert_eq!(rope.to_string(), source);
+        assert_eq!(rope.lines(), 20_001);
+        assert_eq!(rope.len(), source.replace("\r\n", "\n").chars().count());
+        assert!(rope.is_balanced());
+    }
+
+    #[test]
+    fn test_from_reader_rejects_invalid_utf8() {
+        let result = Rope::from_reader(&b"ok\n\xff\xfe\n"[..], SplitStrategy::LineBased);
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_from_file_missing() {
+        let path = std::env::temp_dir().join("rawdeo_does_not_exist.txt");
+        assert!(Rope::from_file(&path, SplitStrategy::LineBased).is_err());
+    }
+
+    #[test]
+    fn test_save_round_trip_after_edit() {
+        let source = "alpha\r\nbeta\r\ngamma\r\n";
+        let input = temp_file("round_trip_in.txt", source.as_bytes());
+        let output = std::env::temp_dir().join(format!("rawdeo_{}_round_trip_out.txt", std::process::id()));
+
+        let mut rope = Rope::from_file(&input, SplitStrategy::LineBased).unwrap();
+        rope.insert(6, "inserted\n");
+        rope.remove_clamped(0, 1);
+        rope.save_to_file(&output).unwrap();
+
+        let saved = fs::read(&output).unwrap();
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&output).unwrap();
+        assert_eq!(saved, b"lpha\r\ninserted\r\nbeta\r\ngamma\r\n");
+    }
+
+    #[test]
+    fn test_write_to_matches_display() {
+        let source: String = (0..500).map(|i| format!("row {}\n", i)).collect();
+        let rope = Rope::from_string(&source, SplitStrategy::LineBased);
+
+        let mut out = Vec::new();
+        rope.write_to(&mut out).unwrap();
+        assert_eq!(out, rope.to_string().into_bytes());
+    }
+
+    #[test]
+    fn test_from_reader_in_seven_byte_chunks() {
+        let source = "größe\r\nzweite Zeile\r\n\r\nlast\r\n";
+        for strategy in [SplitStrategy::LineBased, SplitStrategy::FixedSize(4)] {
+            let reader = BufReader::with_capacity(7, SevenByteReader(source.as_bytes()));
+            let rope = Rope::from_reader(reader, strategy).unwrap();
+
+            assert_eq!(rope.line_ending(), LineEnding::CrLf);
+            assert_eq!(rope.to_string(), source);
+            assert_eq!(rope.lines(), 5);
+            assert_eq!(rope.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_from_reader_without_trailing_newline_in_chunks() {
+        let source = "one\ntwo\nthree";
+        let reader = BufReader::with_capacity(7, SevenByteReader(source.as_bytes()));
+        let rope = Rope::from_reader(reader, SplitStrategy::LineBased).unwrap();
+
+        assert_eq!(rope.line_ending(), LineEnding::Lf);
+        assert_eq!(rope.leaf_texts(), vec!["one\n", "two\n", "three"]);
+    }
+}
@@ -0,0 +1,124 @@
+use rawdeo::rope::{Rope, SplitStrategy};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_in_single_leaf() {
+        let rope = Rope::from_string("Hello, world!", SplitStrategy::LineBased);
+        assert_eq!(rope.find("world"), Some(7));
+        assert_eq!(rope.find("Hello"), Some(0));
+    }
+
+    #[test]
+    fn test_find_across_leaf_boundary() {
+        let mut rope = Rope::from_string("Hello", SplitStrategy::LineBased);
+        rope.insert(5, "\n");
+        rope.insert(6, "world");
+
+        assert!(rope.debug_string().starts_with("Internal"));
+        assert_eq!(rope.find("lo\nwo"), Some(3));
+    }
+
+    #[test]
+    fn test_find_from_skips_earlier_matches() {
+        let rope = Rope::from_string("abc abc abc", SplitStrategy::LineBased);
+        assert_eq!(rope.find_from(1, "abc"), Some(4));
+        assert_eq!(rope.find_from(8, "abc"), Some(8));
+        assert_eq!(rope.find_from(9, "abc"), None);
+    }
+
+    #[test]
+    fn test_find_missing_pattern() {
+        let rope = Rope::from_string("Hello\nRust!\nWorld!", SplitStrategy::LineBased);
+        assert_eq!(rope.find("Python"), None);
+        assert_eq!(rope.find(""), None);
+    }
+
+    #[test]
+    fn test_find_spanning_three_leaves() {
+        let rope = Rope::from_string("Hello\nworld", SplitStrategy::FixedSize(2));
+
+        assert!(rope.debug_string().matches("Leaf").count() >= 3);
+        assert_eq!(rope.find("lo\nwo"), Some(3));
+        assert_eq!(rope.find_all("lo\nwo"), vec![3]);
+    }
+
+    #[test]
+    fn test_find_at_both_ends() {
+        let rope = Rope::from_string("Hello\nRust!\nWorld!", SplitStrategy::LineBased);
+
+        assert_eq!(rope.find("Hel"), Some(0));
+        assert_eq!(rope.find("ld!"), Some(15));
+        assert_eq!(rope.rfind("ld!"), Some(15));
+    }
+
+    #[test]
+    fn test_find_needle_longer_than_leaf() {
+        let rope = Rope::from_string("ab\ncd\nef\ngh", SplitStrategy::LineBased);
+        assert_eq!(rope.find("b\ncd\nef\ng"), Some(1));
+    }
+
+    #[test]
+    fn test_find_all_and_rfind() {
+        let rope = Rope::from_string("one two\none two\none", SplitStrategy::LineBased);
+
+        assert_eq!(rope.find_all("one"), vec![0, 8, 16]);
+        assert_eq!(rope.rfind("two"), Some(12));
+        assert_eq!(rope.rfind("three"), None);
+        assert!(rope.find_all("three").is_empty());
+    }
+
+    #[test]
+    fn test_find_non_ascii_offsets_are_chars() {
+        let mut rope = Rope::from_string("héllo wörld", SplitStrategy::LineBased);
+        let index = rope.find("wörld").unwrap();
+        assert_eq!(index, 6);
+
+        rope.insert(index, "grüne ");
+        assert_eq!(rope.to_string(), "héllo grüne wörld");
+        assert_eq!(rope.find_all("ö"), vec![13]);
+    }
+
+    #[test]
+    fn test_find_all_repeated_pattern_does_not_overlap() {
+        let rope = Rope::from_string("aaaa", SplitStrategy::LineBased);
+        assert_eq!(rope.find_all("aa"), vec![0, 2]);
+
+        let rope = Rope::from_string("aaaaa", SplitStrategy::LineBased);
+        assert_eq!(rope.find_all("aa"), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_rfind_considers_overlapping_candidates() {
+        let rope = Rope::from_string("aaa", SplitStrategy::LineBased);
+        assert_eq!(rope.rfind("aa"), Some(1));
+
+        let rope = Rope::from_string("abab\nabab", SplitStrategy::LineBased);
+        assert_eq!(rope.rfind("bab"), Some(6));
+    }
+
+    #[test]
+    fn test_find_from_a_later_leaf() {
+        let rope = Rope::from_string("ab ab ab ab", SplitStrategy::FixedSize(2));
+        assert_eq!(rope.find_from(4, "ab"), Some(6));
+        assert_eq!(rope.find_from(5, "b a"), Some(7));
+        assert_eq!(rope.find_from(20, "ab"), None);
+    }
+
+    #[test]
+    fn test_rfind_from_looks_before_the_index() {
+        for strategy in [SplitStrategy::LineBased, SplitStrategy::FixedSize(2)] {
+            let rope = Rope::from_string("ab ab\nab", strategy);
+            assert_eq!(rope.rfind_from(6, "ab"), Some(3));
+            assert_eq!(rope.rfind_from(4, "ab"), Some(3));
+            assert_eq!(rope.rfind_from(3, "ab"), Some(0));
+            assert_eq!(rope.rfind_from(0, "ab"), None);
+            assert_eq!(rope.rfind_from(99, "ab"), Some(6));
+            assert_eq!(rope.rfind_from(5, "b\na"), Some(4));
+            assert_eq!(rope.rfind_from(3, ""), None);
+        }
+        assert_eq!(Rope::from_string("aaa", SplitStrategy::LineBased).rfind_from(1, "aa"), Some(0));
+    }
+}
@@ -0,0 +1,172 @@
+use rawdeo::rope::{Rope, SplitStrategy};
+
+const SEEDS: u64 = 64;
+const STEPS: usize = 300;
+
+// Small alphabet so newlines, multi-byte chars and combining marks show up
+// often.
+const ALPHABET: &[char] = &['a', 'b', ' ', '\n', 'é', '日', '🦀', '\u{301}'];
+
+// xorshift64*, so every run with a seed makes the same edits.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn text(&mut self) -> String {
+        let len = 1 + self.below(6);
+        (0..len).map(|_| ALPHABET[self.below(ALPHABET.len())]).collect()
+    }
+}
+
+#[derive(Debug)]
+enum Op {
+    Insert(usize, String),
+    Remove(usize, usize),
+    Replace(usize, usize, String),
+    Undo,
+    Redo,
+}
+
+// A non-empty range of at most 8 chars inside `0..len`.
+fn random_range(rng: &mut Rng, len: usize) -> (usize, usize) {
+    let start = rng.below(len);
+    (start, start + 1 + rng.below((len - start).min(8)))
+}
+
+fn random_op(rng: &mut Rng, len: usize) -> Op {
+    match rng.below(10) {
+        0..=3 => {
+            let index = rng.below(len + 1);
+            Op::Insert(index, rng.text())
+        }
+        4 | 5 if len > 0 => {
+            let (start, end) = random_range(rng, len);
+            Op::Remove(start, end)
+        }
+        6 if len > 0 => {
+            let (start, end) = random_range(rng, len);
+            Op::Replace(start, end, rng.text())
+        }
+        7 | 8 => Op::Undo,
+        _ => Op::Redo,
+    }
+}
+
+fn byte_range(text: &str, start: usize, end: usize) -> std::ops::Range<usize> {
+    let byte = |i| text.char_indices().nth(i).map_or(text.len(), |(b, _)| b);
+    byte(start)..byte(end)
+}
+
+// The texts a reference String went through, so undo and redo on it are
+// just moves between saved states.
+struct Model {
+    text: String,
+    undo: Vec<String>,
+    redo: Vec<String>,
+}
+
+impl Model {
+    fn edit(&mut self, start: usize, end: usize, inserted: &str) {
+        self.undo.push(self.text.clone());
+        self.redo.clear();
+        let range = byte_range(&self.text, start, end);
+        self.text.replace_range(range, inserted);
+    }
+}
+
+fn apply(rope: &mut Rope, model: &mut Model, op: &Op) {
+    // Every op is its own undo step, matching the model.
+    rope.break_undo_group();
+    match op {
+        Op::Insert(index, text) => {
+            rope.insert(*index, text);
+            model.edit(*index, *index, text);
+        }
+        Op::Remove(start, end) => {
+            rope.remove(*start, *end).unwrap();
+            model.edit(*start, *end, "");
+        }
+        Op::Replace(start, end, text) => {
+            rope.replace_range(*start, *end, text);
+            model.edit(*start, *end, text);
+        }
+        Op::Undo => {
+            rope.undo();
+            if let Some(previous) = model.undo.pop() {
+                model.redo.push(std::mem::replace(&mut model.text, previous));
+            }
+        }
+        Op::Redo => {
+            rope.redo();
+            if let Some(next) = model.redo.pop() {
+                model.undo.push(std::mem::replace(&mut model.text, next));
+            }
+        }
+    }
+}
+
+fn check(rope: &Rope, model: &Model) -> Result<(), String> {
+    rope.validate()?;
+    rope.verify_line_counts()?;
+    if rope.to_string() != model.text {
+        return Err(format!("text is {:?}, expected {:?}", rope.to_string(), model.text));
+    }
+    if rope.len() != model.text.chars().count() {
+        return Err(format!("len is {}, expected {}", rope.len(), model.text.chars().count()));
+    }
+    if rope.lines() != model.text.matches('\n').count() + 1 {
+        return Err(format!("lines() is {}, expected {}", rope.lines(), model.text.matches('\n').count() + 1));
+    }
+    Ok(())
+}
+
+// Runs one seed and on failure reports every op up to the failing one, which
+// is enough to replay it by hand.
+fn run(seed: u64, strategy: SplitStrategy) {
+    let mut rng = Rng::new(seed);
+    let mut rope = Rope::new(strategy);
+    let mut model = Model { text: String::new(), undo: Vec::new(), redo: Vec::new() };
+    let mut log = Vec::new();
+
+    for _ in 0..STEPS {
+        let op = random_op(&mut rng, model.text.chars().count());
+        apply(&mut rope, &mut model, &op);
+        log.push(op);
+        if let Err(error) = check(&rope, &model) {
+            panic!("seed {} with {:?} failed after {} ops: {}\nops: {:#?}", seed, strategy, log.len(), error, log);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_edits_on_line_based_ropes() {
+        for seed in 0..SEEDS {
+            run(seed, SplitStrategy::LineBased);
+        }
+    }
+
+    #[test]
+    fn test_random_edits_on_fixed_size_ropes() {
+        for seed in 0..SEEDS {
+            run(seed, SplitStrategy::FixedSize(3));
+        }
+    }
+}
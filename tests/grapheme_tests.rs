@@ -0,0 +1,199 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+use rawdeo::rope::{Rope, SplitStrategy};
+use rawdeo::unicode::{byte_at_column, char_width, str_width};
+
+fn boundaries(text: &str) -> Vec<usize> {
+    let rope = Rope::from_string(text, SplitStrategy::LineBased);
+    let mut result = vec![0];
+    let mut index = 0;
+    while index < rope.len() {
+        index = rope.next_grapheme_boundary(index);
+        result.push(index);
+    }
+    result
+}
+
+fn boundaries_backward(text: &str) -> Vec<usize> {
+    let rope = Rope::from_string(text, SplitStrategy::LineBased);
+    let mut result = vec![rope.len()];
+    let mut index = rope.len();
+    while index > 0 {
+        index = rope.prev_grapheme_boundary(index);
+        result.push(index);
+    }
+    result.reverse();
+    result
+}
+
+// Every stop of `grapheme_after` from the start and of `grapheme_before`
+// from the end, which have to agree.
+fn grapheme_steps(text: &str) -> Vec<usize> {
+    let rope = Rope::from_string(text, SplitStrategy::FixedSize(8));
+    let mut forward = vec![0];
+    while *forward.last().unwrap() < rope.len() {
+        forward.push(rope.grapheme_after(*forward.last().unwrap()));
+    }
+    let mut backward = vec![rope.len()];
+    while *backward.last().unwrap() > 0 {
+        backward.push(rope.grapheme_before(*backward.last().unwrap()));
+    }
+    backward.reverse();
+    assert_eq!(forward, backward);
+    forward
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emoji_with_skin_tone_is_one_grapheme() {
+        assert_eq!(boundaries("a👍🏽b"), vec![0, 1, 3, 4]);
+        assert_eq!(boundaries_backward("a👍🏽b"), vec![0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn test_combining_accent_stays_with_base() {
+        assert_eq!(boundaries("e\u{301}x"), vec![0, 2, 3]);
+        assert_eq!(boundaries_backward("e\u{301}x"), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_flags_pair_up() {
+        assert_eq!(boundaries("🇩🇪🇫🇷"), vec![0, 2, 4]);
+        assert_eq!(boundaries_backward("🇩🇪🇫🇷"), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_zwj_sequence_is_one_grapheme() {
+        let family = "👩\u{200D}👩\u{200D}👧";
+        assert_eq!(boundaries(&format!("{}!", family)), vec![0, 5, 6]);
+        assert_eq!(boundaries_backward(&format!("{}!", family)), vec![0, 5, 6]);
+    }
+
+    #[test]
+    fn test_cjk_chars_are_wide() {
+        assert_eq!(boundaries("漢字"), vec![0, 1, 2]);
+        assert_eq!(char_width('漢'), 2);
+        assert_eq!(char_width('a'), 1);
+        assert_eq!(char_width('\u{301}'), 0);
+        assert_eq!(str_width("日本語abc"), 9);
+    }
+
+    #[test]
+    fn test_buffer_cursor_steps_over_graphemes() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("a👍🏽b");
+
+        let mut positions = vec![buffer.cursor_x];
+        for _ in 0..3 {
+            buffer.handle_keypress(Key::ArrowRight);
+            positions.push(buffer.cursor_x);
+        }
+        assert_eq!(positions, vec![0, 1, 9, 10]);
+
+        buffer.handle_keypress(Key::ArrowLeft);
+        assert_eq!(buffer.cursor_x, 9);
+        buffer.handle_keypress(Key::ArrowLeft);
+        assert_eq!(buffer.cursor_x, 1);
+    }
+
+    #[test]
+    fn test_backspace_deletes_whole_grapheme() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("ce\u{301}");
+        buffer.cursor_x = buffer.line(0).len();
+
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!(buffer.lines(), vec!["c"]);
+        assert_eq!(buffer.cursor_x, 1);
+    }
+
+    #[test]
+    fn test_backspace_steps_over_each_char_kind() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.auto_indent = false;
+        buffer.set_text("x\n\ta漢e\u{301}👍🏽");
+        buffer.cursor_y = 1;
+        buffer.cursor_x = buffer.line(1).len();
+        buffer.handle_keypress(Key::Char('i'));
+
+        let mut cursors = vec![(buffer.cursor_x, buffer.cursor_y)];
+        for _ in 0..6 {
+            buffer.handle_keypress(Key::Backspace);
+            cursors.push((buffer.cursor_x, buffer.cursor_y));
+        }
+        assert_eq!(cursors, vec![(16, 1), (8, 1), (5, 1), (2, 1), (1, 1), (0, 1), (1, 0)]);
+        assert_eq!(buffer.lines(), vec!["x"]);
+
+        buffer.handle_keypress(Key::Backspace);
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+        assert_eq!(buffer.lines(), vec![""]);
+    }
+
+    #[test]
+    fn test_vertical_move_lands_on_boundary() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("abcdef\n漢字");
+        buffer.cursor_x = 4;
+
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!(buffer.cursor_x, 3);
+    }
+
+    #[test]
+    fn test_grapheme_steps_over_family_emoji_and_accents() {
+        let family = "👨\u{200D}👩\u{200D}👧\u{200D}👦";
+        assert_eq!(grapheme_steps(&format!("a{}b", family)), vec![0, 1, 8, 9]);
+        assert_eq!(grapheme_steps("cafe\u{301}s"), vec![0, 1, 2, 3, 5, 6]);
+        assert_eq!(grapheme_steps("a\u{308}\u{301}\nb"), vec![0, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_grapheme_steps_on_long_clusters() {
+        // Longer than the windows the segmenter is fed.
+        let accents = format!("x{}y", "\u{301}".repeat(150));
+        assert_eq!(grapheme_steps(&accents), vec![0, 151, 152]);
+
+        let flags = "🇩🇪".repeat(60);
+        let rope = Rope::from_string(&flags, SplitStrategy::LineBased);
+        assert_eq!(rope.grapheme_before(100), 98);
+        assert_eq!(rope.grapheme_after(100), 102);
+    }
+
+    #[test]
+    fn test_arrows_step_over_a_family_emoji() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("👩\u{200D}👩\u{200D}👧!");
+        buffer.handle_keypress(Key::ArrowRight);
+        assert_eq!(buffer.cursor_x, 18);
+        buffer.handle_keypress(Key::ArrowRight);
+        buffer.handle_keypress(Key::ArrowLeft);
+        buffer.handle_keypress(Key::ArrowLeft);
+        assert_eq!(buffer.cursor_x, 0);
+    }
+
+    #[test]
+    fn test_cjk_line_is_twice_as_wide_as_its_chars() {
+        let mut buffer = TextBuffer::new(12);
+        let line = "漢字かなカナ한글";
+        buffer.set_text(&format!("{}\n{}\tx", line, line));
+        assert_eq!(buffer.visual_line_length(0), 2 * line.chars().count());
+        // Tabs still snap to their configured stops.
+        assert_eq!(buffer.visual_line_length(1), 16 + 4 + 1);
+
+        // Both halves of a wide char map back to it.
+        assert_eq!(byte_at_column(line, 2, 4), 3);
+        assert_eq!(byte_at_column(line, 3, 4), 3);
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_unicode_tables_cover_more_wide_chars() {
+        assert_eq!(char_width('🚀'), 2);
+        assert_eq!(str_width("👍🏽"), 2);
+        assert_eq!(char_width('\u{301}'), 0);
+    }
+}
@@ -0,0 +1,87 @@
+use rawdeo::buffer::{format_gutter, line_number_label, LineNumberMode, LineNumbers, TextBuffer};
+
+mod common;
+
+use common::buffer_with;
+
+const ABSOLUTE: LineNumbers = LineNumbers { number: true, relative: false };
+const HYBRID: LineNumbers = LineNumbers { number: true, relative: true };
+const RELATIVE: LineNumbers = LineNumbers { number: false, relative: true };
+const NONE: LineNumbers = LineNumbers { number: false, relative: false };
+
+fn hundred_twenty_lines() -> TextBuffer {
+    let lines: Vec<String> = (1..=120).map(|i| format!("line {}", i)).collect();
+    buffer_with(&lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_absolute_gutter() {
+        assert_eq!(format_gutter(0, 5, 120, ABSOLUTE), "  1 | ");
+        assert_eq!(format_gutter(119, 5, 120, ABSOLUTE), "120 | ");
+        assert_eq!(format_gutter(4, 4, 9, ABSOLUTE), "5 | ");
+    }
+
+    #[test]
+    fn test_relative_gutter_keeps_cursor_line_absolute() {
+        assert_eq!(format_gutter(5, 5, 120, HYBRID), "  6 | ");
+        assert_eq!(format_gutter(2, 5, 120, HYBRID), "  3 | ");
+        assert_eq!(format_gutter(2, 5, 120, RELATIVE), "  3 | ");
+        assert_eq!(format_gutter(105, 5, 120, HYBRID), "100 | ");
+        assert_eq!(format_gutter(5, 5, 120, RELATIVE), "  0 | ");
+    }
+
+    #[test]
+    fn test_gutter_width_covers_both_formats() {
+        for line_numbers in [ABSOLUTE, HYBRID, RELATIVE] {
+            for (line, cursor) in [(0, 0), (0, 99), (99, 0), (50, 50)] {
+                assert_eq!(format_gutter(line, cursor, 100, line_numbers).len(), 6);
+            }
+        }
+        assert_eq!(format_gutter(3, 1, 100, NONE), "");
+    }
+
+    #[test]
+    fn test_set_number_options() {
+        let mut buffer = hundred_twenty_lines();
+        buffer.execute_command("3");
+        buffer.execute_command("set relativenumber");
+        assert_eq!(buffer.line_numbers, HYBRID);
+        let frame = buffer.compose_frame();
+        assert_eq!(frame[2].1, "  2 | line 1");
+        assert_eq!(frame[4].1, "  3 | line 3");
+        assert_eq!(frame[5].1, "  1 | line 4");
+
+        buffer.execute_command("set nonu");
+        buffer.execute_command("set nornu");
+        assert_eq!(buffer.line_numbers, NONE);
+        assert_eq!(buffer.compose_frame()[2].1, "line 1");
+
+        let mut out = Vec::new();
+        buffer.render_to(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("\x1b[3;1H"));
+
+        buffer.execute_command("set number");
+        assert_eq!(buffer.line_numbers, ABSOLUTE);
+    }
+
+    #[test]
+    fn test_line_number_label_per_mode() {
+        assert_eq!(line_number_label(9, 3, LineNumberMode::Absolute), "10");
+        assert_eq!(line_number_label(9, 3, LineNumberMode::Relative), "6");
+        assert_eq!(line_number_label(0, 3, LineNumberMode::Hybrid), "3");
+        assert_eq!(line_number_label(3, 3, LineNumberMode::Relative), "0");
+        assert_eq!(line_number_label(3, 3, LineNumberMode::Hybrid), "4");
+    }
+
+    #[test]
+    fn test_modes_round_trip_through_options() {
+        for mode in [Some(LineNumberMode::Absolute), Some(LineNumberMode::Relative), Some(LineNumberMode::Hybrid), None] {
+            assert_eq!(LineNumbers::from_mode(mode).mode(), mode);
+        }
+        assert_eq!(HYBRID.mode(), Some(LineNumberMode::Hybrid));
+    }
+}
@@ -0,0 +1,86 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::highlight::{BasicHighlighter, Color, Highlighter, StyleSpan};
+use rawdeo::input::Key;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+mod common;
+
+use common::press;
+
+// Makes the first word of every line red and logs which lines it was asked for.
+struct FirstWord {
+    asked: Rc<RefCell<Vec<usize>>>,
+}
+
+impl Highlighter for FirstWord {
+    fn highlight_line(&mut self, line_idx: usize, text: &str) -> Vec<StyleSpan> {
+        self.asked.borrow_mut().push(line_idx);
+        let end_col = text.chars().take_while(|c| !c.is_whitespace()).count();
+        vec![StyleSpan { start_col: 0, end_col, fg: Color::Red, bold: false }]
+    }
+}
+
+fn highlighted(text: &str) -> (TextBuffer, Rc<RefCell<Vec<usize>>>) {
+    let mut buffer = TextBuffer::new(12);
+    buffer.set_text(text);
+    let asked = Rc::new(RefCell::new(Vec::new()));
+    buffer.set_highlighter(Box::new(FirstWord { asked: asked.clone() }));
+    buffer.refresh_highlights();
+    (buffer, asked)
+}
+
+fn row(buffer: &TextBuffer, line: usize) -> String {
+    buffer.compose_frame()[line + 2].1.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_color_the_text() {
+        let (buffer, _) = highlighted("let x\n\tfn y");
+        assert_eq!(row(&buffer, 0), "1 | \x1b[31mlet\x1b[0m x");
+        // A tab in front of the span is expanded, so the columns still line up.
+        assert_eq!(row(&buffer, 1), "2 |     fn y");
+    }
+
+    #[test]
+    fn test_lines_are_only_asked_for_again_after_an_edit() {
+        let text: String = (0..30).map(|i| format!("w{} x\n", i)).collect();
+        let (mut buffer, asked) = highlighted(&text);
+        assert_eq!(*asked.borrow(), (0..10).collect::<Vec<_>>());
+
+        asked.borrow_mut().clear();
+        buffer.refresh_highlights();
+        assert!(asked.borrow().is_empty());
+
+        buffer.handle_keypress(Key::ArrowDown);
+        buffer.handle_keypress(Key::ArrowDown);
+        press(&mut buffer, "iab");
+        buffer.refresh_highlights();
+        assert_eq!(*asked.borrow(), (2..10).collect::<Vec<_>>());
+        assert_eq!(row(&buffer, 2), " 3 | \x1b[31mabw2\x1b[0m x");
+    }
+
+    #[test]
+    fn test_spans_are_clipped_to_the_visible_columns() {
+        let (mut buffer, _) = highlighted(&format!("{} tail", "a".repeat(100)));
+        buffer.scroll_x = 98;
+        assert_eq!(row(&buffer, 0), "1 | \x1b[31maa\x1b[0m tail");
+    }
+
+    #[test]
+    fn test_basic_highlighter_knows_strings_and_comments() {
+        let mut rust = BasicHighlighter::for_path(Path::new("main.rs")).unwrap();
+        let spans = rust.highlight_line(0, r#"f("a\"//b"); // done"#);
+        let colored: Vec<_> = spans.iter().map(|span| (span.start_col, span.end_col, span.fg)).collect();
+        assert_eq!(colored, vec![(2, 10, Color::Green), (13, 20, Color::Cyan)]);
+
+        let mut python = BasicHighlighter::for_path(Path::new("run.py")).unwrap();
+        assert_eq!(python.highlight_line(0, "x = 1 # one")[0].start_col, 6);
+        assert!(BasicHighlighter::for_path(Path::new("notes.txt")).is_none());
+    }
+}
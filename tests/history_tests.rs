@@ -0,0 +1,108 @@
+use rawdeo::buffer::{Mode, TextBuffer};
+use rawdeo::history::History;
+use rawdeo::input::Key;
+
+mod common;
+
+use common::press;
+
+fn history(entries: &[&str]) -> History {
+    let mut history = History::new(10);
+    for entry in entries {
+        history.push(entry);
+    }
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_skips_empty_lines_and_repeats() {
+        let history = history(&["w", "w", "", "q", "w"]);
+        assert_eq!(history.entries(), ["w", "q", "w"]);
+    }
+
+    #[test]
+    fn test_capacity_drops_the_oldest() {
+        let mut history = History::new(2);
+        for entry in ["a", "b", "c"] {
+            history.push(entry);
+        }
+        assert_eq!(history.entries(), ["b", "c"]);
+        history.set_capacity(1);
+        assert_eq!(history.entries(), ["c"]);
+    }
+
+    #[test]
+    fn test_navigation_stops_at_the_oldest_entry() {
+        let mut history = history(&["one", "two"]);
+        assert_eq!(history.older(""), Some("two"));
+        assert_eq!(history.older(""), Some("one"));
+        assert_eq!(history.older(""), Some("one"));
+        assert_eq!(history.newer(), Some("two"));
+    }
+
+    #[test]
+    fn test_going_back_down_restores_the_typed_text() {
+        let mut history = history(&["one", "two"]);
+        assert_eq!(history.newer(), None);
+        assert_eq!(history.older("%s/a"), Some("two"));
+        assert_eq!(history.older("ignored"), Some("one"));
+        assert_eq!(history.newer(), Some("two"));
+        assert_eq!(history.newer(), Some("%s/a"));
+        assert_eq!(history.newer(), None);
+
+        // A new first step takes a new draft.
+        assert_eq!(history.older("next"), Some("two"));
+        assert_eq!(history.newer(), Some("next"));
+    }
+
+    #[test]
+    fn test_empty_history_keeps_the_input() {
+        let mut history = History::new(10);
+        assert_eq!(history.older("typed"), None);
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("rawdeo_{}_history", std::process::id()));
+        history(&["w", "%s/a/b/g"]).save(&path).unwrap();
+        let mut loaded = History::new(10);
+        loaded.load(&path).unwrap();
+        assert_eq!(loaded.entries(), ["w", "%s/a/b/g"]);
+        std::fs::remove_file(&path).unwrap();
+
+        loaded.load(&path).unwrap();
+        assert!(loaded.entries().is_empty());
+    }
+
+    #[test]
+    fn test_arrows_recall_commands_on_the_command_line() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("a a\nb");
+        press(&mut buffer, ":%s/a/b/g");
+        buffer.handle_keypress(Key::Enter);
+        press(&mut buffer, ":set nu");
+        buffer.handle_keypress(Key::Enter);
+
+        press(&mut buffer, ":se");
+        buffer.handle_keypress(Key::ArrowUp);
+        assert_eq!(buffer.command_input, "set nu");
+        buffer.handle_keypress(Key::ArrowUp);
+        assert_eq!(buffer.command_input, "%s/a/b/g");
+        buffer.handle_keypress(Key::ArrowDown);
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!(buffer.command_input, "se");
+
+        buffer.handle_keypress(Key::ArrowUp);
+        buffer.handle_keypress(Key::Escape);
+        assert!(matches!(buffer.mode, Mode::Normal));
+        buffer.handle_keypress(Key::Char(':'));
+        assert_eq!(buffer.command_input, "");
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!(buffer.command_input, "");
+    }
+}
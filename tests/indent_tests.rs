@@ -0,0 +1,108 @@
+use rawdeo::buffer::{indentation, TextBuffer};
+use rawdeo::input::Key;
+use rawdeo::rope::{Rope, SplitStrategy};
+
+fn insert_at(text: &str, x: usize, y: usize) -> TextBuffer {
+    let mut buffer = TextBuffer::new(12);
+    buffer.set_text(text);
+    buffer.cursor_x = x;
+    buffer.cursor_y = y;
+    buffer.handle_keypress(Key::Char('i'));
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indentation_of_mixed_prefixes() {
+        let rope = Rope::from_string("    four\n\tone tab\n \t  mixed\nnone\n  \t", SplitStrategy::LineBased);
+        let indents: Vec<String> = (0..rope.lines())
+            .map(|y| indentation(&rope.get_line(y).unwrap()).to_string())
+            .collect();
+        assert_eq!(indents, vec!["    ", "\t", " \t  ", "", "  \t"]);
+    }
+
+    #[test]
+    fn test_enter_copies_indentation() {
+        let mut buffer = insert_at(" \t  if x {", 10, 0);
+        buffer.handle_keypress(Key::Enter);
+        assert_eq!(buffer.lines(), vec![" \t  if x {", " \t  "]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (4, 1));
+
+        buffer.handle_keypress(Key::Char('y'));
+        assert_eq!(buffer.line(1), " \t  y");
+    }
+
+    #[test]
+    fn test_enter_keeps_space_and_tab_indents() {
+        let mut buffer = insert_at("    let x = 1;", 14, 0);
+        buffer.handle_keypress(Key::Enter);
+        assert_eq!(buffer.lines(), vec!["    let x = 1;", "    "]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (4, 1));
+
+        let mut buffer = insert_at("\t\tcall();", 9, 0);
+        buffer.handle_keypress(Key::Enter);
+        assert_eq!(buffer.lines(), vec!["\t\tcall();", "\t\t"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 1));
+    }
+
+    #[test]
+    fn test_enter_inside_indentation_copies_only_what_is_before_the_cursor() {
+        let mut buffer = insert_at("        body", 4, 0);
+        buffer.handle_keypress(Key::Enter);
+        assert_eq!(buffer.lines(), vec!["    ", "        body"]);
+        assert_eq!(buffer.cursor_x, 4);
+    }
+
+    #[test]
+    fn test_enter_on_blank_indented_line_clears_it() {
+        let mut buffer = insert_at("\tcode\n\t", 1, 1);
+        buffer.handle_keypress(Key::Enter);
+        assert_eq!(buffer.lines(), vec!["\tcode", "", "\t"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (1, 2));
+    }
+
+    #[test]
+    fn test_enter_with_indent_is_one_undo_entry() {
+        let mut buffer = insert_at("    a", 5, 0);
+        buffer.handle_keypress(Key::Enter);
+        buffer.handle_keypress(Key::OptionSpace);
+        buffer.handle_keypress(Key::Char('u'));
+        assert_eq!(buffer.lines(), vec!["    a"]);
+    }
+
+    #[test]
+    fn test_auto_indent_can_be_turned_off() {
+        let mut buffer = insert_at("    a", 5, 0);
+        buffer.auto_indent = false;
+        buffer.handle_keypress(Key::Enter);
+        assert_eq!(buffer.lines(), vec!["    a", ""]);
+        assert_eq!(buffer.cursor_x, 0);
+    }
+
+    #[test]
+    fn test_backspace_in_indentation_goes_back_a_tab_stop() {
+        let mut buffer = insert_at("          x", 10, 0);
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!(buffer.line(0), "        x");
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!(buffer.line(0), "    x");
+        assert_eq!(buffer.cursor_x, 4);
+
+        // Spaces after a tab only go back as far as the tab.
+        let mut buffer = insert_at("\t  x", 3, 0);
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!(buffer.line(0), "\tx");
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!(buffer.line(0), "x");
+    }
+
+    #[test]
+    fn test_backspace_after_text_deletes_one_space() {
+        let mut buffer = insert_at("a    b", 5, 0);
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!(buffer.line(0), "a   b");
+    }
+}
@@ -0,0 +1,145 @@
+use rawdeo::input::{decode_bytes, decode_key, Key, KeyPress, MouseEvent, MouseKind};
+
+fn decode(bytes: &[u8]) -> Key {
+    decode_bytes(bytes).key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_keys() {
+        assert_eq!(decode(b"a"), Key::Char('a'));
+        assert_eq!(decode(b" "), Key::Space);
+        assert_eq!(decode(b"\t"), Key::Tab);
+        assert_eq!(decode(b"\n"), Key::Enter);
+        assert_eq!(decode(b"\x7f"), Key::Backspace);
+        assert_eq!(decode(b"\xC2\xA0"), Key::OptionSpace);
+        assert_eq!(decode(b""), Key::Unknown);
+    }
+
+    #[test]
+    fn test_arrows_and_ctrl_arrows() {
+        assert_eq!(decode(b"\x1b[A"), Key::ArrowUp);
+        assert_eq!(decode(b"\x1b[B"), Key::ArrowDown);
+        assert_eq!(decode(b"\x1b[C"), Key::ArrowRight);
+        assert_eq!(decode(b"\x1b[D"), Key::ArrowLeft);
+        assert_eq!(decode(b"\x1b[1;5A"), Key::CtrlArrowUp);
+        assert_eq!(decode(b"\x1b[1;5B"), Key::CtrlArrowDown);
+        assert_eq!(decode(b"\x1b[1;5C"), Key::CtrlArrowRight);
+        assert_eq!(decode(b"\x1b[1;5D"), Key::CtrlArrowLeft);
+    }
+
+    #[test]
+    fn test_home_end_and_paging() {
+        assert_eq!(decode(b"\x1b[H"), Key::Home);
+        assert_eq!(decode(b"\x1b[F"), Key::End);
+        assert_eq!(decode(b"\x1bOH"), Key::Home);
+        assert_eq!(decode(b"\x1bOF"), Key::End);
+        assert_eq!(decode(b"\x1b[1~"), Key::Home);
+        assert_eq!(decode(b"\x1b[4~"), Key::End);
+        assert_eq!(decode(b"\x1b[5~"), Key::PageUp);
+        assert_eq!(decode(b"\x1b[6~"), Key::PageDown);
+        assert_eq!(decode(b"\x1b[3~"), Key::Delete);
+        assert_eq!(decode(b"\x1b[3;5~"), Key::CtrlDelete);
+    }
+
+    #[test]
+    fn test_incomplete_and_unknown_sequences() {
+        assert_eq!(decode(b"\x1b"), Key::Escape);
+        assert_eq!(decode(b"\x1b[1;5"), Key::Escape);
+        assert_eq!(decode(b"\x1b[99~"), Key::Unknown);
+    }
+
+    #[test]
+    fn test_decoder_stops_after_one_key() {
+        let mut bytes = b"\x1b[5~x".iter().copied();
+        assert_eq!(decode_key(&mut bytes, &mut None).key, Key::PageUp);
+        assert_eq!(decode_key(&mut bytes, &mut None).key, Key::Char('x'));
+        assert_eq!(decode_key(&mut bytes, &mut None).key, Key::Unknown);
+    }
+
+    #[test]
+    fn test_arrow_sequences_read_exactly_two_bytes_after_esc() {
+        let mut bytes = b"\x1b[A\x1b[Dq".iter().copied();
+        assert_eq!(decode_key(&mut bytes, &mut None), KeyPress::from(Key::ArrowUp));
+        assert_eq!(decode_key(&mut bytes, &mut None), KeyPress::from(Key::ArrowLeft));
+        assert_eq!(bytes.collect::<Vec<u8>>(), b"q");
+    }
+
+    #[test]
+    fn test_multibyte_chars() {
+        assert_eq!(decode("é".as_bytes()), Key::Char('é'));
+        assert_eq!(decode("€".as_bytes()), Key::Char('€'));
+        assert_eq!(decode("🦀".as_bytes()), Key::Char('🦀'));
+        assert_eq!(decode(b"\xC2\xA0"), Key::OptionSpace);
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_unknown() {
+        assert_eq!(decode(b"\xC3"), Key::Unknown);
+        assert_eq!(decode(b"\xE2\x82"), Key::Unknown);
+        assert_eq!(decode(b"\xC3\x28"), Key::Unknown);
+
+        let mut bytes = "€x".bytes();
+        assert_eq!(decode_key(&mut bytes, &mut None).key, Key::Char('€'));
+        assert_eq!(decode_key(&mut bytes, &mut None).key, Key::Char('x'));
+    }
+
+    #[test]
+    fn test_ctrl_letters() {
+        assert_eq!(decode(b"\x1a"), Key::Ctrl('z'));
+        assert_eq!(decode(b"\x12"), Key::Ctrl('r'));
+        assert_eq!(decode(b"\x01"), Key::Ctrl('a'));
+        assert_eq!(decode(b"\x09"), Key::Tab);
+        assert_eq!(decode(b"\x0a"), Key::Enter);
+    }
+
+    #[test]
+    fn test_sgr_mouse_reports() {
+        let mouse = |kind, column, row| Key::Mouse(MouseEvent { kind, column, row });
+        assert_eq!(decode(b"\x1b[<0;12;5M"), mouse(MouseKind::Press, 12, 5));
+        assert_eq!(decode(b"\x1b[<32;13;5M"), mouse(MouseKind::Drag, 13, 5));
+        assert_eq!(decode(b"\x1b[<0;13;6m"), mouse(MouseKind::Release, 13, 6));
+        assert_eq!(decode(b"\x1b[<64;1;1M"), mouse(MouseKind::ScrollUp, 1, 1));
+        assert_eq!(decode(b"\x1b[<65;140;50M"), mouse(MouseKind::ScrollDown, 140, 50));
+    }
+
+    #[test]
+    fn test_other_mouse_reports_are_unknown() {
+        assert_eq!(decode(b"\x1b[<2;4;4M"), Key::Unknown);
+        assert_eq!(decode(b"\x1b[<0;4M"), Key::Unknown);
+
+        let mut bytes = b"\x1b[<1;2;3Mx".iter().copied();
+        assert_eq!(decode_key(&mut bytes, &mut None).key, Key::Unknown);
+        assert_eq!(decode_key(&mut bytes, &mut None).key, Key::Char('x'));
+    }
+
+    #[test]
+    fn test_ctrl_and_alt_modifiers() {
+        let press = |key, ctrl, alt| KeyPress { key, ctrl, alt };
+        assert_eq!(decode_bytes(b"s"), press(Key::Char('s'), false, false));
+        assert_eq!(decode_bytes(b"\x13"), press(Key::Ctrl('s'), true, false));
+        assert_eq!(decode_bytes(b"\x1bs"), press(Key::Char('s'), false, true));
+        assert_eq!(decode_bytes(b"\x1b\x13"), press(Key::Ctrl('s'), true, true));
+        assert_eq!(decode_bytes(b"\x1b\xC3\xA9"), press(Key::Char('é'), false, true));
+        assert_eq!(decode_bytes(b"\x1b[1;5C"), press(Key::CtrlArrowRight, true, false));
+        assert_eq!(decode_bytes(b"\x1b[1;3A"), press(Key::ArrowUp, false, true));
+        assert_eq!(decode_bytes(b"\x1b[1;7B"), press(Key::CtrlArrowDown, true, true));
+        assert_eq!(decode_bytes(b"\x1b[3;3~"), press(Key::Delete, false, true));
+        assert_eq!(decode_bytes(b"\x1b"), press(Key::Escape, false, false));
+    }
+
+    #[test]
+    fn test_double_escape_is_two_keys() {
+        assert_eq!(decode_bytes(b"\x1b\x1b"), KeyPress::from(Key::Escape));
+
+        let mut bytes = b"\x1b\x1bj".iter().copied();
+        let mut unread = None;
+        assert_eq!(decode_key(&mut bytes, &mut unread), KeyPress::from(Key::Escape));
+        assert_eq!(unread, Some(b'\x1b'));
+        assert_eq!(decode_key(&mut bytes, &mut unread), KeyPress::alt(Key::Char('j')));
+        assert_eq!(decode_key(&mut bytes, &mut unread).key, Key::Unknown);
+    }
+}
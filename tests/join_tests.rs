@@ -0,0 +1,75 @@
+use rawdeo::buffer::Mode;
+use rawdeo::input::Key;
+
+mod common;
+
+use common::{buffer_with, press_keys};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_j_joins_with_one_space_and_drops_the_indent() {
+        let mut buffer = buffer_with("fn main() {\n    body();\n}");
+        press_keys(&mut buffer, &[Key::Char('J')]);
+        assert_eq!(buffer.lines(), vec!["fn main() { body();", "}"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (11, 0));
+
+        press_keys(&mut buffer, &[Key::Char('u')]);
+        assert_eq!(buffer.lines(), vec!["fn main() {", "    body();", "}"]);
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_j_keeps_a_space_before_punctuation() {
+        let mut buffer = buffer_with("call(a,\n)");
+        press_keys(&mut buffer, &[Key::Char('J')]);
+        assert_eq!(buffer.lines(), vec!["call(a, )"]);
+    }
+
+    #[test]
+    fn test_joining_an_empty_line_adds_no_space() {
+        let mut buffer = buffer_with("text\n   \nmore");
+        press_keys(&mut buffer, &[Key::Char('J')]);
+        assert_eq!(buffer.lines(), vec!["text", "more"]);
+        assert_eq!(buffer.cursor_x, 4);
+
+        let mut buffer = buffer_with("\n  indented");
+        press_keys(&mut buffer, &[Key::Char('J')]);
+        assert_eq!(buffer.lines(), vec!["indented"]);
+    }
+
+    #[test]
+    fn test_j_on_the_last_line_does_nothing() {
+        let mut buffer = buffer_with("one\ntwo");
+        press_keys(&mut buffer, &[Key::ArrowDown, Key::Char('J')]);
+        assert_eq!(buffer.lines(), vec!["one", "two"]);
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_count_joins_that_many_lines_in_one_undo() {
+        let mut buffer = buffer_with("a\nb\nc\nd");
+        press_keys(&mut buffer, &[Key::Char('3'), Key::Char('J')]);
+        assert_eq!(buffer.lines(), vec!["a b c", "d"]);
+        assert_eq!(buffer.cursor_x, 3);
+
+        press_keys(&mut buffer, &[Key::Char('9'), Key::Char('J')]);
+        assert_eq!(buffer.lines(), vec!["a b c d"]);
+        press_keys(&mut buffer, &[Key::Char('u'), Key::Char('u')]);
+        assert_eq!(buffer.lines(), vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_j_joins_the_selected_lines() {
+        let mut buffer = buffer_with("one\ntwo\nthree\nfour");
+        press_keys(&mut buffer, &[Key::ArrowDown, Key::Char('v'), Key::ArrowDown, Key::Char('J')]);
+        assert_eq!(buffer.lines(), vec!["one", "two three", "four"]);
+        assert!(matches!(buffer.mode, Mode::Normal));
+
+        // A selection on one line still joins it with the next.
+        press_keys(&mut buffer, &[Key::Char('v'), Key::Char('J')]);
+        assert_eq!(buffer.lines(), vec!["one", "two three four"]);
+    }
+}
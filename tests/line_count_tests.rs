@@ -0,0 +1,116 @@
+use rawdeo::rope::{Rope, SplitStrategy};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small deterministic generator so failures are reproducible.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % bound.max(1)
+        }
+    }
+
+    #[test]
+    fn test_lines_counts_newlines() {
+        assert_eq!(Rope::from_string("Hello", SplitStrategy::LineBased).lines(), 1);
+        assert_eq!(Rope::from_string("Hello\nRust!\nWorld!", SplitStrategy::LineBased).lines(), 3);
+        assert_eq!(Rope::new(SplitStrategy::LineBased).lines(), 1);
+    }
+
+    #[test]
+    fn test_lines_after_edits() {
+        let mut rope = Rope::from_string("Hello\nWorld!", SplitStrategy::LineBased);
+        rope.insert(6, "Rust!\n");
+        assert_eq!(rope.lines(), 3);
+
+        rope.remove_clamped(0, 6);
+        assert_eq!(rope.lines(), 2);
+
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.lines(), 3);
+    }
+
+    #[test]
+    fn test_cached_line_counts_after_random_edits() {
+        let pieces = ["a", "\n", "bc\n", "\n\nd", "hello ", "wörld\n"];
+
+        for strategy in [SplitStrategy::LineBased, SplitStrategy::FixedSize(8)] {
+            let mut rng = Lcg(42);
+            let mut rope = Rope::from_string("one\ntwo\nthree\n", strategy);
+            let mut expected = "one\ntwo\nthree\n".to_string();
+
+            for _ in 0..300 {
+                let len = expected.chars().count();
+                if rng.next(3) == 0 && len > 0 {
+                    let start = rng.next(len);
+                    let end = (start + 1 + rng.next(4)).min(len);
+                    rope.remove_clamped(start, end);
+                    expected = expected.chars().take(start).chain(expected.chars().skip(end)).collect();
+                } else {
+                    let index = rng.next(len + 1);
+                    let piece = pieces[rng.next(pieces.len())];
+                    rope.insert(index, piece);
+                    let byte = expected.char_indices().nth(index).map_or(expected.len(), |(b, _)| b);
+                    expected.insert_str(byte, piece);
+                }
+
+                assert_eq!(rope.to_string(), expected);
+                assert_eq!(rope.len(), expected.chars().count());
+                assert_eq!(rope.lines(), expected.matches('\n').count() + 1);
+                assert_eq!(rope.verify_line_counts(), Ok(expected.matches('\n').count()));
+                assert_eq!(rope.validate(), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_trailing_newline_conventions() {
+        let counts: Vec<(usize, usize)> = ["", "a", "a\n", "a\n\n", "\n"]
+            .iter()
+            .map(|text| {
+                let rope = Rope::from_string(text, SplitStrategy::LineBased);
+                assert_eq!(rope.display_lines(), text.lines().count());
+                (rope.lines(), rope.display_lines())
+            })
+            .collect();
+        assert_eq!(counts, vec![(1, 0), (1, 1), (2, 1), (3, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn test_ensure_trailing_newline_toggles_one_newline() {
+        for (text, with, without) in [("a", "a\n", "a"), ("a\n", "a\n", "a"), ("a\n\n", "a\n\n", "a\n"), ("", "\n", "")] {
+            let mut rope = Rope::from_string(text, SplitStrategy::LineBased);
+            rope.ensure_trailing_newline(true);
+            assert_eq!(rope.to_string(), with);
+            rope.ensure_trailing_newline(true);
+            assert_eq!(rope.to_string(), with);
+
+            let mut rope = Rope::from_string(text, SplitStrategy::LineBased);
+            rope.ensure_trailing_newline(false);
+            assert_eq!(rope.to_string(), without);
+            rope.validate().unwrap();
+        }
+
+        let mut rope = Rope::from_string("a\r\nb", SplitStrategy::LineBased);
+        rope.ensure_trailing_newline(true);
+        assert_eq!(rope.to_string(), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_line_len_from_the_tree() {
+        for strategy in [SplitStrategy::LineBased, SplitStrategy::FixedSize(3)] {
+            let rope = Rope::from_string("short\n\na much longer line\nünï", strategy);
+            let lens: Vec<_> = (0..5).map(|line| rope.line_len(line)).collect();
+            assert_eq!(lens, [Some(5), Some(0), Some(18), Some(3), None]);
+        }
+
+        // A trailing newline ends in an empty last line.
+        let rope = Rope::from_string("a\nbc\n", SplitStrategy::LineBased);
+        assert_eq!((rope.line_len(1), rope.line_len(2), rope.line_len(3)), (Some(2), Some(0), None));
+        assert_eq!(Rope::new(SplitStrategy::LineBased).line_len(0), Some(0));
+    }
+}
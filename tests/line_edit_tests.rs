@@ -0,0 +1,132 @@
+use rawdeo::rope::{Rope, SplitStrategy};
+
+fn rope(text: &str) -> Rope {
+    Rope::from_string(text, SplitStrategy::LineBased)
+}
+
+// Every line sits in a leaf of its own, terminator included.
+fn assert_one_leaf_per_line(rope: &Rope) {
+    rope.validate().unwrap();
+    let leaves = rope.leaf_texts();
+    assert!(leaves.iter().all(|leaf| leaf.matches('\n').count() <= 1), "{:?}", leaves);
+}
+
+// Undoes once and checks a single undo brought back `before`.
+fn assert_one_undo(rope: &mut Rope, before: &str) {
+    assert!(rope.undo().is_some());
+    assert_eq!(rope.to_string(), before);
+    assert!(!rope.can_undo());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_line() {
+        let mut r = rope("one\nthree");
+        r.insert_line(1, "two");
+        assert_eq!(r.to_string(), "one\ntwo\nthree");
+        assert_one_leaf_per_line(&r);
+        assert_one_undo(&mut r, "one\nthree");
+
+        r.insert_line(0, "zero");
+        assert_eq!(r.to_string(), "zero\none\nthree");
+    }
+
+    #[test]
+    fn test_insert_line_after_the_last_line() {
+        let mut r = rope("one");
+        r.insert_line(1, "two");
+        assert_eq!(r.to_string(), "one\ntwo");
+        r.insert_line(99, "three");
+        assert_eq!(r.to_string(), "one\ntwo\nthree");
+        assert_one_leaf_per_line(&r);
+
+        // The empty line after a trailing newline is a line like any other.
+        let mut r = rope("one\n");
+        r.insert_line(1, "two");
+        assert_eq!(r.to_string(), "one\ntwo\n");
+
+        let mut r = rope("");
+        r.insert_line(0, "");
+        assert_eq!(r.to_string(), "\n");
+        assert_one_undo(&mut r, "");
+    }
+
+    #[test]
+    fn test_inserted_empty_line_is_not_merged_with_typing() {
+        let mut r = rope("ab");
+        r.insert_char(2, 'c');
+        r.insert_line(1, "");
+        assert_eq!(r.to_string(), "abc\n");
+        r.undo();
+        assert_eq!(r.to_string(), "abc");
+    }
+
+    #[test]
+    fn test_remove_line() {
+        let mut r = rope("one\ntwo\nthree");
+        assert_eq!(r.remove_line(1), "two");
+        assert_eq!(r.to_string(), "one\nthree");
+        assert_one_leaf_per_line(&r);
+        assert_one_undo(&mut r, "one\ntwo\nthree");
+
+        assert_eq!(r.remove_line(2), "three");
+        assert_eq!(r.to_string(), "one\ntwo");
+        assert_eq!(r.remove_line(0), "one");
+        assert_eq!(r.to_string(), "two");
+        assert_eq!(r.remove_line(5), "");
+        assert_eq!(r.to_string(), "two");
+    }
+
+    #[test]
+    fn test_removing_the_only_line_leaves_an_empty_document() {
+        let mut r = rope("only");
+        assert_eq!(r.remove_line(0), "only");
+        assert!(r.is_empty());
+        assert_eq!(r.lines(), 1);
+        r.validate().unwrap();
+        assert_one_undo(&mut r, "only");
+
+        let mut r = rope("a\n");
+        assert_eq!(r.remove_line(1), "");
+        assert_eq!(r.to_string(), "a");
+        assert_eq!(r.remove_line(0), "a");
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn test_swap_lines() {
+        let mut r = rope("one\ntwo\nthree\n");
+        r.swap_lines(2, 0);
+        assert_eq!(r.to_string(), "three\ntwo\none\n");
+        assert_one_leaf_per_line(&r);
+        assert_one_undo(&mut r, "one\ntwo\nthree\n");
+
+        let mut r = rope("one\ntwo");
+        r.swap_lines(0, 1);
+        assert_eq!(r.to_string(), "two\none");
+        r.swap_lines(0, 1);
+        assert_eq!(r.to_string(), "one\ntwo");
+    }
+
+    #[test]
+    fn test_swapping_a_line_with_itself_or_past_the_end_does_nothing() {
+        let mut r = rope("one\ntwo");
+        r.swap_lines(1, 1);
+        r.swap_lines(0, 2);
+        assert_eq!(r.to_string(), "one\ntwo");
+        assert!(!r.can_undo());
+    }
+
+    #[test]
+    fn test_line_edits_on_fixed_size_leaves() {
+        let mut r = Rope::from_string("one\ntwo\nthree", SplitStrategy::FixedSize(2));
+        r.swap_lines(0, 2);
+        r.insert_line(1, "x");
+        assert_eq!(r.remove_line(3), "one");
+        assert_eq!(r.to_string(), "three\nx\ntwo");
+        r.validate().unwrap();
+    }
+}
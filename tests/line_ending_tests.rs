@@ -0,0 +1,98 @@
+use rawdeo::rope::{LineEnding, Rope, SplitStrategy};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_line_ending() {
+        assert_eq!(LineEnding::detect("Hello\r\nWorld!"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("Hello\nWorld!"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("Hello world!"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_crlf_is_normalized_internally() {
+        let rope = Rope::from_string("Hello\r\nWorld!", SplitStrategy::LineBased);
+
+        assert_eq!(rope.line_ending(), LineEnding::CrLf);
+        assert_eq!(rope.len(), 12);
+        assert!(!rope.debug_string().contains('\r'));
+    }
+
+    #[test]
+    fn test_crlf_round_trip() {
+        let text = "Hello\r\nRust!\r\nWorld!\r\n";
+        let rope = Rope::from_string(text, SplitStrategy::LineBased);
+
+        assert_eq!(rope.to_string(), text);
+    }
+
+    #[test]
+    fn test_crlf_preserved_after_edit() {
+        let mut rope = Rope::from_string("Hello\r\nWorld!", SplitStrategy::LineBased);
+        rope.insert(6, "Rust!\r\n");
+
+        assert_eq!(rope.to_string(), "Hello\r\nRust!\r\nWorld!");
+    }
+
+    #[test]
+    fn test_lf_round_trip() {
+        let text = "Hello\nWorld!\n";
+        let rope = Rope::from_string(text, SplitStrategy::LineBased);
+
+        assert_eq!(rope.line_ending(), LineEnding::Lf);
+        assert_eq!(rope.to_string(), text);
+    }
+
+    #[test]
+    fn test_crlf_lines_have_no_carriage_returns() {
+        let rope = Rope::from_string("one\r\ntwo\r\nthree\r\n", SplitStrategy::LineBased);
+
+        assert_eq!(rope.lines(), 4);
+        assert_eq!(rope.get_line(0), Some("one".to_string()));
+        assert_eq!(rope.get_line(1), Some("two".to_string()));
+        assert_eq!(rope.get_line(3), Some(String::new()));
+        assert_eq!(rope.get_line(4), None);
+        assert_eq!(rope.len(), 14);
+    }
+
+    #[test]
+    fn test_lf_lines() {
+        let rope = Rope::from_string("one\n\nthree", SplitStrategy::LineBased);
+
+        assert_eq!(rope.lines(), 3);
+        assert_eq!(rope.get_line(1), Some(String::new()));
+        assert_eq!(rope.get_line(2), Some("three".to_string()));
+        assert_eq!(rope.line_to_char(2), Some(5));
+    }
+
+    #[test]
+    fn test_mixed_endings_are_unified_on_output() {
+        let rope = Rope::from_string("one\r\ntwo\nthree\r\n", SplitStrategy::LineBased);
+
+        assert_eq!(rope.line_ending(), LineEnding::CrLf);
+        assert_eq!(rope.get_line(1), Some("two".to_string()));
+        assert_eq!(rope.to_string(), "one\r\ntwo\r\nthree\r\n");
+    }
+
+    #[test]
+    fn test_get_line_spanning_leaves() {
+        let rope = Rope::from_string("a long line that spans\nleaves", SplitStrategy::FixedSize(5));
+
+        assert_eq!(rope.get_line(0), Some("a long line that spans".to_string()));
+        assert_eq!(rope.get_line(1), Some("leaves".to_string()));
+    }
+
+    #[test]
+    fn test_set_line_ending_is_one_undo_step() {
+        let mut rope = Rope::from_string("one\r\ntwo\r\n", SplitStrategy::LineBased);
+        rope.insert(0, "x");
+        rope.set_line_ending(LineEnding::Lf);
+        assert_eq!(rope.to_string(), "xone\ntwo\n");
+
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.line_ending(), LineEnding::CrLf);
+        assert_eq!(rope.to_string(), "xone\r\ntwo\r\n");
+    }
+}
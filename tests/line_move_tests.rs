@@ -0,0 +1,98 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::{Key, KeyPress};
+
+mod common;
+
+// A buffer already in Insert mode, where Alt+Up/Down are most used.
+fn inserting(text: &str) -> TextBuffer {
+    let mut buffer = common::buffer_with(text);
+    buffer.handle_keypress(Key::Char('i'));
+    buffer
+}
+
+fn alt_up(buffer: &mut TextBuffer, times: usize) {
+    for _ in 0..times {
+        buffer.handle_keypress(KeyPress::alt(Key::ArrowUp));
+    }
+}
+
+fn alt_down(buffer: &mut TextBuffer, times: usize) {
+    for _ in 0..times {
+        buffer.handle_keypress(KeyPress::alt(Key::ArrowDown));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alt_arrows_move_the_line_and_the_cursor() {
+        let mut buffer = inserting("one\ntwo\nthree");
+        buffer.handle_keypress(Key::End);
+        alt_down(&mut buffer, 1);
+        assert_eq!(buffer.lines(), vec!["two", "one", "three"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 1));
+
+        alt_down(&mut buffer, 1);
+        alt_up(&mut buffer, 2);
+        assert_eq!(buffer.lines(), vec!["one", "two", "three"]);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 0));
+    }
+
+    #[test]
+    fn test_moves_stop_at_the_document_ends() {
+        let mut buffer = inserting("one\ntwo");
+        alt_up(&mut buffer, 1);
+        assert_eq!(buffer.lines(), vec!["one", "two"]);
+        assert!(!buffer.is_modified());
+
+        alt_down(&mut buffer, 3);
+        assert_eq!(buffer.lines(), vec!["two", "one"]);
+        assert_eq!(buffer.cursor_y, 1);
+
+        let mut buffer = inserting("only");
+        alt_down(&mut buffer, 1);
+        alt_up(&mut buffer, 1);
+        assert_eq!(buffer.lines(), vec!["only"]);
+    }
+
+    #[test]
+    fn test_a_burst_of_moves_undoes_at_once() {
+        let mut buffer = inserting("a\nb\nc\nd");
+        alt_down(&mut buffer, 3);
+        assert_eq!(buffer.lines(), vec!["b", "c", "d", "a"]);
+        buffer.handle_keypress(Key::Ctrl('z'));
+        assert_eq!(buffer.lines(), vec!["a", "b", "c", "d"]);
+        assert_eq!(buffer.cursor_y, 0);
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_another_key_starts_a_new_burst() {
+        let mut buffer = inserting("a\nb\nc");
+        alt_down(&mut buffer, 1);
+        buffer.handle_keypress(Key::ArrowRight);
+        alt_down(&mut buffer, 1);
+        assert_eq!(buffer.lines(), vec!["b", "c", "a"]);
+
+        buffer.handle_keypress(Key::Ctrl('z'));
+        assert_eq!(buffer.lines(), vec!["b", "a", "c"]);
+        assert_eq!(buffer.cursor_y, 1);
+        buffer.handle_keypress(Key::Ctrl('z'));
+        assert_eq!(buffer.lines(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_moving_past_the_bottom_of_the_screen_scrolls() {
+        let text: Vec<String> = (0..40).map(|i| i.to_string()).collect();
+        let mut buffer = inserting(&text.join("\n"));
+        alt_down(&mut buffer, 30);
+        assert_eq!(buffer.cursor_y, 30);
+        assert_eq!(buffer.line(30), "0");
+        assert!(buffer.scroll_y <= 30 && 30 < buffer.scroll_y + buffer.screen_height);
+
+        alt_up(&mut buffer, 30);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (0, 0));
+    }
+}
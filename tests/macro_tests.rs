@@ -0,0 +1,118 @@
+use rawdeo::buffer::{Mode, TextBuffer};
+use rawdeo::input::Key;
+
+mod common;
+
+use common::{buffer_with, press};
+
+fn recorded(buffer: &TextBuffer, register: char) -> Option<Vec<Key>> {
+    buffer.macro_keys(register).map(|keys| keys.iter().map(|press| press.key).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording_stores_keys_without_the_q() {
+        let mut buffer = buffer_with("one");
+        press(&mut buffer, "qa");
+        assert!(buffer.is_recording());
+        assert_eq!(buffer.message_text(), "recording @a");
+
+        press(&mut buffer, "ix~q");
+        assert!(!buffer.is_recording());
+        assert_eq!(buffer.message_text(), "");
+        assert_eq!(recorded(&buffer, 'a'), Some(vec![Key::Char('i'), Key::Char('x'), Key::OptionSpace]));
+        assert_eq!(buffer.lines(), vec!["xone"]);
+    }
+
+    #[test]
+    fn test_replay_repeats_the_edit() {
+        let mut buffer = buffer_with("a b c d");
+        press(&mut buffer, "qcxwq");
+        assert_eq!(recorded(&buffer, 'c'), Some(vec![Key::Char('x'), Key::Char('w')]));
+        assert_eq!(buffer.lines(), vec![" b c d"]);
+
+        press(&mut buffer, "@c@c");
+        assert_eq!(buffer.lines(), vec!["   d"]);
+        assert!(matches!(buffer.mode, Mode::Normal));
+    }
+
+    #[test]
+    fn test_count_prefix_repeats_macro() {
+        let mut buffer = buffer_with("abcdefgh");
+        press(&mut buffer, "qaxq");
+        press(&mut buffer, "3@a");
+        assert_eq!(buffer.lines(), vec!["efgh"]);
+
+        press(&mut buffer, "10@a");
+        assert_eq!(buffer.lines(), vec![""]);
+    }
+
+    #[test]
+    fn test_self_calling_macro_stops() {
+        let mut buffer = buffer_with("one");
+        press(&mut buffer, "qaiz~q");
+        press(&mut buffer, "qa@aq");
+        assert_eq!(recorded(&buffer, 'a'), Some(vec![Key::Char('@'), Key::Char('a')]));
+
+        press(&mut buffer, "@a");
+        assert_eq!(buffer.message_text(), "Macro recursion too deep");
+
+        assert_eq!(buffer.lines(), vec!["zzone"]);
+
+        press(&mut buffer, "qbxq@b");
+        assert_eq!(buffer.line(0).len(), 3);
+    }
+
+    #[test]
+    fn test_replay_undoes_in_one_step() {
+        let mut buffer = buffer_with("a\nb\nc\nd");
+        press(&mut buffer, "qqix~");
+        buffer.handle_keypress(Key::ArrowDown);
+        press(&mut buffer, "iy~q");
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!(buffer.lines(), vec!["xa", "by", "c", "d"]);
+
+        press(&mut buffer, "@q");
+        assert_eq!(buffer.lines(), vec!["xa", "by", "cx", "dy"]);
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["xa", "by", "c", "d"]);
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["xa", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_replay_larger_than_the_undo_limit_undoes_in_one_step() {
+        let mut buffer = buffer_with("a\nb");
+        buffer.rope.set_undo_limit(3);
+        press(&mut buffer, "qqix~ix~q");
+        press(&mut buffer, "5@q");
+        assert_eq!(buffer.lines(), vec!["xxxxxxxxxxxxa", "b"]);
+
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["xxa", "b"]);
+        // The replay's first edit pushed the oldest x out of the history.
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["xa", "b"]);
+        assert!(!buffer.rope.can_undo());
+    }
+
+    #[test]
+    fn test_replay_of_unknown_register_does_nothing() {
+        let mut buffer = buffer_with("one");
+        press(&mut buffer, "@zq!");
+        assert_eq!(buffer.lines(), vec!["one"]);
+        assert!(!buffer.is_recording());
+    }
+
+    #[test]
+    fn test_replayed_keys_are_not_recorded_again() {
+        let mut buffer = buffer_with("abcd");
+        press(&mut buffer, "qaxq");
+        press(&mut buffer, "qb2@aq");
+        assert_eq!(recorded(&buffer, 'b'), Some(vec![Key::Char('2'), Key::Char('@'), Key::Char('a')]));
+        assert_eq!(buffer.lines(), vec!["d"]);
+    }
+}
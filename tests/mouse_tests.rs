@@ -0,0 +1,157 @@
+use rawdeo::buffer::{Mode, TextBuffer};
+use rawdeo::input::{Key, MouseEvent, MouseKind};
+
+mod common;
+
+use common::{buffer_with, hundred_lines};
+
+fn mouse(buffer: &mut TextBuffer, kind: MouseKind, column: usize, row: usize) {
+    buffer.handle_keypress(Key::Mouse(MouseEvent { kind, column, row }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_to_text_skips_gutter() {
+        let buffer = buffer_with("hello\nworld");
+        assert_eq!(buffer.screen_to_text(1, 5), Some((0, 0)));
+        assert_eq!(buffer.screen_to_text(1, 7), Some((2, 0)));
+        assert_eq!(buffer.screen_to_text(2, 1), Some((0, 1)));
+        assert_eq!(buffer.screen_to_text(0, 7), None);
+    }
+
+    #[test]
+    fn test_screen_to_text_through_tabs() {
+        let buffer = buffer_with("\tab\tc");
+        assert_eq!(buffer.screen_to_text(1, 5), Some((0, 0)));
+        assert_eq!(buffer.screen_to_text(1, 8), Some((0, 0)));
+        assert_eq!(buffer.screen_to_text(1, 9), Some((1, 0)));
+        assert_eq!(buffer.screen_to_text(1, 11), Some((3, 0)));
+        assert_eq!(buffer.screen_to_text(1, 13), Some((4, 0)));
+    }
+
+    #[test]
+    fn test_screen_to_text_clamps_past_the_text() {
+        let mut buffer = buffer_with("hello\nhi");
+        assert_eq!(buffer.screen_to_text(1, 60), Some((5, 0)));
+        assert_eq!(buffer.screen_to_text(7, 6), Some((1, 1)));
+        assert_eq!(buffer.screen_to_text(7, 60), Some((2, 1)));
+
+        buffer.execute_command("set nonumber");
+        assert_eq!(buffer.screen_to_text(1, 2), Some((1, 0)));
+    }
+
+    #[test]
+    fn test_screen_to_text_with_scroll() {
+        let mut buffer = hundred_lines();
+        buffer.scroll_y = 40;
+        assert_eq!(buffer.screen_to_text(1, 7), Some((0, 40)));
+
+        let mut buffer = buffer_with(&"abcdefghij".repeat(10));
+        buffer.scroll_x = 30;
+        assert_eq!(buffer.screen_to_text(1, 5), Some((30, 0)));
+    }
+
+    #[test]
+    fn test_screen_to_text_on_wrapped_rows() {
+        let mut buffer = buffer_with(&format!("{}\nshort", "x".repeat(40)));
+        buffer.screen_width = 20;
+        buffer.execute_command("set wrap");
+        assert_eq!(buffer.screen_to_text(1, 20), Some((15, 0)));
+        assert_eq!(buffer.screen_to_text(2, 5), Some((16, 0)));
+        assert_eq!(buffer.screen_to_text(3, 9), Some((36, 0)));
+        assert_eq!(buffer.screen_to_text(3, 20), Some((40, 0)));
+        assert_eq!(buffer.screen_to_text(4, 6), Some((1, 1)));
+
+        buffer.scroll_y = 1;
+        assert_eq!(buffer.screen_to_text(1, 6), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_screen_to_text_with_wide_gutter() {
+        let mut buffer = hundred_lines();
+        buffer.scroll_y = 95;
+        assert_eq!(buffer.screen_to_text(1, 7), Some((0, 95)));
+        assert_eq!(buffer.screen_to_text(1, 9), Some((2, 95)));
+        assert_eq!(buffer.screen_to_text(5, 12), Some((5, 99)));
+    }
+
+    #[test]
+    fn test_click_moves_cursor() {
+        let mut buffer = buffer_with("hello\nworld");
+        mouse(&mut buffer, MouseKind::Press, 8, 2);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 1));
+        mouse(&mut buffer, MouseKind::Release, 8, 2);
+        assert!(matches!(buffer.mode, Mode::Normal));
+    }
+
+    #[test]
+    fn test_drag_selects() {
+        let mut buffer = buffer_with("hello\nworld");
+        mouse(&mut buffer, MouseKind::Press, 6, 1);
+        mouse(&mut buffer, MouseKind::Drag, 7, 2);
+        assert!(matches!(buffer.mode, Mode::Visual { anchor: (1, 0) }));
+        assert_eq!(buffer.selection_range(), Some((1, 9)));
+
+        buffer.handle_keypress(Key::Char('d'));
+        assert_eq!(buffer.lines(), vec!["hld"]);
+
+        mouse(&mut buffer, MouseKind::Press, 5, 1);
+        mouse(&mut buffer, MouseKind::Drag, 6, 1);
+        mouse(&mut buffer, MouseKind::Press, 7, 1);
+        assert!(matches!(buffer.mode, Mode::Normal));
+        assert_eq!(buffer.cursor_x, 2);
+    }
+
+    #[test]
+    fn test_wheel_scrolls_without_moving_cursor() {
+        let mut buffer = hundred_lines();
+        buffer.cursor_y = 5;
+        mouse(&mut buffer, MouseKind::ScrollDown, 1, 5);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (5, 3));
+
+        mouse(&mut buffer, MouseKind::ScrollDown, 1, 5);
+        mouse(&mut buffer, MouseKind::ScrollDown, 1, 5);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (9, 9));
+
+        mouse(&mut buffer, MouseKind::ScrollUp, 1, 5);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (9, 6));
+        for _ in 0..5 {
+            mouse(&mut buffer, MouseKind::ScrollUp, 1, 5);
+        }
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (9, 0));
+    }
+
+    #[test]
+    fn test_wheel_stops_at_the_end() {
+        let mut buffer = hundred_lines();
+        for _ in 0..40 {
+            mouse(&mut buffer, MouseKind::ScrollDown, 1, 5);
+        }
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (90, 90));
+    }
+
+    #[test]
+    fn test_click_focuses_other_window() {
+        let mut buffer = hundred_lines();
+        buffer.execute_command("sp");
+        buffer.execute_command("50");
+        mouse(&mut buffer, MouseKind::Press, 9, 8);
+        assert_eq!(buffer.active_window(), 1);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 2));
+
+        mouse(&mut buffer, MouseKind::Press, 7, 1);
+        assert_eq!(buffer.active_window(), 0);
+        assert_eq!(buffer.cursor_y, 47);
+    }
+
+    #[test]
+    fn test_mouse_ignored_in_command_mode() {
+        let mut buffer = buffer_with("hello\nworld");
+        buffer.handle_keypress(Key::Char(':'));
+        mouse(&mut buffer, MouseKind::Press, 8, 2);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+    }
+}
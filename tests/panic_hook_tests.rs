@@ -0,0 +1,37 @@
+use rawdeo::terminal::TerminalGuard;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// The panic hook is global to the process, so this file holds a single test
+// and no other test can panic into it.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_hook_cleans_up_before_the_message() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (log, counter) = (Arc::clone(&events), Arc::clone(&calls));
+        let guard = TerminalGuard::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            log.lock().unwrap().push("cleanup");
+        });
+        guard.install_panic_hook();
+
+        let log = Arc::clone(&events);
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            default_hook(info);
+            log.lock().unwrap().push("message");
+        }));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let _guard = guard;
+            panic!("editor crashed");
+        }));
+        assert!(result.is_err());
+        assert_eq!(*events.lock().unwrap(), vec!["cleanup", "message", "cleanup"]);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -23,7 +23,7 @@ mod tests {
     #[test]
     fn test_delete_from_leaf() {
         let mut rope = Rope::from_string("Hello amazing world!", SplitStrategy::LineBased);
-        rope.delete(6, 14);
+        rope.remove_clamped(6, 14);
 
         let expected_output = "Leaf: \"Hello world!\"\n";
         assert_eq!(rope.debug_string(), expected_output);
@@ -62,7 +62,7 @@ mod tests {
     fn test_insert_and_delete_inside_leaf() {
         let mut rope = Rope::from_string("Hello world!", SplitStrategy::LineBased);
         rope.insert(6, "amazing ");
-        rope.delete(6, 14); // Remove "amazing "
+        rope.remove_clamped(6, 14); // Remove "amazing "
 
         let expected_output = "Leaf: \"Hello world!\"\n";
         assert_eq!(rope.debug_string(), expected_output);
@@ -73,7 +73,7 @@ mod tests {
         let mut rope = Rope::from_string("Hello world!", SplitStrategy::LineBased);
         rope.insert(6, "\nThis is Rust!\n");
 
-        let expected_output = "Internal (left_size = 7):\n  Leaf: \"Hello \"\n  Leaf: \"\nThis is Rust!\nworld!\"\n";
+        let expected_output = "Internal (left_size = 7):\n  Leaf: \"Hello \\n\"\n  Internal (left_size = 14):\n    Leaf: \"This is Rust!\\n\"\n    Leaf: \"world!\"\n";
         assert_eq!(rope.debug_string(), expected_output);
     }
 
@@ -81,18 +81,18 @@ mod tests {
     fn test_insert_and_delete_entire_leaf() {
         let mut rope = Rope::from_string("Hello\nRust!\nWorld!", SplitStrategy::LineBased);
         rope.insert(6, "\nAmazing ");
-        rope.delete(6, 15); // Remove "Amazing "
+        rope.remove_clamped(6, 15); // Remove "Amazing "
 
-        let expected_output = "Internal (left_size = 6):\n  Leaf: \"Hello\n\"\n  Leaf: \"Rust!\nWorld!\"\n";
+        let expected_output = "Internal (left_size = 6):\n  Leaf: \"Hello\\n\"\n  Internal (left_size = 6):\n    Leaf: \"Rust!\\n\"\n    Leaf: \"World!\"\n";
         assert_eq!(rope.debug_string(), expected_output);
     }
 
     #[test]
     fn test_insert_fixed_size_splitting() {
         let mut rope = Rope::from_string("Hello world!", SplitStrategy::FixedSize(10));
-        rope.insert(6, " amazing"); // Causes split due to max 10 chars
+        rope.insert(6, "amazing "); // Causes split due to max 10 chars
 
-        let expected_output = "Internal (left_size = 10):\n  Leaf: \"Hello \"\n  Leaf: \"amazing world!\"\n";
+        let expected_output = "Internal (left_size = 6):\n  Leaf: \"Hello \"\n  Internal (left_size = 8):\n    Leaf: \"amazing \"\n    Leaf: \"world!\"\n";
         assert_eq!(rope.debug_string(), expected_output);
     }
 
@@ -100,9 +100,9 @@ mod tests {
     fn test_delete_across_internal_nodes() {
         let mut rope = Rope::from_string("Hello\nRust!\nWorld!", SplitStrategy::LineBased);
         rope.insert(6, "\nNew Line!");
-        rope.delete(6, 16); // Remove the newly inserted "New Line!"
+        rope.remove_clamped(6, 16); // Remove the newly inserted "New Line!"
 
-        let expected_output = "Internal (left_size = 6):\n  Leaf: \"Hello\n\"\n  Leaf: \"Rust!\nWorld!\"\n";
+        let expected_output = "Internal (left_size = 6):\n  Leaf: \"Hello\\n\"\n  Internal (left_size = 6):\n    Leaf: \"Rust!\\n\"\n    Leaf: \"World!\"\n";
         assert_eq!(rope.debug_string(), expected_output);
     }
 
@@ -110,13 +110,33 @@ mod tests {
     fn test_insert_delete_mixed_operations() {
         let mut rope = Rope::from_string("Hello, world!", SplitStrategy::LineBased);
         rope.insert(5, " wonderful");
-        rope.insert(13, "\nNew Line!\n");
-        rope.delete(5, 16); // Delete " wonderful"
+        rope.insert(23, "\nNew Line!\n");
+        rope.remove_clamped(5, 15); // Delete " wonderful"
         rope.insert(0, "Start: ");
-        rope.delete(0, 7); // Delete "Start: "
+        rope.remove_clamped(0, 7); // Delete "Start: "
 
-        let expected_output = "Internal (left_size = 13):\n  Leaf: \"Hello, world!\"\n  Leaf: \"\nNew Line!\n\"\n";
+        let expected_output = "Internal (left_size = 14):\n  Leaf: \"Hello, world!\\n\"\n  Leaf: \"New Line!\\n\"\n";
         assert_eq!(rope.debug_string(), expected_output);
     }
 
+    #[test]
+    fn test_insert_char_matches_insert() {
+        let typed = [(0, 'a'), (1, '\n'), (1, 'é'), (4, '🦀'), (2, '\n'), (6, 'z'), (0, '\n'), (8, '\n'), (3, 'q')];
+        for strategy in [SplitStrategy::LineBased, SplitStrategy::FixedSize(3)] {
+            let mut by_char = Rope::from_string("xy\nw", strategy);
+            let mut by_str = Rope::from_string("xy\nw", strategy);
+            for &(index, c) in &typed {
+                by_char.insert_char(index, c);
+                by_str.insert(index, &c.to_string());
+                assert_eq!(by_char.debug_string(), by_str.debug_string());
+                by_char.validate().unwrap();
+            }
+
+            while by_str.can_undo() {
+                assert_eq!(by_char.undo(), by_str.undo());
+                assert_eq!(by_char.to_string(), by_str.to_string());
+            }
+            assert!(!by_char.can_undo());
+        }
+    }
 }
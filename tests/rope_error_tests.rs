@@ -0,0 +1,104 @@
+use rawdeo::rope::{Rope, RopeError, SplitStrategy};
+
+fn sample() -> Rope {
+    Rope::from_string("one\ntwo\n", SplitStrategy::FixedSize(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_rejects_backwards_ranges() {
+        let mut rope = sample();
+        assert_eq!(rope.remove(5, 2), Err(RopeError::InvalidRange { start: 5, end: 2, len: 8 }));
+        assert_eq!(rope.to_string(), "one\ntwo\n");
+        assert!(!rope.can_undo());
+    }
+
+    #[test]
+    fn test_remove_rejects_ranges_past_the_end() {
+        let mut rope = sample();
+        assert_eq!(rope.remove(6, 9), Err(RopeError::InvalidRange { start: 6, end: 9, len: 8 }));
+        assert_eq!(rope.remove(9, 12), Err(RopeError::InvalidRange { start: 9, end: 12, len: 8 }));
+        assert_eq!(rope.to_string(), "one\ntwo\n");
+        assert_eq!(
+            rope.remove(6, 9).unwrap_err().to_string(),
+            "range 6..9 is not inside a text of 8 chars"
+        );
+    }
+
+    #[test]
+    fn test_remove_accepts_ranges_up_to_the_end() {
+        let mut rope = sample();
+        assert_eq!(rope.remove(3, 3), Ok(()));
+        assert!(!rope.can_undo());
+        assert_eq!(rope.remove(4, 8), Ok(()));
+        assert_eq!(rope.to_string(), "one\n");
+        rope.undo();
+        assert_eq!(rope.to_string(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_remove_clamped_stops_at_the_end() {
+        let mut rope = sample();
+        rope.remove_clamped(5, 2);
+        rope.remove_clamped(9, 12);
+        assert!(!rope.can_undo());
+        rope.remove_clamped(6, 99);
+        assert_eq!(rope.to_string(), "one\ntw");
+        rope.validate().unwrap();
+    }
+
+    #[test]
+    fn test_replace_range_past_the_end_appends_and_undoes() {
+        let mut rope = sample();
+        rope.replace_range(6, 99, "X");
+        assert_eq!(rope.to_string(), "one\ntwX");
+        rope.replace_range(20, 30, "!");
+        assert_eq!(rope.to_string(), "one\ntwX!");
+        rope.validate().unwrap();
+
+        rope.undo();
+        assert_eq!(rope.to_string(), "one\ntwX");
+        rope.undo();
+        assert_eq!(rope.to_string(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_backwards_replace_range_only_inserts() {
+        let mut rope = sample();
+        rope.replace_range(5, 2, "X");
+        assert_eq!(rope.to_string(), "one\ntXwo\n");
+        rope.undo();
+        assert_eq!(rope.to_string(), "one\ntwo\n");
+        rope.replace_range(5, 2, "");
+        assert!(!rope.can_undo());
+    }
+
+    #[test]
+    fn test_try_insert_rejects_indices_past_the_end() {
+        let mut rope = sample();
+        assert_eq!(rope.try_insert(9, "x"), Err(RopeError::IndexOutOfBounds { index: 9, len: 8 }));
+        assert!(!rope.can_undo());
+        assert_eq!(rope.try_insert(8, "three\n"), Ok(()));
+        assert_eq!(rope.to_string(), "one\ntwo\nthree\n");
+    }
+
+    #[test]
+    fn test_try_char_at() {
+        let rope = sample();
+        assert_eq!(rope.try_char_at(4), Ok('t'));
+        assert_eq!(rope.try_char_at(8), Err(RopeError::IndexOutOfBounds { index: 8, len: 8 }));
+    }
+
+    #[test]
+    fn test_try_byte_to_char_rejects_offsets_inside_a_char() {
+        let rope = Rope::from_string("a日b", SplitStrategy::LineBased);
+        assert_eq!(rope.try_byte_to_char(1), Ok(1));
+        assert_eq!(rope.try_byte_to_char(2), Err(RopeError::NotCharBoundary { byte: 2 }));
+        assert_eq!(rope.try_byte_to_char(5), Ok(3));
+        assert_eq!(rope.try_byte_to_char(6), Err(RopeError::IndexOutOfBounds { index: 6, len: 5 }));
+        assert_eq!(RopeError::NotCharBoundary { byte: 2 }.to_string(), "byte 2 is inside a char");
+    }
+}
@@ -0,0 +1,96 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+use std::path::PathBuf;
+
+mod common;
+
+use common::temp_file;
+
+fn command(buffer: &mut TextBuffer, line: &str) {
+    buffer.handle_keypress(Key::Char(':'));
+    for c in line.chars() {
+        buffer.handle_keypress(if c == ' ' { Key::Space } else { Key::Char(c) });
+    }
+    buffer.handle_keypress(Key::Enter);
+}
+
+fn open_with_fixups(name: &str, contents: &str) -> (TextBuffer, PathBuf) {
+    let path = temp_file(name, contents);
+    let mut buffer = TextBuffer::new_with_path(12, path.to_str().unwrap()).unwrap();
+    command(&mut buffer, "set trim_trailing_ws");
+    command(&mut buffer, "set final_newline");
+    (buffer, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_trims_whitespace_and_extra_newlines_in_one_undo() {
+        let (mut buffer, path) = open_with_fixups("fixup_dirty.txt", "one  \n\ttwo\t \nthree\n\n\n");
+        command(&mut buffer, "w");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\n\ttwo\nthree\n");
+        assert!(!buffer.is_modified());
+
+        buffer.handle_keypress(Key::Char('u'));
+        assert_eq!(buffer.lines(), vec!["one  ", "\ttwo\t ", "three", "", ""]);
+        assert!(buffer.is_modified());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_clean_buffer_gets_no_undo_entry() {
+        let (mut buffer, path) = open_with_fixups("fixup_clean.txt", "one\ntwo\n");
+        command(&mut buffer, "w");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        assert!(!buffer.rope.can_undo());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_fixups_are_off_by_default() {
+        let path = temp_file("fixup_off.txt", "one  \n\n");
+        let mut buffer = TextBuffer::new_with_path(12, path.to_str().unwrap()).unwrap();
+        command(&mut buffer, "w");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one  \n\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_cursor_in_trailing_whitespace_is_clamped() {
+        let (mut buffer, path) = open_with_fixups("fixup_cursor.txt", "ab    \ncd  \n");
+        buffer.handle_keypress(Key::End);
+        buffer.handle_keypress(Key::ArrowDown);
+        buffer.handle_keypress(Key::ArrowLeft);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 1));
+        command(&mut buffer, "w");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 1));
+
+        // A cursor before the whitespace stays where it is.
+        buffer.handle_keypress(Key::ArrowUp);
+        buffer.handle_keypress(Key::Home);
+        buffer.handle_keypress(Key::ArrowRight);
+        buffer.handle_keypress(Key::Char('i'));
+        buffer.handle_keypress(Key::Char('x'));
+        buffer.handle_keypress(Key::Space);
+        buffer.handle_keypress(Key::OptionSpace);
+        assert_eq!(buffer.cursor_x, 3);
+        command(&mut buffer, "w");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ax b\ncd\n");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 0));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_final_newline_empties_a_blank_buffer() {
+        let (mut buffer, path) = open_with_fixups("fixup_blank.txt", "\n\n\n");
+        buffer.handle_keypress(Key::ArrowDown);
+        buffer.handle_keypress(Key::ArrowDown);
+        command(&mut buffer, "w");
+        assert_eq!(buffer.lines(), vec![""]);
+        assert_eq!(buffer.cursor_y, 0);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "\n");
+        std::fs::remove_file(path).unwrap();
+    }
+}
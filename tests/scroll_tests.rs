@@ -0,0 +1,112 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+
+mod common;
+
+use common::hundred_lines;
+
+// 20 columns leave 16 for text behind the "N | " gutter.
+fn narrow_buffer(text: &str) -> TextBuffer {
+    let mut buffer = TextBuffer::new(8);
+    buffer.screen_width = 20;
+    buffer.set_text(text);
+    buffer
+}
+
+fn cursor_output(buffer: &mut TextBuffer) -> String {
+    let mut out = Vec::new();
+    buffer.render_to(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    out[out.rfind("\x1b[").unwrap()..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_x_follows_cursor_past_right_edge() {
+        let mut buffer = narrow_buffer("0123456789abcdefghijklmnopqrstuvwxyz\nshort");
+        buffer.cursor_x = 15;
+        buffer.scroll_x_to_cursor();
+        assert_eq!(buffer.scroll_x, 0);
+
+        buffer.handle_keypress(Key::ArrowRight);
+        assert_eq!(buffer.scroll_x, 1);
+        assert_eq!(buffer.compose_frame()[2].1, "1 | 123456789abcdefg");
+        assert_eq!(cursor_output(&mut buffer), "\x1b[1;20H");
+
+        buffer.handle_keypress(Key::End);
+        assert_eq!(buffer.scroll_x, 21);
+        assert_eq!(buffer.compose_frame()[2].1, "1 | lmnopqrstuvwxyz");
+        assert_eq!(buffer.compose_frame()[3].1, "2 | ");
+    }
+
+    #[test]
+    fn test_scroll_x_returns_when_cursor_moves_left() {
+        let mut buffer = narrow_buffer("0123456789abcdefghijklmnopqrstuvwxyz\nshort");
+        buffer.handle_keypress(Key::End);
+        buffer.handle_keypress(Key::Home);
+        assert_eq!(buffer.scroll_x, 0);
+
+        buffer.cursor_x = 30;
+        buffer.scroll_x_to_cursor();
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!((buffer.cursor_x, buffer.scroll_x), (5, 5));
+        assert_eq!(buffer.compose_frame()[3].1, "2 | ");
+        assert_eq!(cursor_output(&mut buffer), "\x1b[2;5H");
+    }
+
+    #[test]
+    fn test_scroll_x_counts_tab_columns() {
+        let mut buffer = narrow_buffer("\t\t\t\tabcdef");
+        buffer.cursor_x = 5;
+        buffer.scroll_x_to_cursor();
+        assert_eq!(buffer.scroll_x, 2);
+        assert_eq!(buffer.compose_frame()[2].1, format!("1 | {}ab", " ".repeat(14)));
+
+        buffer.cursor_x = 1;
+        buffer.scroll_x_to_cursor();
+        assert_eq!(buffer.scroll_x, 2);
+        buffer.cursor_x = 0;
+        buffer.scroll_x_to_cursor();
+        assert_eq!(buffer.scroll_x, 0);
+    }
+
+    #[test]
+    fn test_wrap_never_scrolls_sideways() {
+        let mut buffer = narrow_buffer(&"x".repeat(40));
+        buffer.handle_keypress(Key::End);
+        assert_eq!(buffer.scroll_x, 25);
+
+        buffer.execute_command("set wrap");
+        buffer.scroll_x_to_cursor();
+        assert_eq!(buffer.scroll_x, 0);
+    }
+
+    #[test]
+    fn test_scroll_to_cursor_above_view() {
+        let mut buffer = hundred_lines();
+        buffer.scroll_y = 50;
+        buffer.cursor_y = 20;
+        buffer.scroll_to_cursor();
+        assert_eq!(buffer.scroll_y, 20);
+
+        buffer.cursor_y = 25;
+        buffer.scroll_to_cursor();
+        assert_eq!(buffer.scroll_y, 20);
+    }
+
+    #[test]
+    fn test_scroll_to_cursor_below_view() {
+        let mut buffer = hundred_lines();
+        buffer.cursor_y = 10;
+        buffer.scroll_to_cursor();
+        assert_eq!(buffer.scroll_y, 1);
+
+        buffer.cursor_y = 99;
+        buffer.scroll_to_cursor();
+        assert_eq!(buffer.scroll_y, 90);
+        assert_eq!(buffer.compose_frame()[11].1, "100 | line 100");
+    }
+}
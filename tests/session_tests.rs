@@ -0,0 +1,112 @@
+#![cfg(feature = "serde")]
+
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+use rawdeo::rope::{Rope, SplitStrategy};
+use std::path::PathBuf;
+
+mod common;
+
+use common::press;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rawdeo_{}_{}", std::process::id(), name))
+}
+
+fn edited_rope() -> Rope {
+    let mut rope = Rope::from_string("first line\r\nsecond line\r\n", SplitStrategy::FixedSize(4));
+    rope.insert(0, "zero\n");
+    rope.break_undo_group();
+    rope.remove_clamped(5, 11);
+    rope.replace_range(0, 4, "ZERO");
+    rope.insert(9, "!");
+    rope
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_history_survives_a_round_trip() {
+        let mut original = edited_rope();
+        let mut restored: Rope = serde_json::from_str(&serde_json::to_string(&original).unwrap()).unwrap();
+        assert_eq!(restored.to_string(), original.to_string());
+        assert_eq!(restored.line_ending(), original.line_ending());
+        assert!(restored.is_modified());
+
+        for _ in 0..3 {
+            assert_eq!(restored.undo(), original.undo());
+            assert_eq!(restored.to_string(), original.to_string());
+        }
+        assert_eq!(restored.redo(), original.redo());
+        assert_eq!(restored.to_string(), original.to_string());
+    }
+
+    #[test]
+    fn test_loading_rebuilds_a_balanced_tree() {
+        let text: String = (0..200).map(|i| format!("{} ", i)).collect();
+        let mut rope = Rope::from_string("", SplitStrategy::FixedSize(4));
+        for chunk in text.as_bytes().chunks(4).rev() {
+            rope.insert(0, std::str::from_utf8(chunk).unwrap());
+        }
+
+        let restored: Rope = serde_json::from_str(&serde_json::to_string(&rope).unwrap()).unwrap();
+        restored.validate().unwrap();
+        assert!(restored.is_balanced());
+        assert_eq!(restored.to_string(), text);
+    }
+
+    #[test]
+    fn test_session_restores_buffer_and_cursor() {
+        let path = temp_path("session.json");
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("alpha\nbeta\ngamma");
+        buffer.filename = Some("notes.txt".to_string());
+        press(&mut buffer, "dd");
+        buffer.handle_keypress(Key::ArrowDown);
+        press(&mut buffer, "x");
+        buffer.handle_keypress(Key::End);
+        buffer.save_session(path.to_str().unwrap()).unwrap();
+
+        let mut restored = TextBuffer::new(12);
+        restored.restore_session(path.to_str().unwrap()).unwrap();
+        assert_eq!(restored.lines(), vec!["beta", "amma"]);
+        assert_eq!((restored.cursor_x, restored.cursor_y), (4, 1));
+        assert_eq!(restored.filename.as_deref(), Some("notes.txt"));
+        assert!(restored.is_modified());
+
+        press(&mut restored, "uu");
+        assert_eq!(restored.lines(), vec!["alpha", "beta", "gamma"]);
+        assert!(!restored.is_modified());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_file_name(format!(".rawdeo_{}_session.json.history", std::process::id()))).unwrap();
+    }
+
+    #[test]
+    fn test_command_history_is_kept_beside_the_session() {
+        let path = temp_path("history_session.json");
+        let mut buffer = TextBuffer::new(12);
+        buffer.command_history.push("set nowrap");
+        buffer.command_history.push("%s/a/b/g");
+        buffer.save_session(path.to_str().unwrap()).unwrap();
+
+        let history_file = path.with_file_name(format!(".rawdeo_{}_history_session.json.history", std::process::id()));
+        let mut restored = TextBuffer::new(12);
+        restored.restore_session(path.to_str().unwrap()).unwrap();
+        assert_eq!(restored.command_history.entries(), ["set nowrap", "%s/a/b/g"]);
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(history_file).unwrap();
+    }
+
+    #[test]
+    fn test_bad_session_leaves_buffer_alone() {
+        let path = temp_path("bad_session.json");
+        std::fs::write(&path, "{ not json").unwrap();
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("kept");
+        assert!(buffer.restore_session(path.to_str().unwrap()).is_err());
+        assert_eq!(buffer.lines(), vec!["kept"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use rawdeo::rope::{LineEnding, Rope, SplitStrategy};
+
+fn sample() -> String {
+    (0..40).map(|i| format!("line number {}\n", i)).collect()
+}
+
+// Runs 100 mixed edits spread over the whole text.
+fn edit(rope: &mut Rope) {
+    for i in 0..100 {
+        let at = (i * 37) % rope.len().max(1);
+        match i % 4 {
+            0 => rope.insert(at, "inserted\n"),
+            1 => rope.remove_clamped(at, (at + 5).min(rope.len())),
+            2 => rope.replace_range(at, (at + 3).min(rope.len()), "xyz"),
+            _ => {
+                rope.undo();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_survives_edits() {
+        for strategy in [SplitStrategy::LineBased, SplitStrategy::FixedSize(8)] {
+            let text = sample();
+            let mut rope = Rope::from_string(&text, strategy);
+            let snapshot = rope.snapshot();
+
+            edit(&mut rope);
+            assert_ne!(rope.to_string(), text);
+            assert!(rope.validate().is_ok());
+
+            assert_eq!(snapshot.to_string(), text);
+            assert_eq!(snapshot.len(), text.chars().count());
+            assert_eq!(snapshot.lines(), 41);
+            assert_eq!(snapshot.line(7).as_deref(), Some("line number 7"));
+            assert_eq!(snapshot.text_range(0, 6), "line n");
+            assert_eq!(snapshot.chars().collect::<String>(), text);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_survives_split_and_append() {
+        let text = sample();
+        let mut rope = Rope::from_string(&text, SplitStrategy::FixedSize(16));
+        let snapshot = rope.snapshot();
+
+        let tail = rope.split_at(100);
+        rope.append(Rope::from_string("end", SplitStrategy::FixedSize(16)));
+        rope.rebalance();
+        assert_eq!(rope.len(), 103);
+        assert_eq!(tail.len(), text.chars().count() - 100);
+        assert_eq!(snapshot.to_string(), text);
+    }
+
+    #[test]
+    fn test_snapshots_taken_later_see_later_text() {
+        let mut rope = Rope::from_string("one\ntwo\n", SplitStrategy::LineBased);
+        let first = rope.snapshot();
+        rope.insert(4, "middle\n");
+        let second = rope.snapshot();
+        rope.remove_clamped(0, 4);
+
+        assert_eq!(first.to_string(), "one\ntwo\n");
+        assert_eq!(second.to_string(), "one\nmiddle\ntwo\n");
+        assert_eq!(rope.to_string(), "middle\ntwo\n");
+        drop(first);
+        rope.insert(0, "zero\n");
+        assert_eq!(second.line(0).as_deref(), Some("one"));
+        assert_eq!(rope.to_string(), "zero\nmiddle\ntwo\n");
+    }
+
+    #[test]
+    fn test_snapshot_can_be_read_on_another_thread() {
+        let mut rope = Rope::from_string(&sample(), SplitStrategy::FixedSize(8));
+        let snapshot = rope.snapshot();
+        let reader = std::thread::spawn(move || snapshot.to_string());
+        edit(&mut rope);
+        assert_eq!(reader.join().unwrap(), sample());
+
+        let moved = std::thread::spawn(move || {
+            rope.insert(0, "head\n");
+            rope
+        });
+        assert!(moved.join().unwrap().to_string().starts_with("head\n"));
+    }
+
+    #[test]
+    fn test_restore_brings_back_the_snapshot_and_can_be_undone() {
+        for strategy in [SplitStrategy::LineBased, SplitStrategy::FixedSize(8)] {
+            let mut rope = Rope::from_string(&sample(), strategy);
+            rope.insert(0, "first\n");
+            let snapshot = rope.snapshot();
+            let saved = snapshot.to_string();
+
+            edit(&mut rope);
+            let edited = rope.to_string();
+            assert_ne!(edited, saved);
+
+            rope.restore(&snapshot).unwrap();
+            assert_eq!(rope.to_string(), saved);
+            assert!(rope.validate().is_ok());
+            rope.restore(&snapshot).unwrap();
+            assert_eq!(rope.to_string(), saved);
+
+            rope.undo();
+            assert_eq!(rope.to_string(), edited);
+            rope.redo();
+            assert_eq!(rope.to_string(), saved);
+            rope.insert(0, "again\n");
+            assert_eq!(snapshot.to_string(), saved);
+        }
+    }
+
+    #[test]
+    fn test_restore_brings_back_the_line_ending_in_the_same_undo() {
+        let mut rope = Rope::from_string("one\r\ntwo\r\n", SplitStrategy::LineBased);
+        let snapshot = rope.snapshot();
+        rope.set_line_ending(LineEnding::Lf);
+        rope.insert(0, "zero\n");
+
+        rope.restore(&snapshot).unwrap();
+        assert_eq!(rope.line_ending(), LineEnding::CrLf);
+        assert_eq!(rope.to_string(), "one\r\ntwo\r\n");
+        rope.undo();
+        assert_eq!(rope.line_ending(), LineEnding::Lf);
+        assert_eq!(rope.to_string(), "zero\none\ntwo\n");
+
+        // Only the ending differs, so that's all the restore does.
+        rope.undo();
+        let lf = rope.snapshot();
+        rope.set_line_ending(LineEnding::CrLf);
+        rope.restore(&lf).unwrap();
+        assert_eq!(rope.to_string(), "one\ntwo\n");
+        rope.undo();
+        assert_eq!(rope.to_string(), "one\r\ntwo\r\n");
+    }
+}
@@ -27,16 +27,16 @@ mod tests {
         let mut rope = Rope::from_string("Hello world!", SplitStrategy::LineBased);
         rope.insert(6, "\nThis is Rust!\n");
 
-        let expected_output = "Internal (left_size = 7):\n  Leaf: \"Hello \"\n  Leaf: \"\nThis is Rust!\nworld!\"\n";
+        let expected_output = "Internal (left_size = 7):\n  Leaf: \"Hello \\n\"\n  Internal (left_size = 14):\n    Leaf: \"This is Rust!\\n\"\n    Leaf: \"world!\"\n";
         assert_eq!(rope.debug_string(), expected_output);
     }
 
     #[test]
     fn test_insert_fixed_size_creates_internal_node() {
         let mut rope = Rope::from_string("Hello world!", SplitStrategy::FixedSize(10));
-        rope.insert(6, " amazing");
+        rope.insert(6, "amazing ");
 
-        let expected_output = "Internal (left_size = 10):\n  Leaf: \"Hello \"\n  Leaf: \"amazing world!\"\n";
+        let expected_output = "Internal (left_size = 6):\n  Leaf: \"Hello \"\n  Internal (left_size = 8):\n    Leaf: \"amazing \"\n    Leaf: \"world!\"\n";
         assert_eq!(rope.debug_string(), expected_output);
     }
 
@@ -44,9 +44,134 @@ mod tests {
     fn test_delete_after_split() {
         let mut rope = Rope::from_string("Hello\nRust!\nWorld!", SplitStrategy::LineBased);
         rope.insert(6, "\nNew Line!");
-        rope.delete(6, 16);
+        rope.remove_clamped(6, 16);
 
-        let expected_output = "Internal (left_size = 6):\n  Leaf: \"Hello\n\"\n  Leaf: \"Rust!\nWorld!\"\n";
+        let expected_output = "Internal (left_size = 6):\n  Leaf: \"Hello\\n\"\n  Internal (left_size = 6):\n    Leaf: \"Rust!\\n\"\n    Leaf: \"World!\"\n";
         assert_eq!(rope.debug_string(), expected_output);
     }
+
+    #[test]
+    fn test_fixed_size_chunks_long_single_line() {
+        let json: String = (0..200).map(|i| format!("{{\"k{}\":{}}},", i, i)).collect();
+        let mut rope = Rope::from_string(&json, SplitStrategy::FixedSize(64));
+        rope.insert(500, &"x".repeat(300));
+        rope.remove_clamped(10, 40);
+
+        let leaves = rope.leaf_texts();
+        assert!(leaves.len() > 1);
+        assert!(leaves.iter().all(|leaf| leaf.chars().count() <= 64));
+        assert_eq!(rope.validate(), Ok(()));
+
+        let mut expected = json.clone();
+        expected.insert_str(500, &"x".repeat(300));
+        expected.replace_range(10..40, "");
+        assert_eq!(rope.to_string(), expected);
+    }
+
+    #[test]
+    fn test_line_based_gives_each_line_a_leaf() {
+        let rope = Rope::from_string("one\ntwo\nthree\nfour", SplitStrategy::LineBased);
+        assert_eq!(rope.leaf_texts(), vec!["one\n", "two\n", "three\n", "four"]);
+        assert_eq!(rope.to_string(), "one\ntwo\nthree\nfour");
+    }
+
+    #[test]
+    fn test_split_at_ends() {
+        let mut rope = Rope::from_string("one\ntwo\n", SplitStrategy::LineBased);
+        let right = rope.split_at(0);
+        assert!(rope.is_empty());
+        assert_eq!(right.to_string(), "one\ntwo\n");
+
+        let mut rope = right;
+        let right = rope.split_at(rope.len());
+        assert!(right.is_empty());
+        assert_eq!(rope.to_string(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_split_at_multibyte_char() {
+        let mut rope = Rope::from_string("héllo wörld", SplitStrategy::LineBased);
+        let right = rope.split_at(2);
+
+        assert_eq!(rope.to_string(), "hé");
+        assert_eq!(right.to_string(), "llo wörld");
+        assert_eq!(right.get_char(5), Some('ö'));
+    }
+
+    #[test]
+    fn test_concat_empty_ropes() {
+        let empty = Rope::concat(Rope::new(SplitStrategy::LineBased), Rope::new(SplitStrategy::LineBased));
+        assert!(empty.is_empty());
+
+        let rope = Rope::concat(Rope::new(SplitStrategy::LineBased), Rope::from_string("x\n", SplitStrategy::LineBased));
+        assert_eq!(rope.to_string(), "x\n");
+    }
+
+    #[test]
+    fn test_split_and_append_match_string_ops() {
+        let text: String = (0..300).map(|i| format!("línea número {}\n", i)).collect();
+        let chars: Vec<char> = text.chars().collect();
+
+        for index in [1, 17, 555, 2048, chars.len() - 3] {
+            let mut rope = Rope::from_string(&text, SplitStrategy::LineBased);
+            let right = rope.split_at(index);
+
+            assert_eq!(rope.to_string(), chars[..index].iter().collect::<String>());
+            assert_eq!(right.to_string(), chars[index..].iter().collect::<String>());
+            assert_eq!(rope.len(), index);
+            assert_eq!(rope.verify_line_counts(), Ok(rope.lines() - 1));
+            assert_eq!(right.verify_line_counts(), Ok(right.lines() - 1));
+
+            rope.append(right);
+            assert_eq!(rope.to_string(), text);
+            assert!(rope.is_balanced());
+        }
+    }
+
+    #[test]
+    fn test_append_keeps_undo_history() {
+        let mut rope = Rope::from_string("abc", SplitStrategy::LineBased);
+        rope.insert(3, "d");
+        rope.append(Rope::from_string("xyz", SplitStrategy::LineBased));
+
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "abcxyz");
+    }
+
+    #[test]
+    fn test_rechunk_changes_leaves_but_not_text() {
+        let text = "Hello wonderful world!\nshort\nünïcödé wörds hére\n";
+        let mut rope = Rope::from_string(text, SplitStrategy::LineBased);
+        assert_eq!(rope.leaf_count(), 3);
+
+        rope.rechunk(SplitStrategy::FixedSize(10));
+        assert_eq!(rope.to_string(), text);
+        assert!(matches!(rope.split_strategy(), SplitStrategy::FixedSize(10)));
+        assert_eq!(rope.leaf_texts()[..2], ["Hello ", "wonderful "]);
+        assert!(rope.leaf_texts().iter().all(|leaf| leaf.chars().count() <= 10));
+        rope.validate().unwrap();
+
+        // Edits after the switch follow the new strategy.
+        rope.insert(0, "0123456789abc");
+        assert!(rope.leaf_texts().iter().all(|leaf| leaf.chars().count() <= 10));
+        rope.validate().unwrap();
+
+        rope.rechunk(SplitStrategy::LineBased);
+        assert_eq!(rope.leaf_texts(), ["0123456789abcHello wonderful world!\n", "short\n", "ünïcödé wörds hére\n"]);
+        rope.validate().unwrap();
+    }
+
+    #[test]
+    fn test_rechunk_keeps_undo_history() {
+        let mut rope = Rope::from_string("one\ntwo", SplitStrategy::LineBased);
+        rope.insert(3, " more");
+        rope.rechunk(SplitStrategy::FixedSize(3));
+        assert!(rope.is_modified());
+        rope.undo();
+        assert_eq!(rope.to_string(), "one\ntwo");
+
+        let mut empty = Rope::new(SplitStrategy::LineBased);
+        empty.rechunk(SplitStrategy::FixedSize(4));
+        assert_eq!(empty.leaf_count(), 0);
+    }
 }
@@ -0,0 +1,82 @@
+use rawdeo::buffer::{Mode, TextBuffer};
+use rawdeo::input::Key;
+use rawdeo::status::{Severity, StatusBar};
+
+fn bar() -> StatusBar<'static> {
+    StatusBar {
+        mode: "-- NORMAL --",
+        filename: Some("src/main.rs"),
+        modified: true,
+        file_format: "unix",
+        recording: None,
+        line: 3,
+        column: 5,
+        line_count: 10,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bar_puts_position_on_the_right() {
+        assert_eq!(bar().format(60), format!("-- NORMAL --  src/main.rs [+]{}[unix]  3:5  30%", " ".repeat(15)));
+
+        let unnamed = StatusBar { filename: None, modified: false, recording: Some('q'), ..bar() };
+        assert_eq!(
+            unnamed.format(60),
+            format!("-- NORMAL --  [No Name]{}recording @q  [unix]  3:5  30%", " ".repeat(7))
+        );
+    }
+
+    #[test]
+    fn test_narrow_bar_cuts_the_file_name_first() {
+        assert_eq!(bar().format(40), "-- NORMAL --  <n.rs [+] [unix]  3:5  30%");
+        assert_eq!(bar().format(30), "-- NORMAL --  src/main.rs [+] ");
+        assert_eq!(bar().format(20), "-- NORMAL --  <s [+]");
+        assert_eq!(bar().format(10), "-- NORMAL ");
+    }
+
+    #[test]
+    fn test_bar_always_fills_the_width() {
+        for width in 0..80 {
+            assert_eq!(bar().format(width).chars().count(), width);
+        }
+    }
+
+    #[test]
+    fn test_severity_colors() {
+        assert_eq!(Severity::Info.paint("ok"), "ok");
+        assert_eq!(Severity::Warning.paint("hm"), "\x1b[33mhm\x1b[0m");
+        assert_eq!(Severity::Error.paint("no"), "\x1b[31mno\x1b[0m");
+    }
+
+    #[test]
+    fn test_messages_last_until_the_next_key() {
+        let mut buffer = TextBuffer::new(12);
+        for c in ":nope\n".chars() {
+            buffer.handle_keypress(if c == '\n' { Key::Enter } else { Key::Char(c) });
+        }
+        assert_eq!(buffer.message, Some((Severity::Error, "Not an editor command: nope".to_string())));
+        assert_eq!(buffer.compose_frame()[1], (12, "\x1b[31mNot an editor command: nope\x1b[0m".to_string()));
+
+        buffer.handle_keypress(Key::Char('l'));
+        assert_eq!(buffer.message, None);
+        assert_eq!(buffer.compose_frame()[1], (12, String::new()));
+    }
+
+    #[test]
+    fn test_prompt_takes_the_bottom_line_and_the_cursor() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.notify(Severity::Info, "hello");
+        buffer.handle_keypress(Key::Char(':'));
+        buffer.handle_keypress(Key::Char('w'));
+        assert!(matches!(buffer.mode, Mode::Command));
+        assert_eq!(buffer.compose_frame()[1], (12, ":w".to_string()));
+
+        let mut out = Vec::new();
+        buffer.render_to(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("\x1b[12;3H"));
+    }
+}
@@ -0,0 +1,151 @@
+use rawdeo::rope::{Rope, SplitStrategy};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered_lines(count: usize) -> String {
+        (0..count).map(|i| format!("line {}\n", i)).collect()
+    }
+
+    #[test]
+    fn test_leaf_count_and_depth() {
+        let rope = Rope::from_string("Hello, world!", SplitStrategy::LineBased);
+        assert_eq!(rope.leaf_count(), 1);
+        assert_eq!(rope.depth(), 1);
+
+        let rope = Rope::new(SplitStrategy::LineBased);
+        assert_eq!(rope.leaf_count(), 0);
+        assert_eq!(rope.depth(), 0);
+    }
+
+    #[test]
+    fn test_large_paste_only_rebuilds_touched_leaf() {
+        let mut rope = Rope::from_string(&numbered_lines(200), SplitStrategy::LineBased);
+        let leaves_before = rope.leaf_count();
+        let paste = numbered_lines(50);
+
+        let index = rope.find("line 100\n").unwrap();
+        rope.insert(index, &paste);
+
+        let mut expected = numbered_lines(200);
+        let byte = expected.find("line 100\n").unwrap();
+        expected.insert_str(byte, &paste);
+
+        assert_eq!(rope.to_string(), expected);
+        assert_eq!(rope.lines(), 251);
+        assert!(rope.leaf_count() > leaves_before);
+        assert!(rope.leaf_count() <= leaves_before + 50 * 4);
+    }
+
+    #[test]
+    fn test_multi_line_delete_keeps_text() {
+        let mut rope = Rope::from_string(&numbered_lines(200), SplitStrategy::LineBased);
+        let start = rope.find("line 10\n").unwrap();
+        let end = rope.find("line 150\n").unwrap();
+        rope.remove_clamped(start, end);
+
+        let expected = numbered_lines(200);
+        let expected = format!(
+            "{}{}",
+            &expected[..expected.find("line 10\n").unwrap()],
+            &expected[expected.find("line 150\n").unwrap()..]
+        );
+        assert_eq!(rope.to_string(), expected);
+        assert_eq!(rope.lines(), 61);
+    }
+
+    #[test]
+    fn test_depth_stays_logarithmic_in_large_rope() {
+        let mut rope = Rope::from_string(&numbered_lines(100_000), SplitStrategy::LineBased);
+        let log_leaves = (rope.leaf_count() as f64).log2().ceil() as usize;
+        assert!(rope.depth() <= 2 * log_leaves, "depth {} for {} leaves", rope.depth(), rope.leaf_count());
+
+        let paste = numbered_lines(50);
+        for line in [10, 50_000, 99_999] {
+            let index = rope.find(&format!("\nline {}\n", line)).unwrap() + 1;
+            rope.insert(index, &paste);
+        }
+
+        assert_eq!(rope.lines(), 100_151);
+        assert!(rope.depth() <= 2 * log_leaves + 8, "depth {} after pastes", rope.depth());
+    }
+
+    #[test]
+    fn test_sequential_newline_inserts_stay_balanced() {
+        let mut rope = Rope::new(SplitStrategy::LineBased);
+        for i in 0..10_000 {
+            rope.insert(rope.len(), &format!("{}\n", i));
+        }
+
+        let log_leaves = (rope.leaf_count() as f64).log2().ceil() as usize;
+        assert!(rope.is_balanced());
+        assert!(rope.depth() <= 2 * (log_leaves + 1), "depth {} for {} leaves", rope.depth(), rope.leaf_count());
+        assert_eq!(rope.lines(), 10_001);
+        assert_eq!(rope.verify_line_counts(), Ok(10_000));
+    }
+
+    #[test]
+    fn test_rebalance_keeps_text() {
+        let mut rope = Rope::from_string("a\nb", SplitStrategy::LineBased);
+        for i in 0..50 {
+            rope.insert(rope.len(), &format!("\n{}", i));
+        }
+        let text = rope.to_string();
+
+        rope.rebalance();
+        assert_eq!(rope.to_string(), text);
+        assert!(rope.is_balanced());
+        assert_eq!(rope.depth(), (rope.leaf_count() as f64).log2().ceil() as usize + 1);
+        assert_eq!(rope.get_char(4), Some('0'));
+    }
+
+    #[test]
+    fn test_stats_describe_the_tree() {
+        let rope = Rope::from_string("one\ntwo\nthree\nfour\n", SplitStrategy::LineBased);
+        let stats = rope.stats();
+        assert_eq!((stats.depth, stats.leaf_count, stats.total_chars), (rope.depth(), 4, 19));
+        assert!(stats.bytes_allocated_estimate >= 19 + 7 * std::mem::size_of::<Rope>());
+
+        let empty = Rope::new(SplitStrategy::LineBased).stats();
+        assert_eq!((empty.depth, empty.leaf_count, empty.total_chars), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_coalescing_merges_tiny_lines() {
+        let text: String = (0..200).map(|i| format!("{}\n", i % 10)).collect();
+        let mut rope = Rope::from_string(&text, SplitStrategy::LineBased);
+        assert_eq!(rope.leaf_count(), 200);
+
+        rope.set_min_leaf_size(16);
+        rope.rebalance();
+        assert_eq!(rope.to_string(), text);
+        assert_eq!(rope.leaf_count(), 25);
+        assert!(rope.leaf_texts().iter().all(|leaf| leaf.ends_with('\n') && leaf.len() <= 16));
+        assert!(rope.is_balanced());
+        rope.validate().unwrap();
+
+        // Edits still land in the right place in a merged leaf.
+        rope.insert(3, "x");
+        rope.remove(0, 1).unwrap();
+        assert_eq!(rope.get_line(1), Some("1x".to_string()));
+        assert_eq!(rope.verify_line_counts(), Ok(200));
+        rope.validate().unwrap();
+    }
+
+    #[test]
+    fn test_coalescing_keeps_fixed_size_limits_and_large_leaves() {
+        let mut rope = Rope::from_string(&"ab ".repeat(40), SplitStrategy::FixedSize(8));
+        rope.set_min_leaf_size(64);
+        rope.rebalance();
+        assert!(rope.leaf_texts().iter().all(|leaf| leaf.chars().count() <= 8));
+        rope.validate().unwrap();
+
+        let text = format!("{}\na\nb\n", "long line ".repeat(4));
+        let mut rope = Rope::from_string(&text, SplitStrategy::LineBased);
+        rope.set_min_leaf_size(8);
+        rope.rebalance();
+        assert_eq!(rope.leaf_texts(), vec!["long line ".repeat(4) + "\n", "a\nb\n".to_string()]);
+        assert_eq!(rope.to_string(), text);
+    }
+}
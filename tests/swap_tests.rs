@@ -0,0 +1,127 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+use rawdeo::status::Severity;
+use rawdeo::swap::{swap_is_newer, swap_path, Recovery};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+mod common;
+
+use common::press;
+
+// A fresh directory per test, so swap files never meet another test's.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rawdeo_{}_{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+fn write_at(path: &Path, contents: &str, seconds: u64) {
+    fs::write(path, contents).unwrap();
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+    File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_file_sits_beside_the_file() {
+        assert_eq!(swap_path(Path::new("/home/me/notes.txt")), PathBuf::from("/home/me/.notes.txt.swp"));
+        assert_eq!(swap_path(Path::new("notes.txt")), PathBuf::from(".notes.txt.swp"));
+    }
+
+    #[test]
+    fn test_only_a_newer_swap_file_counts() {
+        let dir = temp_dir("swap_age");
+        let (file, swap) = (dir.join("a.txt"), dir.join(".a.txt.swp"));
+        write_at(&file, "saved\n", 2_000);
+        assert!(!swap_is_newer(&file, &swap));
+
+        write_at(&swap, "swapped\n", 1_000);
+        assert!(!swap_is_newer(&file, &swap));
+        write_at(&swap, "swapped\n", 3_000);
+        assert!(swap_is_newer(&file, &swap));
+
+        fs::remove_file(&file).unwrap();
+        assert!(swap_is_newer(&file, &swap));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_recovery_answers() {
+        assert_eq!(Recovery::from_key('r'), Some(Recovery::Recover));
+        assert_eq!(Recovery::from_key('R'), Some(Recovery::Recover));
+        assert_eq!(Recovery::from_key('i'), Some(Recovery::Ignore));
+        assert_eq!(Recovery::from_key('d'), Some(Recovery::Ignore));
+        assert_eq!(Recovery::from_key('x'), None);
+    }
+
+    #[test]
+    fn test_autosave_waits_for_a_pause_and_save_removes_the_swap() {
+        let dir = temp_dir("swap_autosave");
+        let file = dir.join("a.txt");
+        fs::write(&file, "one\n").unwrap();
+        let swap = swap_path(&file);
+
+        let mut buffer = TextBuffer::new_with_path(12, file.to_str().unwrap()).unwrap();
+        let start = Instant::now();
+        buffer.tick(start + Duration::from_secs(60));
+        assert!(!swap.exists());
+
+        buffer.handle_keypress(Key::End);
+        press(&mut buffer, "ix");
+        buffer.tick(start);
+        buffer.tick(start + Duration::from_secs(3));
+        buffer.finish_swap();
+        assert!(!swap.exists());
+
+        buffer.tick(start + Duration::from_secs(4));
+        buffer.finish_swap();
+        assert_eq!(fs::read_to_string(&swap).unwrap(), "onex\n");
+
+        buffer.handle_keypress(Key::OptionSpace);
+        press(&mut buffer, ":w\n");
+        assert!(!swap.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_newer_swap_file_can_be_recovered() {
+        let dir = temp_dir("swap_recover");
+        let file = dir.join("a.txt");
+        write_at(&file, "old\n", 1_000);
+        write_at(&swap_path(&file), "new\ntext\n", 2_000);
+
+        let mut buffer = TextBuffer::new_with_path(12, file.to_str().unwrap()).unwrap();
+        assert!(matches!(buffer.message, Some((Severity::Warning, _))));
+        press(&mut buffer, "x");
+        assert_eq!(buffer.lines(), vec!["old"]);
+        assert!(buffer.message_text().contains("(r)ecover"));
+
+        press(&mut buffer, "r");
+        assert_eq!(buffer.lines(), vec!["new", "text"]);
+        assert!(buffer.is_modified());
+        press(&mut buffer, "u");
+        assert_eq!(buffer.lines(), vec!["old"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_ignoring_a_swap_file_deletes_it() {
+        let dir = temp_dir("swap_ignore");
+        let file = dir.join("a.txt");
+        write_at(&file, "old\n", 1_000);
+        write_at(&swap_path(&file), "new\n", 2_000);
+
+        let mut buffer = TextBuffer::new_with_path(12, file.to_str().unwrap()).unwrap();
+        press(&mut buffer, "i");
+        assert_eq!(buffer.lines(), vec!["old"]);
+        assert!(!swap_path(&file).exists());
+        assert!(!buffer.is_modified());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
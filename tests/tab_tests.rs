@@ -0,0 +1,136 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+use rawdeo::unicode::{byte_at_column, display_width, expand_tabs};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_width_snaps_to_tab_stops() {
+        assert_eq!(display_width("a\tb\tc", 4), 9);
+        assert_eq!(display_width("a\tb\tc", 8), 17);
+        assert_eq!(display_width("abcd\t", 4), 8);
+        assert_eq!(display_width("\t\t", 4), 8);
+        assert_eq!(display_width("漢\t", 4), 4);
+    }
+
+    #[test]
+    fn test_byte_at_column_inside_tab() {
+        let line = "a\tb\tc";
+        assert_eq!(byte_at_column(line, 0, 4), 0);
+        assert_eq!(byte_at_column(line, 1, 4), 1);
+        assert_eq!(byte_at_column(line, 3, 4), 1);
+        assert_eq!(byte_at_column(line, 4, 4), 2);
+        assert_eq!(byte_at_column(line, 8, 4), 4);
+        assert_eq!(byte_at_column(line, 20, 4), line.len());
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("a\tb\tc", 4), "a   b   c");
+        assert_eq!(expand_tabs("a\tb\tc", 2), "a b c");
+        assert_eq!(expand_tabs("no tabs", 4), "no tabs");
+    }
+
+    #[test]
+    fn test_expandtab_fills_to_next_stop() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("ab");
+        buffer.cursor_x = 2;
+        buffer.handle_keypress(Key::Char('i'));
+        buffer.handle_keypress(Key::Tab);
+
+        assert_eq!(buffer.rope.to_string(), "ab  ");
+        assert_eq!(buffer.cursor_x, 4);
+    }
+
+    #[test]
+    fn test_literal_tab_and_backspace() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("ab");
+        buffer.execute_command("set noexpandtab");
+        buffer.execute_command("set tabwidth=8");
+        buffer.cursor_x = 1;
+        buffer.handle_keypress(Key::Char('i'));
+        buffer.handle_keypress(Key::Tab);
+
+        assert_eq!(buffer.rope.to_string(), "a\tb");
+        assert_eq!(buffer.compose_frame()[2].1, "1 | a       b");
+        let mut out = Vec::new();
+        buffer.render_to(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("\x1b[1;13H"));
+
+        buffer.handle_keypress(Key::Backspace);
+        assert_eq!(buffer.rope.to_string(), "ab");
+        assert_eq!(buffer.cursor_x, 1);
+    }
+
+    #[test]
+    fn test_set_tab_options() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.execute_command("set ts=2");
+        assert_eq!(buffer.tab_width, 2);
+        buffer.execute_command("set ts");
+        assert_eq!(buffer.message_text(), "tabwidth=2");
+
+        buffer.execute_command("set tabwidth=0");
+        assert_eq!(buffer.message_text(), "Invalid argument: tabwidth=0");
+        assert_eq!(buffer.tab_width, 2);
+
+        buffer.execute_command("set noet");
+        assert!(!buffer.expand_tabs);
+        buffer.execute_command("set et");
+        assert!(buffer.expand_tabs);
+    }
+
+    #[test]
+    fn test_visual_line_length_follows_tab_width() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("\tab\tc\nno tabs\n\t\t");
+
+        buffer.set_tab_width(2);
+        assert_eq!(buffer.visual_line_length(0), 7);
+        assert_eq!(buffer.visual_line_length(1), 7);
+        assert_eq!(buffer.visual_line_length(2), 4);
+
+        buffer.set_tab_width(8);
+        assert_eq!(buffer.visual_line_length(0), 17);
+        assert_eq!(buffer.visual_line_length(1), 7);
+        assert_eq!(buffer.visual_line_length(2), 16);
+
+        buffer.set_tab_width(0);
+        assert_eq!(buffer.tab_width, 1);
+    }
+
+    #[test]
+    fn test_cursor_column_tracks_tab_width() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("\tx");
+        buffer.cursor_x = 1;
+
+        for (width, column) in [(2, 7), (8, 13)] {
+            buffer.set_tab_width(width);
+            let mut out = Vec::new();
+            buffer.render_to(&mut out).unwrap();
+            assert!(String::from_utf8(out).unwrap().ends_with(&format!("\x1b[1;{}H", column)));
+            assert_eq!(buffer.cursor_x, 1);
+        }
+    }
+
+    #[test]
+    fn test_tab_content_depends_on_expandtab() {
+        let typed = |option: &str| {
+            let mut buffer = TextBuffer::new(12);
+            buffer.execute_command(option);
+            buffer.execute_command("set ts=8");
+            for key in [Key::Char('i'), Key::Tab, Key::Char('x')] {
+                buffer.handle_keypress(key);
+            }
+            (buffer.rope.to_string(), buffer.cursor_x)
+        };
+
+        assert_eq!(typed("set et"), ("        x".to_string(), 9));
+        assert_eq!(typed("set noet"), ("\tx".to_string(), 2));
+    }
+}
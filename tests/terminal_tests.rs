@@ -0,0 +1,85 @@
+use rawdeo::terminal::{disable_raw_mode_on, enable_raw_mode_on, original_termios, TerminalGuard};
+use std::ffi::CStr;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+fn counting_guard() -> (TerminalGuard, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+    let guard = TerminalGuard::new(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+    (guard, calls)
+}
+
+// The leader and follower ends of a new pseudo terminal. The follower starts
+// out in canonical mode with echo, like a real terminal.
+fn open_pty() -> (RawFd, RawFd) {
+    unsafe {
+        let leader = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(leader >= 0, "posix_openpt failed");
+        assert_eq!(libc::grantpt(leader), 0);
+        assert_eq!(libc::unlockpt(leader), 0);
+        let mut name = [0 as libc::c_char; 64];
+        assert_eq!(libc::ptsname_r(leader, name.as_mut_ptr(), name.len()), 0);
+        let follower = libc::open(CStr::from_ptr(name.as_ptr()).as_ptr(), libc::O_RDWR | libc::O_NOCTTY);
+        assert!(follower >= 0, "opening the pty failed");
+        (leader, follower)
+    }
+}
+
+fn local_flags(fd: RawFd) -> libc::tcflag_t {
+    unsafe {
+        let mut termios = std::mem::zeroed();
+        assert_eq!(libc::tcgetattr(fd, &mut termios), 0);
+        termios.c_lflag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_drop_runs_cleanup_once() {
+        let (guard, calls) = counting_guard();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        drop(guard);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_guard_cleans_up_when_unwinding() {
+        let (guard, calls) = counting_guard();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let _guard = guard;
+            panic!("editor crashed");
+        }));
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_enabling_raw_mode_twice_keeps_the_original() {
+        let (leader, fd) = open_pty();
+        let cooked = local_flags(fd);
+        assert_ne!(cooked & libc::ICANON, 0);
+
+        enable_raw_mode_on(fd).unwrap();
+        assert_eq!(local_flags(fd) & (libc::ICANON | libc::ECHO | libc::ISIG), 0);
+        enable_raw_mode_on(fd).unwrap();
+        assert_eq!(original_termios().map(|t| t.c_lflag), Some(cooked));
+
+        disable_raw_mode_on(fd);
+        assert_eq!(local_flags(fd), cooked);
+        assert!(original_termios().is_none());
+
+        disable_raw_mode_on(fd);
+        assert_eq!(local_flags(fd), cooked);
+        unsafe {
+            libc::close(fd);
+            libc::close(leader);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use rawdeo::rope::{LineEnding, Rope, SplitStrategy};
+use rawdeo::undo::{UndoAction, UndoStack};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_insert() {
+        let mut rope = Rope::from_string("Hello world!", SplitStrategy::LineBased);
+        rope.insert(6, "amazing ");
+        assert!(rope.undo().is_some());
+
+        assert_eq!(rope.to_string(), "Hello world!");
+        assert!(!rope.can_undo());
+    }
+
+    #[test]
+    fn test_insert_past_the_end_appends_and_undoes() {
+        for strategy in [SplitStrategy::LineBased, SplitStrategy::FixedSize(2)] {
+            let mut rope = Rope::from_string("ab\ncd", strategy);
+            rope.insert(99, "xyz");
+            rope.break_undo_group();
+            rope.insert_char(50, '!');
+            assert_eq!(rope.to_string(), "ab\ncdxyz!");
+
+            assert_eq!(rope.undo(), Some((1, 5)));
+            assert_eq!(rope.to_string(), "ab\ncdxyz");
+            assert_eq!(rope.undo(), Some((1, 2)));
+            assert_eq!(rope.to_string(), "ab\ncd");
+            rope.redo();
+            assert_eq!(rope.to_string(), "ab\ncdxyz");
+            rope.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_undo_delete() {
+        let mut rope = Rope::from_string("Hello\nRust!\nWorld!", SplitStrategy::LineBased);
+        rope.remove_clamped(4, 8);
+        assert_eq!(rope.to_string(), "Hellst!\nWorld!");
+
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "Hello\nRust!\nWorld!");
+    }
+
+    #[test]
+    fn test_replace_range_is_one_undo_step() {
+        let mut rope = Rope::from_string("Hello world!", SplitStrategy::LineBased);
+        rope.replace_range(6, 11, "Rust");
+        assert_eq!(rope.to_string(), "Hello Rust!");
+
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "Hello world!");
+        assert!(!rope.can_undo());
+    }
+
+    #[test]
+    fn test_replace_range_across_leaves() {
+        let mut rope = Rope::from_string("Hello\nRust!\nWorld!", SplitStrategy::LineBased);
+        rope.replace_range(3, 13, "p\nW");
+        assert_eq!(rope.to_string(), "Help\nWorld!");
+
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "Hello\nRust!\nWorld!");
+    }
+
+    #[test]
+    fn test_undo_on_empty_history() {
+        let mut rope = Rope::from_string("Hello", SplitStrategy::LineBased);
+        assert!(!rope.can_undo());
+        assert!(rope.undo().is_none());
+        assert_eq!(rope.to_string(), "Hello");
+    }
+
+    #[test]
+    fn test_typing_is_undone_as_one_step() {
+        let mut rope = Rope::from_string("Hello !", SplitStrategy::LineBased);
+        for (i, c) in "world".chars().enumerate() {
+            rope.insert(6 + i, &c.to_string());
+        }
+        assert_eq!(rope.to_string(), "Hello world!");
+
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "Hello !");
+        assert!(!rope.can_undo());
+    }
+
+    #[test]
+    fn test_break_undo_group_splits_typing() {
+        let mut rope = Rope::from_string("", SplitStrategy::LineBased);
+        rope.insert(0, "a");
+        rope.insert(1, "b");
+        rope.break_undo_group();
+        rope.insert(2, "c");
+        rope.insert(3, "d");
+
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "ab");
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "");
+    }
+
+    #[test]
+    fn test_non_adjacent_inserts_are_not_merged() {
+        let mut stack = UndoStack::new();
+        stack.push(UndoAction::Insert { index: 0, text: "a".to_string(), cursor: None });
+        stack.push(UndoAction::Insert { index: 5, text: "b".to_string(), cursor: None });
+        stack.push(UndoAction::Insert { index: 6, text: "c".to_string(), cursor: None });
+
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop(), Some(UndoAction::Insert { index: 5, text: "bc".to_string(), cursor: None }));
+    }
+
+    #[test]
+    fn test_multi_char_inserts_are_not_merged() {
+        let mut stack = UndoStack::new();
+        stack.push(UndoAction::Insert { index: 0, text: "a".to_string(), cursor: None });
+        stack.push(UndoAction::Insert { index: 1, text: "pasted".to_string(), cursor: None });
+
+        assert_eq!(stack.len(), 2);
+    }
+
+    #[test]
+    fn test_typing_after_a_paste_is_not_merged_into_it() {
+        let mut stack = UndoStack::new();
+        stack.push(UndoAction::Insert { index: 0, text: "pasted".to_string(), cursor: None });
+        stack.push(UndoAction::Insert { index: 6, text: "x".to_string(), cursor: None });
+        stack.push(UndoAction::Insert { index: 7, text: "y".to_string(), cursor: None });
+
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop(), Some(UndoAction::Insert { index: 6, text: "xy".to_string(), cursor: None }));
+    }
+
+    #[test]
+    fn test_newline_breaks_typing() {
+        let mut rope = Rope::from_string("", SplitStrategy::LineBased);
+        for (i, c) in "ab\ncd".chars().enumerate() {
+            rope.insert(i, &c.to_string());
+        }
+
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "ab\n");
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "ab");
+        assert!(rope.undo().is_some());
+        assert_eq!(rope.to_string(), "");
+    }
+
+    #[test]
+    fn test_bounded_stack_keeps_most_recent_actions() {
+        let mut stack = UndoStack::with_capacity(2);
+        stack.push(UndoAction::Delete { index: 0, text: "a".to_string(), cursor: None });
+        stack.push(UndoAction::Delete { index: 1, text: "b".to_string(), cursor: None });
+        stack.push(UndoAction::Delete { index: 2, text: "c".to_string(), cursor: None });
+
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop(), Some(UndoAction::Delete { index: 2, text: "c".to_string(), cursor: None }));
+        assert_eq!(stack.pop(), Some(UndoAction::Delete { index: 1, text: "b".to_string(), cursor: None }));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn test_undo_limit_stops_cleanly_when_exhausted() {
+        let mut rope = Rope::from_string("Hello", SplitStrategy::LineBased);
+        rope.set_undo_limit(2);
+        for word in [" big", " wide", " Rust"] {
+            rope.insert(rope.len(), word);
+        }
+        assert_eq!(rope.to_string(), "Hello big wide Rust");
+
+        assert!(rope.undo().is_some());
+        assert!(rope.undo().is_some());
+        assert!(rope.undo().is_none());
+        assert_eq!(rope.to_string(), "Hello big");
+    }
+
+    #[test]
+    fn test_lowering_undo_limit_trims_history() {
+        let mut rope = Rope::from_string("", SplitStrategy::LineBased);
+        rope.insert(0, "ab");
+        rope.insert(2, "cd");
+        rope.insert(4, "ef");
+        rope.set_undo_limit(1);
+
+        assert!(rope.undo().is_some());
+        assert!(!rope.can_undo());
+        assert_eq!(rope.to_string(), "abcd");
+    }
+
+    #[test]
+    fn test_undo_returns_cursor_before_edit() {
+        let mut rope = Rope::from_string("first\nsecond\nthird", SplitStrategy::LineBased);
+        rope.insert(9, "XY");
+        rope.break_undo_group();
+        rope.remove_clamped(15, 20);
+
+        assert_eq!(rope.to_string(), "first\nsecXYond\n");
+        assert_eq!(rope.undo(), Some((2, 0)));
+        assert_eq!(rope.undo(), Some((1, 3)));
+        assert_eq!(rope.undo(), None);
+        assert_eq!(rope.to_string(), "first\nsecond\nthird");
+    }
+
+    #[test]
+    fn test_line_col_at_line_boundaries() {
+        let rope = Rope::from_string("ab\ncd\n", SplitStrategy::LineBased);
+        assert_eq!(rope.line_col(0), (0, 0));
+        assert_eq!(rope.line_col(2), (0, 2));
+        assert_eq!(rope.line_col(3), (1, 0));
+        assert_eq!(rope.line_col(6), (2, 0));
+    }
+
+    #[test]
+    fn test_undo_back_to_save_point_is_clean() {
+        let mut rope = Rope::from_string("text", SplitStrategy::LineBased);
+        assert!(!rope.is_modified());
+
+        rope.insert(4, "!");
+        rope.break_undo_group();
+        rope.mark_saved();
+        rope.insert(5, "?");
+        assert!(rope.is_modified());
+
+        rope.undo();
+        assert!(!rope.is_modified());
+        rope.undo();
+        assert!(rope.is_modified());
+    }
+
+    #[test]
+    fn test_edit_after_undo_past_save_point_stays_dirty() {
+        let mut rope = Rope::from_string("text", SplitStrategy::LineBased);
+        rope.insert(4, "!");
+        rope.mark_saved();
+
+        rope.undo();
+        rope.insert(4, "!");
+        assert_eq!(rope.to_string(), "text!");
+        assert!(rope.is_modified());
+
+        rope.undo();
+        assert!(rope.is_modified());
+    }
+
+    #[test]
+    fn test_coalesced_typing_undoes_to_clean() {
+        let mut rope = Rope::from_string("", SplitStrategy::LineBased);
+        for (i, c) in "abc".chars().enumerate() {
+            rope.insert(i, &c.to_string());
+        }
+        assert!(rope.is_modified());
+
+        rope.undo();
+        assert!(!rope.is_modified());
+        assert!(!rope.can_undo());
+    }
+
+    #[test]
+    fn test_redo_reapplies_undone_actions() {
+        let mut rope = Rope::from_string("Hello", SplitStrategy::LineBased);
+        rope.insert(5, " world");
+        rope.break_undo_group();
+        rope.replace_range(0, 5, "Hi");
+        rope.undo();
+        rope.undo();
+        assert_eq!(rope.to_string(), "Hello");
+
+        assert_eq!(rope.redo(), Some((0, 5)));
+        assert_eq!(rope.redo(), Some((0, 0)));
+        assert_eq!(rope.to_string(), "Hi world");
+        assert_eq!(rope.redo(), None);
+    }
+
+    #[test]
+    fn test_new_edit_drops_redo() {
+        let mut rope = Rope::from_string("abc", SplitStrategy::LineBased);
+        rope.remove_clamped(0, 1);
+        rope.undo();
+        assert!(rope.can_redo());
+
+        rope.insert(3, "d");
+        assert!(!rope.can_redo());
+        assert_eq!(rope.redo(), None);
+        assert_eq!(rope.to_string(), "abcd");
+    }
+
+    #[test]
+    fn test_redo_past_save_point_is_dirty() {
+        let mut rope = Rope::from_string("abc", SplitStrategy::LineBased);
+        rope.insert(3, "d");
+        rope.undo();
+        rope.mark_saved();
+
+        rope.redo();
+        assert!(rope.is_modified());
+        rope.undo();
+        assert!(!rope.is_modified());
+        rope.set_line_ending(LineEnding::CrLf);
+        rope.undo();
+        rope.redo();
+        assert_eq!(rope.line_ending(), LineEnding::CrLf);
+    }
+}
@@ -0,0 +1,71 @@
+use rawdeo::rope::{Position, Rope, SplitStrategy};
+
+fn ropes(text: &str) -> Vec<Rope> {
+    vec![
+        Rope::from_string(text, SplitStrategy::LineBased),
+        Rope::from_string(text, SplitStrategy::FixedSize(2)),
+    ]
+}
+
+fn at(line: usize, character: usize) -> Position {
+    Position { line, character }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surrogate_pairs_count_twice() {
+        let text = "a🦀b\n日😀\n";
+        for rope in ropes(text) {
+            rope.validate().unwrap();
+            assert_eq!(rope.utf16_size(), text.encode_utf16().count());
+            let offsets: Vec<usize> = (0..=rope.len()).map(|i| rope.char_to_utf16_cu(i)).collect();
+            assert_eq!(offsets, vec![0, 1, 3, 4, 5, 6, 8, 9]);
+        }
+    }
+
+    #[test]
+    fn test_positions_round_trip() {
+        for rope in ropes("a🦀b\n日😀\n") {
+            let positions: Vec<Position> = (0..=rope.len()).map(|i| rope.char_to_position(i)).collect();
+            assert_eq!(positions, vec![at(0, 0), at(0, 1), at(0, 3), at(0, 4), at(1, 0), at(1, 1), at(1, 3), at(2, 0)]);
+            for (index, position) in positions.into_iter().enumerate() {
+                assert_eq!(rope.position_to_char(position), index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_inside_a_surrogate_pair_rounds_down() {
+        for rope in ropes("a🦀b") {
+            assert_eq!(rope.utf16_cu_to_char(0, 2), 1);
+            assert_eq!(rope.utf16_cu_to_char(0, 3), 2);
+        }
+    }
+
+    #[test]
+    fn test_positions_past_the_end_clamp() {
+        for rope in ropes("a🦀b\n日😀\nz") {
+            assert_eq!(rope.position_to_char(at(0, 99)), 3);
+            assert_eq!(rope.position_to_char(at(1, 3)), 6);
+            assert_eq!(rope.position_to_char(at(1, 4)), 6);
+            assert_eq!(rope.position_to_char(at(2, 99)), 8);
+            assert_eq!(rope.position_to_char(at(7, 0)), 8);
+            assert_eq!(rope.char_to_position(99), at(2, 1));
+        }
+    }
+
+    #[test]
+    fn test_utf16_counts_follow_edits() {
+        let mut rope = Rope::from_string("ab\ncd\n", SplitStrategy::FixedSize(2));
+        rope.insert(1, "🦀🦀");
+        rope.remove_clamped(4, 5);
+        rope.replace_range(5, 6, "😀");
+        rope.validate().unwrap();
+        assert_eq!(rope.utf16_size(), rope.to_string().encode_utf16().count());
+        assert_eq!(rope.char_to_position(6), at(0, 9));
+        assert_eq!(rope.char_to_position(7), at(1, 0));
+    }
+}
@@ -0,0 +1,70 @@
+use rawdeo::buffer::Mode;
+use rawdeo::input::Key;
+
+mod common;
+
+use common::{buffer_with, press_keys};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_range_across_lines() {
+        let mut buffer = buffer_with("one\ntwo\nthree");
+        buffer.cursor_x = 1;
+        press_keys(&mut buffer, &[Key::Char('v'), Key::ArrowDown, Key::ArrowDown, Key::ArrowRight]);
+        assert_eq!(buffer.selection_range(), Some((1, 11)));
+
+        press_keys(&mut buffer, &[Key::ArrowUp, Key::ArrowUp, Key::Home]);
+        assert_eq!(buffer.selection_range(), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_selection_is_none_outside_visual_mode() {
+        let mut buffer = buffer_with("one");
+        assert_eq!(buffer.selection_range(), None);
+
+        press_keys(&mut buffer, &[Key::Char('v'), Key::ArrowRight, Key::OptionSpace]);
+        assert!(matches!(buffer.mode, Mode::Normal));
+        assert_eq!(buffer.selection_range(), None);
+        assert_eq!(buffer.rope.to_string(), "one");
+    }
+
+    #[test]
+    fn test_yank_selection() {
+        let mut buffer = buffer_with("one\ntwo");
+        buffer.cursor_x = 1;
+        press_keys(&mut buffer, &[Key::Char('v'), Key::ArrowDown, Key::Char('y')]);
+
+        assert_eq!(buffer.register, "ne\ntw");
+        assert!(matches!(buffer.mode, Mode::Normal));
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_delete_selection_is_one_undo_step() {
+        let mut buffer = buffer_with("one\ntwo\nthree");
+        buffer.cursor_x = 2;
+        press_keys(&mut buffer, &[Key::Char('v'), Key::ArrowDown, Key::Char('d')]);
+
+        assert_eq!(buffer.rope.to_string(), "on\nthree");
+        assert_eq!(buffer.register, "e\ntwo");
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (2, 0));
+
+        press_keys(&mut buffer, &[Key::Char('u')]);
+        assert_eq!(buffer.rope.to_string(), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn test_selection_is_rendered_inverted() {
+        let mut buffer = buffer_with("a\tbc\nd");
+        buffer.cursor_x = 1;
+        press_keys(&mut buffer, &[Key::Char('v'), Key::ArrowRight]);
+
+        let frame = buffer.compose_frame();
+        assert!(frame[0].1.starts_with("-- VISUAL --  [No Name]  "));
+        assert_eq!(frame[2].1, "1 | a\x1b[7m   b\x1b[0mc");
+        assert_eq!(frame[3].1, "2 | d");
+    }
+}
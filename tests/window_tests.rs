@@ -0,0 +1,187 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+use rawdeo::layout::Layout;
+
+// A 14-row terminal leaves 12 text rows.
+fn twenty_lines() -> TextBuffer {
+    let mut buffer = TextBuffer::new(14);
+    let lines: Vec<String> = (1..=20).map(|i| format!("line {}", i)).collect();
+    buffer.set_text(&lines.join("\n"));
+    buffer
+}
+
+fn row(buffer: &TextBuffer, row: usize) -> String {
+    let frame = buffer.compose_frame();
+    frame.iter().find(|(r, _)| *r == row).map(|(_, text)| text.clone()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_single_window_takes_every_row() {
+        let layout = Layout::compute(7, 1).unwrap();
+        assert_eq!(layout.windows, vec![0..7]);
+        assert_eq!(layout.status_row(0), None);
+        assert_eq!(Layout::compute(7, 0), None);
+    }
+
+    #[test]
+    fn test_layout_odd_heights() {
+        let layout = Layout::compute(11, 2).unwrap();
+        assert_eq!(layout.windows, vec![0..5, 6..10]);
+        assert_eq!(layout.status_row(0), Some(5));
+        assert_eq!(layout.status_row(1), Some(10));
+
+        let layout = Layout::compute(13, 3).unwrap();
+        assert_eq!(layout.windows, vec![0..4, 5..8, 9..12]);
+        assert_eq!(layout.status_row(2), Some(12));
+    }
+
+    #[test]
+    fn test_layout_minimum_size_windows() {
+        assert_eq!(Layout::compute(4, 2).unwrap().windows, vec![0..1, 2..3]);
+        assert_eq!(Layout::compute(3, 2), None);
+        assert_eq!(Layout::compute(6, 3).unwrap().windows, vec![0..1, 2..3, 4..5]);
+        assert_eq!(Layout::compute(5, 3), None);
+    }
+
+    #[test]
+    fn test_split_shares_the_text() {
+        let mut buffer = twenty_lines();
+        buffer.execute_command("sp");
+        assert_eq!(buffer.window_count(), 2);
+        assert_eq!(buffer.screen_height, 5);
+        assert_eq!(row(&buffer, 1), " 1 | line 1");
+        assert_eq!(row(&buffer, 6), "* [No Name]  1/20 (5%)");
+        assert_eq!(row(&buffer, 7), " 1 | line 1");
+        assert_eq!(row(&buffer, 12), "  [No Name]  1/20 (5%)");
+
+        buffer.handle_keypress(Key::Char('x'));
+        assert_eq!(row(&buffer, 1), " 1 | ine 1");
+        assert_eq!(row(&buffer, 7), " 1 | ine 1");
+    }
+
+    #[test]
+    fn test_windows_keep_their_own_cursor_and_scroll() {
+        let mut buffer = twenty_lines();
+        buffer.execute_command("split");
+        buffer.execute_command("15");
+        assert_eq!(buffer.cursor_y, 14);
+        assert!(buffer.scroll_y + buffer.screen_height > 14);
+
+        buffer.handle_keypress(Key::Ctrl('w'));
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!(buffer.active_window(), 1);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (0, 0));
+        assert_eq!(row(&buffer, 12), "* [No Name]  1/20 (5%)");
+
+        let mut out = Vec::new();
+        buffer.render_to(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("\x1b[7;6H"));
+
+        buffer.handle_keypress(Key::Ctrl('w'));
+        buffer.handle_keypress(Key::Char('k'));
+        assert_eq!((buffer.active_window(), buffer.cursor_y), (0, 14));
+    }
+
+    #[test]
+    fn test_page_moves_use_window_height() {
+        let mut buffer = twenty_lines();
+        buffer.execute_command("sp");
+        buffer.handle_keypress(Key::PageDown);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (5, 5));
+    }
+
+    #[test]
+    fn test_close_restores_full_height() {
+        let mut buffer = twenty_lines();
+        buffer.execute_command("close");
+        assert_eq!(buffer.message_text(), "Cannot close last window");
+
+        buffer.execute_command("sp");
+        buffer.execute_command("sp");
+        assert_eq!(buffer.screen_height, 3);
+        buffer.execute_command("close");
+        buffer.execute_command("clo");
+        assert_eq!(buffer.window_count(), 1);
+        assert_eq!(buffer.screen_height, 12);
+        assert_eq!(buffer.compose_frame().len(), 14);
+    }
+
+    #[test]
+    fn test_split_needs_room() {
+        let mut buffer = TextBuffer::new(5);
+        buffer.execute_command("sp");
+        assert_eq!(buffer.message_text(), "Not enough room");
+        assert_eq!(buffer.window_count(), 1);
+    }
+
+    #[test]
+    fn test_closed_window_cursor_is_clamped() {
+        let mut buffer = twenty_lines();
+        buffer.execute_command("sp");
+        buffer.handle_keypress(Key::Ctrl('w'));
+        buffer.handle_keypress(Key::ArrowDown);
+        buffer.execute_command("20");
+        buffer.handle_keypress(Key::Ctrl('w'));
+        buffer.handle_keypress(Key::ArrowUp);
+        buffer.set_text("short");
+        buffer.handle_keypress(Key::Ctrl('w'));
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 0));
+    }
+
+    #[test]
+    fn test_resize_updates_size_and_clamps_scroll() {
+        let mut buffer = twenty_lines();
+        buffer.execute_command("20");
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (19, 8));
+
+        buffer.handle_resize(100, 18);
+        assert_eq!((buffer.screen_width, buffer.screen_height), (100, 16));
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (19, 4));
+
+        buffer.handle_resize(30, 6);
+        assert_eq!(buffer.screen_height, 4);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (19, 16));
+        assert_eq!(buffer.compose_frame().len(), 6);
+    }
+
+    #[test]
+    fn test_resize_clamps_horizontal_scroll() {
+        let mut buffer = twenty_lines();
+        buffer.set_text(&"x".repeat(100));
+        buffer.handle_keypress(Key::End);
+        assert_eq!(buffer.scroll_x, 25);
+
+        buffer.handle_resize(40, 14);
+        assert_eq!(buffer.scroll_x, 65);
+        buffer.handle_resize(200, 14);
+        assert_eq!(buffer.scroll_x, 65);
+        buffer.handle_keypress(Key::Home);
+        assert_eq!(buffer.scroll_x, 0);
+        buffer.handle_resize(20, 14);
+        buffer.handle_keypress(Key::End);
+        assert_eq!(buffer.scroll_x, 85);
+    }
+
+    #[test]
+    fn test_resize_closes_splits_that_no_longer_fit() {
+        let mut buffer = twenty_lines();
+        buffer.execute_command("sp");
+        buffer.execute_command("sp");
+        buffer.handle_keypress(Key::Ctrl('w'));
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!((buffer.window_count(), buffer.active_window()), (3, 1));
+
+        buffer.handle_resize(80, 6);
+        assert_eq!((buffer.window_count(), buffer.active_window()), (2, 1));
+        assert_eq!(buffer.screen_height, 1);
+
+        buffer.handle_resize(80, 4);
+        assert_eq!((buffer.window_count(), buffer.active_window()), (1, 0));
+        assert_eq!(buffer.screen_height, 2);
+    }
+}
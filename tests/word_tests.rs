@@ -0,0 +1,112 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+use rawdeo::rope::{Rope, SplitStrategy};
+
+// Every index `next_word_boundary` visits from the start of `text`.
+fn forward_stops(text: &str) -> Vec<usize> {
+    let rope = Rope::from_string(text, SplitStrategy::FixedSize(3));
+    let mut stops = vec![0];
+    while *stops.last().unwrap() < rope.len() {
+        stops.push(rope.next_word_boundary(*stops.last().unwrap()));
+    }
+    stops
+}
+
+fn backward_stops(text: &str) -> Vec<usize> {
+    let rope = Rope::from_string(text, SplitStrategy::FixedSize(3));
+    let mut stops = vec![rope.len()];
+    while *stops.last().unwrap() > 0 {
+        stops.push(rope.prev_word_boundary(*stops.last().unwrap()));
+    }
+    stops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_underscores_belong_to_words() {
+        assert_eq!(forward_stops("snake_case word"), vec![0, 11, 15]);
+        assert_eq!(backward_stops("snake_case word"), vec![15, 11, 0]);
+    }
+
+    #[test]
+    fn test_punctuation_runs_are_words() {
+        assert_eq!(forward_stops("a->b();"), vec![0, 1, 3, 4, 7]);
+        assert_eq!(backward_stops("a->b();"), vec![7, 4, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_consecutive_spaces_are_skipped() {
+        assert_eq!(forward_stops("one    two\t three"), vec![0, 7, 12, 17]);
+        assert_eq!(backward_stops("one    two\t three"), vec![17, 12, 7, 0]);
+    }
+
+    #[test]
+    fn test_unicode_letters() {
+        assert_eq!(forward_stops("größe straße"), vec![0, 6, 12]);
+        assert_eq!(forward_stops("e\u{301}té, ok"), vec![0, 4, 6, 8]);
+        assert_eq!(backward_stops("日本語 テキスト"), vec![8, 4, 0]);
+    }
+
+    #[test]
+    fn test_boundaries_cross_lines() {
+        assert_eq!(forward_stops("foo\nbar"), vec![0, 4, 7]);
+        assert_eq!(forward_stops("foo\n\n  bar"), vec![0, 4, 7, 10]);
+        assert_eq!(backward_stops("foo\n\n  bar"), vec![10, 7, 4, 0]);
+
+        let rope = Rope::from_string("foo  \nbar", SplitStrategy::LineBased);
+        assert_eq!(rope.next_word_boundary(3), 6);
+        assert_eq!(rope.prev_word_boundary(6), 0);
+    }
+
+    #[test]
+    fn test_next_word_end() {
+        let rope = Rope::from_string("one two\n\nthree", SplitStrategy::LineBased);
+        assert_eq!(rope.next_word_end(0), 2);
+        assert_eq!(rope.next_word_end(2), 6);
+        assert_eq!(rope.next_word_end(6), 13);
+        assert_eq!(rope.next_word_end(13), 14);
+    }
+
+    #[test]
+    fn test_dw_and_db() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("one two three\nnext");
+        buffer.cursor_x = 4;
+        for c in "dw".chars() {
+            buffer.handle_keypress(Key::Char(c));
+        }
+        assert_eq!(buffer.rope.to_string(), "one three\nnext");
+        assert_eq!(buffer.register, "two ");
+
+        buffer.handle_keypress(Key::End);
+        for c in "dbdw".chars() {
+            buffer.handle_keypress(Key::Char(c));
+        }
+        assert_eq!(buffer.rope.to_string(), "one next");
+
+        buffer.handle_keypress(Key::Char('u'));
+        buffer.handle_keypress(Key::Char('u'));
+        assert_eq!(buffer.rope.to_string(), "one three\nnext");
+    }
+
+    #[test]
+    fn test_ctrl_keys_in_insert_mode() {
+        let mut buffer = TextBuffer::new(12);
+        buffer.set_text("alpha beta\ngamma");
+        buffer.handle_keypress(Key::Char('i'));
+
+        buffer.handle_keypress(Key::End);
+        buffer.handle_keypress(Key::CtrlArrowRight);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (0, 1));
+        buffer.handle_keypress(Key::CtrlArrowLeft);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (6, 0));
+
+        buffer.handle_keypress(Key::Ctrl('h'));
+        assert_eq!(buffer.rope.to_string(), "beta\ngamma");
+        buffer.handle_keypress(Key::CtrlDelete);
+        assert_eq!(buffer.rope.to_string(), "\ngamma");
+    }
+}
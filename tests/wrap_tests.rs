@@ -0,0 +1,194 @@
+use rawdeo::buffer::TextBuffer;
+use rawdeo::input::Key;
+use rawdeo::wrap::LineWrap;
+
+fn rows(wrap: &LineWrap) -> Vec<String> {
+    (0..wrap.row_count()).map(|row| wrap.row_text(row)).collect()
+}
+
+// 20 columns leave 16 for text behind the "NN | " gutter.
+fn wrapped_buffer(text: &str) -> TextBuffer {
+    let mut buffer = TextBuffer::new(8);
+    buffer.screen_width = 20;
+    buffer.set_text(text);
+    buffer.execute_command("set wrap");
+    buffer
+}
+
+fn cursor_output(buffer: &mut TextBuffer) -> String {
+    let mut out = Vec::new();
+    buffer.render_to(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    out[out.rfind("\x1b[").unwrap()..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_splits_at_width() {
+        let wrap = LineWrap::new("abcdefghij", 4, 4);
+        assert_eq!(rows(&wrap), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap.row_range(1), 4..8);
+
+        assert_eq!(LineWrap::new("abcd", 4, 4).row_count(), 1);
+        assert_eq!(rows(&LineWrap::new("", 4, 4)), vec![""]);
+        assert_eq!(LineWrap::unwrapped(&"x".repeat(500), 4).row_count(), 1);
+    }
+
+    #[test]
+    fn test_wrap_breaks_after_whitespace() {
+        let wrap = LineWrap::new("the quick brown fox", 10, 4);
+        assert_eq!(rows(&wrap), vec!["the quick ", "brown fox"]);
+
+        let wrap = LineWrap::new("one two  three", 8, 4);
+        assert_eq!(rows(&wrap), vec!["one two ", " three"]);
+    }
+
+    #[test]
+    fn test_long_words_fall_back_to_char_breaks() {
+        let wrap = LineWrap::new("a verylongwordhere b", 8, 4);
+        assert_eq!(rows(&wrap), vec!["a ", "verylong", "wordhere", " b"]);
+        assert_eq!(wrap.row_range(1), 2..10);
+    }
+
+    #[test]
+    fn test_wide_chars_and_graphemes_move_whole() {
+        let wrap = LineWrap::new("ab漢字", 3, 4);
+        assert_eq!(rows(&wrap), vec!["ab", "漢", "字"]);
+
+        let wrap = LineWrap::new("abe\u{301}d", 3, 4);
+        assert_eq!(rows(&wrap), vec!["abe\u{301}", "d"]);
+
+        let wrap = LineWrap::new("漢字", 1, 4);
+        assert_eq!(rows(&wrap), vec!["漢", "字"]);
+    }
+
+    #[test]
+    fn test_tabs_keep_line_tab_stops() {
+        let wrap = LineWrap::new("ab\tcdefg\th", 6, 4);
+        assert_eq!(rows(&wrap), vec!["ab  ", "cdefg", "   h"]);
+        assert_eq!(wrap.position(8), (2, 0));
+        assert_eq!(wrap.position(9), (2, 3));
+
+        let wrap = LineWrap::new("abc\td", 3, 4);
+        assert_eq!(rows(&wrap), vec!["abc", " d"]);
+    }
+
+    #[test]
+    fn test_position_and_byte_at_round_trip() {
+        let line = "a\tb漢cdefgh";
+        let wrap = LineWrap::new(line, 5, 4);
+        for (x, _) in line.char_indices() {
+            let (row, column) = wrap.position(x);
+            assert_eq!(wrap.byte_at(row, column), x);
+        }
+        assert_eq!(wrap.position(line.len()), (2, 4));
+    }
+
+    #[test]
+    fn test_byte_at_past_row_end_clamps() {
+        let wrap = LineWrap::new("abcdefg", 4, 4);
+        assert_eq!(wrap.byte_at(0, 9), 3);
+        assert_eq!(wrap.byte_at(1, 9), 7);
+        assert_eq!(wrap.byte_at(5, 0), 4);
+
+        let wrap = LineWrap::new("a\tb", 8, 4);
+        assert_eq!(wrap.byte_at(0, 2), 1);
+        assert_eq!(wrap.byte_at(0, 4), 2);
+    }
+
+    #[test]
+    fn test_byte_at_steps_whole_graphemes_on_later_rows() {
+        let line = "abcde\u{301}fgh\u{301}ij";
+        let wrap = LineWrap::new(line, 4, 4);
+        let e = line.find('e').unwrap();
+        let h = line.find('h').unwrap();
+        assert_eq!(wrap.byte_at(1, 0), e);
+        assert_eq!(wrap.byte_at(1, 1), e + 3);
+        assert_eq!(wrap.byte_at(1, 3), h);
+        assert_eq!(wrap.byte_at(1, 9), h);
+        assert_eq!(wrap.byte_at(2, 9), line.len());
+    }
+
+    #[test]
+    fn test_wrapped_frame_numbers_first_row_only() {
+        let mut buffer = wrapped_buffer(&format!("{}\nshort", "x".repeat(40)));
+        let frame = buffer.compose_frame();
+        assert_eq!(frame[2].1, format!("1 | {}", "x".repeat(16)));
+        assert_eq!(frame[3].1, format!("    {}", "x".repeat(16)));
+        assert_eq!(frame[4].1, format!("    {}", "x".repeat(8)));
+        assert_eq!(frame[5].1, "2 | short");
+
+        buffer.execute_command("set nowrap");
+        assert_eq!(buffer.compose_frame()[3].1, "2 | short");
+    }
+
+    #[test]
+    fn test_up_down_move_by_screen_row() {
+        let mut buffer = wrapped_buffer(&format!("{}\nshort", "x".repeat(40)));
+        buffer.cursor_x = 3;
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (19, 0));
+        assert_eq!(cursor_output(&mut buffer), "\x1b[2;8H");
+
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (35, 0));
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 1));
+        assert_eq!(cursor_output(&mut buffer), "\x1b[4;8H");
+
+        buffer.handle_keypress(Key::ArrowUp);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (35, 0));
+    }
+
+    #[test]
+    fn test_g_arrow_moves_by_logical_line() {
+        let mut buffer = wrapped_buffer(&format!("{}\nshort", "x".repeat(40)));
+        buffer.cursor_x = 3;
+        buffer.handle_keypress(Key::Char('g'));
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 1));
+
+        buffer.handle_keypress(Key::Char('g'));
+        buffer.handle_keypress(Key::ArrowUp);
+        assert_eq!((buffer.cursor_x, buffer.cursor_y), (3, 0));
+    }
+
+    #[test]
+    fn test_scroll_counts_wrapped_rows() {
+        let long = "y".repeat(40);
+        let text = [long.as_str(); 4].join("\n");
+        let mut buffer = wrapped_buffer(&text);
+        assert_eq!(buffer.screen_height, 6);
+
+        buffer.handle_keypress(Key::Char('g'));
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!(buffer.scroll_y, 0);
+        buffer.handle_keypress(Key::ArrowDown);
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (1, 0));
+
+        buffer.handle_keypress(Key::ArrowDown);
+        assert_eq!((buffer.cursor_y, buffer.scroll_y), (2, 1));
+        assert_eq!(cursor_output(&mut buffer), "\x1b[4;5H");
+
+        buffer.execute_command("4");
+        buffer.handle_keypress(Key::End);
+        let frame = buffer.compose_frame();
+        assert!(frame[7].1.ends_with(&"y".repeat(8)));
+    }
+
+    #[test]
+    fn test_selection_spans_wrapped_rows() {
+        let mut buffer = wrapped_buffer(&format!("{}\nnext", "abcd".repeat(5)));
+        buffer.cursor_x = 14;
+        buffer.handle_keypress(Key::Char('v'));
+        buffer.handle_keypress(Key::End);
+        let frame = buffer.compose_frame();
+        assert_eq!(frame[2].1, "1 | abcdabcdabcdab\x1b[7mcd\x1b[0m");
+        assert_eq!(frame[3].1, "    \x1b[7mabcd \x1b[0m");
+        assert_eq!(frame[4].1, "2 | next");
+    }
+}